@@ -15,7 +15,9 @@
 #![no_main]
 
 use risc0_zkvm::guest::env;
-use zeth_lib::{block_builder::EthereumBlockBuilder, consts::ETH_MAINNET_CHAIN_SPEC};
+use zeth_lib::{
+    block_builder::EthereumBlockBuilder, consts::ETH_MAINNET_CHAIN_SPEC, output::BlockOutput,
+};
 
 risc0_zkvm::guest::entry!(main);
 
@@ -23,10 +25,10 @@ pub fn main() {
     // Read the input previous block and transaction data
     let input = env::read();
     // Build the resulting block
-    let output = EthereumBlockBuilder::build_from(&ETH_MAINNET_CHAIN_SPEC, input)
+    let header = EthereumBlockBuilder::build_from(&ETH_MAINNET_CHAIN_SPEC, input)
         .expect("Failed to build the resulting block");
-    // Output the resulting block's hash to the journal
-    env::commit(&output.hash());
+    // Output the summary of the resulting block to the journal
+    env::commit(&BlockOutput::new(&ETH_MAINNET_CHAIN_SPEC, &header));
     // Leak memory, save cycles
-    core::mem::forget(output);
+    core::mem::forget(header);
 }
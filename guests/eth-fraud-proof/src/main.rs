@@ -0,0 +1,43 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use risc0_zkvm::guest::env;
+use zeth_lib::{
+    block_builder::EthereumStrategyBundle, consts::ETH_MAINNET_CHAIN_SPEC,
+    fraud_proof::verify_claimed_block, output::FraudProofOutput,
+};
+
+risc0_zkvm::guest::entry!(main);
+
+pub fn main() {
+    // Read the claimed header of the block
+    let claimed_header = env::read();
+    // Read the input previous block and transaction data
+    let input = env::read();
+    // Judge the validity of the claimed block
+    let verdict = verify_claimed_block::<EthereumStrategyBundle>(
+        &ETH_MAINNET_CHAIN_SPEC,
+        &claimed_header,
+        input,
+    )
+    .expect("Failed to verify the claimed block");
+    // Output the verdict on the claimed block to the journal
+    env::commit(&FraudProofOutput::new(
+        &ETH_MAINNET_CHAIN_SPEC,
+        &claimed_header,
+        verdict,
+    ));
+}
@@ -15,18 +15,22 @@
 #![no_main]
 
 use risc0_zkvm::guest::env;
-use zeth_lib::{block_builder::OptimismBlockBuilder, consts::OP_MAINNET_CHAIN_SPEC};
+use zeth_lib::{
+    block_builder::OptimismBlockBuilder, consts::ChainSpec, output::OptimismBlockOutput,
+};
 
 risc0_zkvm::guest::entry!(main);
 
 pub fn main() {
+    // Read the specification of the OP-stack chain
+    let chain_spec: ChainSpec = env::read();
     // Read the input previous block and transaction data
     let input = env::read();
-    // Build the resulting block
-    let output = OptimismBlockBuilder::build_from(&OP_MAINNET_CHAIN_SPEC, input)
+    // Build the resulting block and compute its output root
+    let (header, output_root) = OptimismBlockBuilder::build_with_output_root(&chain_spec, input)
         .expect("Failed to build the resulting block");
-    // Output the resulting block's hash to the journal
-    env::commit(&output.hash());
+    // Output the summary of the resulting block and its output root to the journal
+    env::commit(&OptimismBlockOutput::new(&chain_spec, &header, output_root));
     // Leak memory, save cycles
-    core::mem::forget(output);
+    core::mem::forget(header);
 }
@@ -0,0 +1,47 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use risc0_zkvm::guest::env;
+use zeth_lib::{
+    block_builder::OptimismBlockBuilder, consts::ChainSpec, input::MultiBlockInput,
+    output::MultiBlockOutput,
+};
+
+risc0_zkvm::guest::entry!(main);
+
+pub fn main() {
+    // Read the specification of the OP-stack chain
+    let chain_spec: ChainSpec = env::read();
+    // Read the input of the consecutive blocks
+    let input: MultiBlockInput<_> = env::read();
+    let parent_hash = input
+        .blocks
+        .first()
+        .expect("Missing block input")
+        .parent_header
+        .hash();
+    // Build the resulting blocks
+    let headers = OptimismBlockBuilder::build_multiple_from(&chain_spec, input)
+        .expect("Failed to build the resulting blocks");
+    // Output the first parent and the final block to the journal
+    env::commit(&MultiBlockOutput::new(
+        &chain_spec,
+        parent_hash,
+        headers.last().unwrap(),
+    ));
+    // Leak memory, save cycles
+    core::mem::forget(headers);
+}
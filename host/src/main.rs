@@ -14,13 +14,14 @@
 
 extern crate core;
 
-use std::{fmt::Debug, time::Instant};
+use std::{fmt::Debug, iter::zip, time::Instant};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bonsai_sdk::alpha as bonsai_sdk;
 use clap::Parser;
-use ethers_core::types::Transaction as EthersTransaction;
-use log::{error, info};
+use ethers_core::types::{Transaction as EthersTransaction, Withdrawal as EthersWithdrawal};
+use log::{error, info, warn};
+use revm::primitives::SpecId;
 use risc0_zkvm::{
     serde::{from_slice, to_vec},
     Executor, ExecutorEnv, FileSegmentRef, MemoryImage, Program, Receipt,
@@ -28,18 +29,42 @@ use risc0_zkvm::{
 use serde::{Deserialize, Serialize};
 use tempfile::tempdir;
 use zeth_guests::{
-    ETH_BLOCK_ELF, ETH_BLOCK_ID, ETH_BLOCK_PATH, OP_BLOCK_ELF, OP_BLOCK_ID, OP_BLOCK_PATH,
+    ETH_BLOCK_ELF, ETH_BLOCK_ID, ETH_BLOCK_PATH, ETH_CUSTOM_BLOCK_ELF, ETH_CUSTOM_BLOCK_ID,
+    ETH_CUSTOM_BLOCK_PATH, ETH_FRAUD_PROOF_ELF, ETH_FRAUD_PROOF_ID, ETH_FRAUD_PROOF_PATH,
+    ETH_MULTI_BLOCK_ELF, ETH_MULTI_BLOCK_ID, ETH_MULTI_BLOCK_PATH, HOLESKY_BLOCK_ELF,
+    HOLESKY_BLOCK_ID, HOLESKY_BLOCK_PATH,
+    OP_BLOCK_ELF, OP_BLOCK_ID, OP_BLOCK_PATH, OP_FRAUD_PROOF_ELF, OP_FRAUD_PROOF_ID,
+    OP_FRAUD_PROOF_PATH, OP_MULTI_BLOCK_ELF, OP_MULTI_BLOCK_ID, OP_MULTI_BLOCK_PATH,
+    SEPOLIA_BLOCK_ELF, SEPOLIA_BLOCK_ID, SEPOLIA_BLOCK_PATH,
 };
 use zeth_lib::{
     block_builder::{
-        BlockBuilder, EthereumStrategyBundle, NetworkStrategyBundle, OptimismStrategyBundle,
+        BlockBuildError, BlockBuilder, ConfiguredBlockBuilder, EthereumStrategyBundle,
+        NetworkStrategyBundle, OptimismStrategyBundle,
+    },
+    consts::{
+        ChainSpec, Network, BASE_MAINNET_CHAIN_SPEC, ETH_MAINNET_CHAIN_SPEC, HOLESKY_CHAIN_SPEC,
+        OP_MAINNET_CHAIN_SPEC, OP_SEPOLIA_CHAIN_SPEC, SEPOLIA_CHAIN_SPEC, ZORA_MAINNET_CHAIN_SPEC,
+    },
+    execution::InspectTxExecStrategy,
+    finalization::{BlockBuildStrategy, DebugBuildFromMemDbStrategy, MemDbBuildStrategy},
+    fraud_proof::{verify_claimed_block, BlockVerdict},
+    host::{
+        get_output_root,
+        simulation::{simulate_block, SimulatedBlock},
+        trace::StructLogger,
     },
-    consts::{ChainSpec, Network, ETH_MAINNET_CHAIN_SPEC, OP_MAINNET_CHAIN_SPEC},
-    finalization::DebugBuildFromMemDbStrategy,
     initialization::MemDbInitStrategy,
     input::Input,
+    mem_db::MemDb,
+    output::{
+        BlockOutput, FraudProofOutput, MultiBlockOutput, OptimismBlockOutput, BLOCK_OUTPUT_VERSION,
+        FRAUD_PROOF_OUTPUT_VERSION, MULTI_BLOCK_OUTPUT_VERSION,
+    },
+};
+use zeth_primitives::{
+    block::Header, transactions::TxEssence, Address, BlockHash, Bytes, B256, U256,
 };
-use zeth_primitives::BlockHash;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -48,6 +73,10 @@ struct Args {
     /// URL of the chain RPC node.
     rpc_url: Option<String>,
 
+    #[clap(long, require_equals = true)]
+    /// URL of the L1 RPC node, used to fetch the L1 origin of OP-stack blocks.
+    l1_rpc_url: Option<String>,
+
     #[clap(short, long, require_equals = true, num_args = 0..=1, default_missing_value = "host/testdata")]
     /// Use a local directory as a cache for RPC calls. Accepts a custom directory.
     /// [default: host/testdata]
@@ -63,10 +92,26 @@ struct Args {
     /// Network name.
     network: Network,
 
+    #[clap(long, require_equals = true)]
+    /// Path to a JSON chain specification, either a geth-style genesis file or a
+    /// serialized zeth chain spec, overriding the built-in spec of the network.
+    chain_spec: Option<String>,
+
+    #[clap(long, require_equals = true, requires = "chain_spec")]
+    /// Path to the rollup config of an OP-stack chain, providing the L1 addresses missing
+    /// from its geth-style genesis file.
+    rollup_config: Option<String>,
+
     #[clap(short, long, require_equals = true)]
     /// Block number to validate.
     block_no: u64,
 
+    #[clap(long, require_equals = true, default_value_t = 1)]
+    /// Number of consecutive blocks, starting at the given block number, to validate in a
+    /// single session. Only supported for Ethereum after the Paris update and the
+    /// OP-stack networks.
+    block_count: u64,
+
     #[clap(short, long, require_equals = true, num_args = 0..=1, default_missing_value = "20")]
     /// Runs the verification inside the zkvm executor locally. Accepts a custom maximum
     /// segment cycle count as a power of 2. [default: 20]
@@ -83,44 +128,428 @@ struct Args {
     #[clap(short, long, default_value_t = false)]
     /// Whether to profile the zkVM execution
     profile: bool,
+
+    #[clap(long, default_value_t = false)]
+    /// Whether to prove a verdict on the validity of the block instead of its hash.
+    fraud_proof: bool,
+
+    #[clap(long, require_equals = true, requires = "fraud_proof")]
+    /// Path to a JSON file containing the header claimed for the given block number, which
+    /// is judged instead of the canonical header. [default: the canonical header]
+    claimed_header: Option<String>,
+
+    #[clap(long, require_equals = true)]
+    /// Writes a geth-style struct log trace of each transaction to the given directory.
+    trace: Option<String>,
+
+    #[clap(long, require_equals = true)]
+    /// Path to a JSON file describing a hypothetical block, which is built on top of the
+    /// parent of the given block number instead of the existing block. Blocks deleting
+    /// state cannot be proven.
+    simulate: Option<String>,
+}
+
+impl Args {
+    /// Returns whether a guest is run, either locally or in Bonsai.
+    fn runs_guest(&self) -> bool {
+        self.local_exec.is_some()
+            || self.submit_to_bonsai
+            || self.verify_bonsai_receipt_uuid.is_some()
+    }
+}
+
+/// Hypothetical block given in a JSON file, with the transactions in the RPC format.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulatedBlockFile {
+    beneficiary: Address,
+    gas_limit: U256,
+    timestamp: U256,
+    #[serde(default)]
+    extra_data: Bytes,
+    #[serde(default)]
+    mix_hash: B256,
+    parent_beacon_block_root: Option<B256>,
+    transactions: Vec<EthersTransaction>,
+    #[serde(default)]
+    withdrawals: Vec<EthersWithdrawal>,
+}
+
+/// Loads a hypothetical block from a JSON file.
+fn load_simulated_block<E>(file_path: &String) -> Result<SimulatedBlock<E>>
+where
+    E: TxEssence + TryFrom<EthersTransaction>,
+    <E as TryFrom<EthersTransaction>>::Error: Debug,
+{
+    let buf = std::fs::read(file_path)
+        .with_context(|| format!("failed to read simulated block {}", file_path))?;
+    let block: SimulatedBlockFile = serde_json::from_slice(&buf)?;
+
+    Ok(SimulatedBlock {
+        beneficiary: block.beneficiary,
+        gas_limit: block.gas_limit,
+        timestamp: block.timestamp,
+        extra_data: block.extra_data,
+        mix_hash: block.mix_hash,
+        parent_beacon_block_root: block.parent_beacon_block_root,
+        transactions: block
+            .transactions
+            .into_iter()
+            .enumerate()
+            .map(|(tx_no, tx)| {
+                tx.try_into()
+                    .map_err(|err| anyhow!("invalid transaction {}: {:?}", tx_no, err))
+            })
+            .collect::<Result<_>>()
+            .with_context(|| format!("failed to convert simulated block {}", file_path))?,
+        withdrawals: block
+            .withdrawals
+            .into_iter()
+            .map(|w| w.try_into())
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Loads the header claimed for a block from a JSON file.
+fn load_claimed_header(file_path: &String) -> Result<Header> {
+    let buf = std::fs::read(file_path)
+        .with_context(|| format!("failed to read claimed header {}", file_path))?;
+    serde_json::from_slice(&buf)
+        .with_context(|| format!("failed to parse claimed header {}", file_path))
 }
 
 fn cache_file_path(cache_path: &String, network: &String, block_no: u64, ext: &str) -> String {
     format!("{}/{}/{}.{}", cache_path, network, block_no, ext)
 }
 
+/// Serializes the guest input, optionally preceded by the chain spec and the claimed header.
+fn guest_input<I: Serialize>(
+    chain_spec: &ChainSpec,
+    chain_spec_input: bool,
+    claimed_header: Option<&Header>,
+    input: &I,
+) -> Vec<u32> {
+    let mut words = Vec::new();
+    if chain_spec_input {
+        words.extend(to_vec(chain_spec).expect("Could not serialize chain spec!"));
+    }
+    if let Some(claimed_header) = claimed_header {
+        words.extend(to_vec(claimed_header).expect("Could not serialize claimed header!"));
+    }
+    words.extend(to_vec(input).expect("Could not serialize input!"));
+    words
+}
+
+/// Verifies the journal committed by the guest against the expected block.
+///
+/// The journals of OP-stack blocks additionally contain the `expected_output_root`, while
+/// the journals of several consecutive blocks contain the `expected_parent_hash` of the
+/// first block instead of the details of the final block.
+fn verify_journal(
+    source: &str,
+    journal: &[u8],
+    chain_spec: &ChainSpec,
+    expected_header: &Header,
+    expected_output_root: Option<B256>,
+    expected_parent_hash: Option<BlockHash>,
+    fraud_proof: bool,
+) {
+    let expected_hash = expected_header.hash();
+    if let Some(parent_hash) = expected_parent_hash {
+        let output: MultiBlockOutput = from_slice(journal).unwrap();
+        let expected_output = MultiBlockOutput::new(chain_spec, parent_hash, expected_header);
+        if output.version != MULTI_BLOCK_OUTPUT_VERSION {
+            error!(
+                "Multi-block output version mismatch (from {}) {} (expected {})",
+                source, output.version, MULTI_BLOCK_OUTPUT_VERSION
+            );
+        } else if output != expected_output {
+            error!(
+                "Multi-block output mismatch (from {}) {:?} (expected {:?})",
+                source, output, expected_output
+            );
+        } else {
+            info!("Multi-block output (from {}):", source);
+            info!("  Chain ID: {}", output.chain_id);
+            info!("  Chain spec hash: {}", output.chain_spec_hash);
+            info!("  Parent hash: {}", output.parent_hash);
+            info!("  Final number: {}", output.number);
+            info!("  Final block hash: {}", output.block_hash);
+            info!("  Final state root: {}", output.state_root);
+        }
+        return;
+    }
+    if !fraud_proof {
+        let (output, output_root) = match expected_output_root {
+            Some(_) => {
+                let output: OptimismBlockOutput = from_slice(journal).unwrap();
+                (output.block, Some(output.output_root))
+            }
+            None => (from_slice::<BlockOutput, _>(journal).unwrap(), None),
+        };
+        let expected_output = BlockOutput::new(chain_spec, expected_header);
+        if output.version != BLOCK_OUTPUT_VERSION {
+            error!(
+                "Block output version mismatch (from {}) {} (expected {})",
+                source, output.version, BLOCK_OUTPUT_VERSION
+            );
+        } else if output != expected_output {
+            error!(
+                "Block output mismatch (from {}) {:?} (expected {:?})",
+                source, output, expected_output
+            );
+        } else if output_root != expected_output_root {
+            error!(
+                "Output root mismatch (from {}) {:?} (expected {:?})",
+                source, output_root, expected_output_root
+            );
+        } else {
+            info!("Block output (from {}):", source);
+            info!("  Chain ID: {}", output.chain_id);
+            info!("  Chain spec hash: {}", output.chain_spec_hash);
+            info!("  Number: {}", output.number);
+            info!("  Parent hash: {}", output.parent_hash);
+            info!("  Block hash: {}", output.block_hash);
+            info!("  State root: {}", output.state_root);
+            info!("  Receipts root: {}", output.receipts_root);
+            info!("  Gas used: {}", output.gas_used);
+            if let Some(output_root) = output_root {
+                info!("  Output root: {}", output_root);
+            }
+        }
+        return;
+    }
+
+    let output: FraudProofOutput = from_slice(journal).unwrap();
+    if output.version != FRAUD_PROOF_OUTPUT_VERSION {
+        error!(
+            "Fraud-proof output version mismatch (from {}) {} (expected {})",
+            source, output.version, FRAUD_PROOF_OUTPUT_VERSION
+        );
+    } else if output.chain_id != chain_spec.chain_id() {
+        error!(
+            "Chain ID mismatch (from {}) {} (expected {})",
+            source,
+            output.chain_id,
+            chain_spec.chain_id()
+        );
+    } else if output.chain_spec_hash != chain_spec.hash() {
+        error!(
+            "Chain spec hash mismatch (from {}) {} (expected {})",
+            source,
+            output.chain_spec_hash,
+            chain_spec.hash()
+        );
+    } else if output.block_hash != expected_hash {
+        error!(
+            "Final block hash mismatch (from {}) {} (expected {})",
+            source, output.block_hash, expected_hash,
+        );
+    } else {
+        info!(
+            "Verdict on block {} (from {}): {:?}",
+            output.block_hash, source, output.verdict
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     let args = Args::parse();
 
+    let custom_chain_spec = args
+        .chain_spec
+        .as_ref()
+        .map(|path| zeth_lib::host::load_chain_spec(path, args.rollup_config.as_ref()))
+        .transpose()?;
+    // the OP-stack guests read their chain spec as input, the L1 guests have it built in
+    // except for the custom L1 guest
+    let is_optimism = matches!(
+        args.network,
+        Network::Optimism | Network::OptimismSepolia | Network::Base | Network::Zora
+    );
+    let runs_guest = args.runs_guest();
+    if custom_chain_spec.is_some() && !is_optimism && args.fraud_proof && runs_guest {
+        bail!("Fraud proofs are not supported for custom L1 chain specs");
+    }
+    if args.fraud_proof && args.simulate.is_some() {
+        bail!("Fraud proofs are not supported for simulated blocks");
+    }
+    if args.fraud_proof && !(is_optimism || matches!(args.network, Network::Ethereum)) && runs_guest
+    {
+        bail!("Fraud proofs are only supported for Ethereum and the OP-stack networks");
+    }
+    let multi_block = args.block_count > 1;
+    if args.block_count == 0 {
+        bail!("At least one block must be validated");
+    }
+    if multi_block && (args.fraud_proof || args.simulate.is_some() || args.trace.is_some()) {
+        bail!("Multiple blocks are not supported for fraud proofs, simulations and traces");
+    }
+    if multi_block
+        && runs_guest
+        && (!(is_optimism || matches!(args.network, Network::Ethereum))
+            || (custom_chain_spec.is_some() && !is_optimism))
+    {
+        bail!("Multiple blocks are only supported for Ethereum and the OP-stack networks");
+    }
+
+    let (eth_elf, eth_id, eth_path) = if args.fraud_proof {
+        (
+            ETH_FRAUD_PROOF_ELF,
+            ETH_FRAUD_PROOF_ID,
+            ETH_FRAUD_PROOF_PATH,
+        )
+    } else if multi_block {
+        (
+            ETH_MULTI_BLOCK_ELF,
+            ETH_MULTI_BLOCK_ID,
+            ETH_MULTI_BLOCK_PATH,
+        )
+    } else {
+        (ETH_BLOCK_ELF, ETH_BLOCK_ID, ETH_BLOCK_PATH)
+    };
+    // all OP-stack chains, including OP Sepolia, share the guests reading the chain spec
+    let (op_elf, op_id, op_path) = if args.fraud_proof {
+        (OP_FRAUD_PROOF_ELF, OP_FRAUD_PROOF_ID, OP_FRAUD_PROOF_PATH)
+    } else if multi_block {
+        (OP_MULTI_BLOCK_ELF, OP_MULTI_BLOCK_ID, OP_MULTI_BLOCK_PATH)
+    } else {
+        (OP_BLOCK_ELF, OP_BLOCK_ID, OP_BLOCK_PATH)
+    };
+
+    // custom L1 chain specs are read as input by the custom guest, which requires the merge
+    if let Some(chain_spec) = custom_chain_spec.as_ref().filter(|_| !is_optimism) {
+        // the Paris update is activated by block number, so the timestamp can be ignored
+        let merged = chain_spec.spec_id(args.block_no, 0)? >= SpecId::MERGE;
+        if merged {
+            return run_with_bundle::<EthereumStrategyBundle>(
+                args,
+                chain_spec.clone(),
+                true,
+                ETH_CUSTOM_BLOCK_ELF,
+                ETH_CUSTOM_BLOCK_ID,
+                ETH_CUSTOM_BLOCK_PATH,
+            )
+            .await;
+        } else if runs_guest {
+            bail!("The custom L1 guest does not support blocks before the Paris update");
+        }
+    }
+
     match args.network {
         Network::Ethereum => {
+            let chain_spec = custom_chain_spec.unwrap_or_else(|| ETH_MAINNET_CHAIN_SPEC.clone());
+            // the Paris update is activated by block number, so the timestamp can be ignored
+            if chain_spec.spec_id(args.block_no, 0)? < SpecId::MERGE {
+                if multi_block {
+                    bail!("Several blocks are not supported before the Paris update");
+                }
+                if args.fraud_proof && runs_guest {
+                    bail!("Fraud proofs are not supported before the Paris update");
+                }
+                run_pow_block(args, chain_spec).await
+            } else {
+                run_with_bundle::<EthereumStrategyBundle>(
+                    args, chain_spec, false, eth_elf, eth_id, eth_path,
+                )
+                .await
+            }
+        }
+        Network::Optimism => {
+            run_with_bundle::<OptimismStrategyBundle>(
+                args,
+                custom_chain_spec.unwrap_or_else(|| OP_MAINNET_CHAIN_SPEC.clone()),
+                true,
+                op_elf,
+                op_id,
+                op_path,
+            )
+            .await
+        }
+        Network::Sepolia => {
             run_with_bundle::<EthereumStrategyBundle>(
                 args,
-                ETH_MAINNET_CHAIN_SPEC.clone(),
-                ETH_BLOCK_ELF,
-                ETH_BLOCK_ID,
-                ETH_BLOCK_PATH,
+                custom_chain_spec.unwrap_or_else(|| SEPOLIA_CHAIN_SPEC.clone()),
+                false,
+                SEPOLIA_BLOCK_ELF,
+                SEPOLIA_BLOCK_ID,
+                SEPOLIA_BLOCK_PATH,
             )
             .await
         }
-        Network::Optimism => {
+        Network::Holesky => {
+            run_with_bundle::<EthereumStrategyBundle>(
+                args,
+                custom_chain_spec.unwrap_or_else(|| HOLESKY_CHAIN_SPEC.clone()),
+                false,
+                HOLESKY_BLOCK_ELF,
+                HOLESKY_BLOCK_ID,
+                HOLESKY_BLOCK_PATH,
+            )
+            .await
+        }
+        Network::OptimismSepolia => {
             run_with_bundle::<OptimismStrategyBundle>(
                 args,
-                OP_MAINNET_CHAIN_SPEC.clone(),
-                OP_BLOCK_ELF,
-                OP_BLOCK_ID,
-                OP_BLOCK_PATH,
+                custom_chain_spec.unwrap_or_else(|| OP_SEPOLIA_CHAIN_SPEC.clone()),
+                true,
+                op_elf,
+                op_id,
+                op_path,
+            )
+            .await
+        }
+        Network::Base => {
+            run_with_bundle::<OptimismStrategyBundle>(
+                args,
+                custom_chain_spec.unwrap_or_else(|| BASE_MAINNET_CHAIN_SPEC.clone()),
+                true,
+                op_elf,
+                op_id,
+                op_path,
+            )
+            .await
+        }
+        Network::Zora => {
+            run_with_bundle::<OptimismStrategyBundle>(
+                args,
+                custom_chain_spec.unwrap_or_else(|| ZORA_MAINNET_CHAIN_SPEC.clone()),
+                true,
+                op_elf,
+                op_id,
+                op_path,
             )
             .await
         }
     }
 }
 
-async fn run_with_bundle<N: NetworkStrategyBundle>(
+/// Runs a proof-of-work block, whose Ethash seal verification is only available with the
+/// `ethash` feature.
+#[cfg(feature = "ethash")]
+async fn run_pow_block(args: Args, chain_spec: ChainSpec) -> Result<()> {
+    run_with_bundle::<zeth_lib::block_builder::EthereumPowStrategyBundle>(
+        args,
+        chain_spec,
+        false,
+        zeth_guests::ETH_POW_BLOCK_ELF,
+        zeth_guests::ETH_POW_BLOCK_ID,
+        zeth_guests::ETH_POW_BLOCK_PATH,
+    )
+    .await
+}
+
+#[cfg(not(feature = "ethash"))]
+async fn run_pow_block(_args: Args, _chain_spec: ChainSpec) -> Result<()> {
+    bail!("Blocks before the Paris update require the `ethash` feature")
+}
+
+async fn run_with_bundle<N: NetworkStrategyBundle<Database = MemDb>>(
     args: Args,
     chain_spec: ChainSpec,
+    chain_spec_input: bool,
     guest_elf: &[u8],
     guest_id: [u32; risc0_zkvm::sha::DIGEST_WORDS],
     guest_path: &str,
@@ -128,25 +557,159 @@ async fn run_with_bundle<N: NetworkStrategyBundle>(
 where
     N::TxEssence: 'static + Send + TryFrom<EthersTransaction> + Serialize + Deserialize<'static>,
     <N::TxEssence as TryFrom<EthersTransaction>>::Error: Debug,
-    <N::Database as revm::primitives::db::Database>::Error: Debug,
+    N::BlockBuildStrategy: BlockBuildStrategy<N::TxEssence, Output = Header> + MemDbBuildStrategy,
+    N::TxExecStrategy: InspectTxExecStrategy<N::TxEssence>,
+    <N::Database as revm::primitives::db::Database>::Error: Debug + Into<BlockBuildError>,
 {
     // Fetch all of the initial data
+    // custom chains are cached by their chain ID to not mix them up with the built-in ones
+    let cache_network = match args.chain_spec {
+        Some(_) => chain_spec.chain_id().to_string(),
+        None => args.network.to_string(),
+    };
+
+    // Build several consecutive blocks in a single session (if requested)
+    if args.block_count > 1 {
+        return run_multiple_blocks::<N>(
+            args,
+            chain_spec,
+            chain_spec_input,
+            guest_elf,
+            guest_id,
+            guest_path,
+            cache_network,
+        )
+        .await;
+    }
     let rpc_cache = args
         .cache
         .as_ref()
-        .map(|dir| cache_file_path(dir, &args.network.to_string(), args.block_no, "json.gz"));
+        .map(|dir| cache_file_path(dir, &cache_network, args.block_no, "json.gz"));
+    // the L1 origins of OP-stack blocks are cached next to the L2 data
+    let l1_rpc_cache = args.cache.as_ref().map(|dir| {
+        cache_file_path(
+            dir,
+            &format!("{}/l1", cache_network),
+            args.block_no,
+            "json.gz",
+        )
+    });
+
+    // Simulate the hypothetical block (if requested)
+    if let Some(block_file) = &args.simulate {
+        let block = load_simulated_block(block_file)?;
+        let sim_spec = chain_spec.clone();
+        let rpc_url = args.rpc_url.clone();
+        let l1_rpc_url = args.l1_rpc_url.clone();
+        let block_no = args.block_no;
+        let simulation = tokio::task::spawn_blocking(move || {
+            simulate_block::<N>(
+                sim_spec,
+                rpc_cache,
+                rpc_url,
+                l1_rpc_cache,
+                l1_rpc_url,
+                block_no,
+                block,
+            )
+            .expect("Could not simulate")
+        })
+        .await?;
+
+        info!("Simulated block hash: {}", simulation.header.hash());
+        if simulation.deletes_state {
+            warn!("The simulated block deletes state, so its state root may be wrong");
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "header": simulation.header,
+                "receipts": simulation.receipts,
+                "txResults": simulation.tx_results,
+                "stateDiff": simulation.state_diff,
+                "outputRoot": simulation.output_root,
+            }))?
+        );
+
+        if simulation.deletes_state && args.runs_guest() {
+            bail!("Simulated blocks deleting state cannot be proven");
+        }
+
+        return prove_block(
+            args,
+            chain_spec,
+            chain_spec_input,
+            guest_elf,
+            guest_id,
+            guest_path,
+            simulation.input,
+            simulation.header,
+            simulation.output_root,
+            None,
+        )
+        .await;
+    }
 
     let init_spec = chain_spec.clone();
+    let rpc_url = args.rpc_url.clone();
+    let l1_rpc_url = args.l1_rpc_url.clone();
+    let block_no = args.block_no;
     let init = tokio::task::spawn_blocking(move || {
-        zeth_lib::host::get_initial_data::<N>(init_spec, rpc_cache, args.rpc_url, args.block_no)
-            .expect("Could not init")
+        zeth_lib::host::get_initial_data::<N>(
+            init_spec,
+            rpc_cache,
+            rpc_url,
+            l1_rpc_cache,
+            l1_rpc_url,
+            block_no,
+        )
+        .expect("Could not init")
     })
     .await?;
 
     let input: Input<N::TxEssence> = init.clone().into();
 
+    // Trace the transactions (if requested)
+    if let Some(trace_dir) = &args.trace {
+        write_traces::<N>(&chain_spec, input.clone(), trace_dir)?;
+    }
+
+    // Judge the claimed block before building it, as an invalid block fails to build
+    if args.fraud_proof {
+        let claimed_header = match &args.claimed_header {
+            Some(file_path) => load_claimed_header(file_path)?,
+            None => init.fini_block.clone(),
+        };
+        if claimed_header.number != args.block_no {
+            bail!(
+                "Claimed header is for block {} (expected {})",
+                claimed_header.number,
+                args.block_no
+            );
+        }
+        let verdict = verify_claimed_block::<N>(&chain_spec, &claimed_header, input.clone())
+            .context("failed to verify the claimed block")?;
+        info!("Verdict on the claimed block: {:?}", verdict);
+
+        if let BlockVerdict::Invalid(_) = verdict {
+            return prove_block(
+                args,
+                chain_spec,
+                chain_spec_input,
+                guest_elf,
+                guest_id,
+                guest_path,
+                input,
+                claimed_header,
+                None,
+                None,
+            )
+            .await;
+        }
+    }
+
     // Verify that the transactions run correctly
-    {
+    let output_root = {
         info!("Running from memory ...");
 
         // todo: extend to use [ConfiguredBlockBuilder]
@@ -164,6 +727,8 @@ where
         let (validated_header, storage_deltas) = block_builder
             .build::<DebugBuildFromMemDbStrategy>()
             .expect("Error while verifying final state");
+        let output_root = get_output_root(&chain_spec, &validated_header, &storage_deltas)
+            .expect("Error while computing the output root");
 
         info!(
             "Memory-backed execution is Done! Database contains {} accounts",
@@ -206,7 +771,7 @@ where
 
         if validated_header.base_fee_per_gas != init.fini_block.base_fee_per_gas {
             error!(
-                "Base fee mismatch {} (expected {})",
+                "Base fee mismatch {:?} (expected {:?})",
                 validated_header.base_fee_per_gas, init.fini_block.base_fee_per_gas
             );
         }
@@ -250,9 +815,165 @@ where
             bail!("Invalid block hash");
         }
 
-        info!("Final block hash derived successfully. {}", found_hash)
+        info!("Final block hash derived successfully. {}", found_hash);
+
+        output_root
+    };
+
+    prove_block(
+        args,
+        chain_spec,
+        chain_spec_input,
+        guest_elf,
+        guest_id,
+        guest_path,
+        input,
+        init.fini_block,
+        output_root,
+        None,
+    )
+    .await
+}
+
+/// Validates the consecutive blocks starting at the given block number in a single session.
+async fn run_multiple_blocks<N: NetworkStrategyBundle<Database = MemDb>>(
+    args: Args,
+    chain_spec: ChainSpec,
+    chain_spec_input: bool,
+    guest_elf: &[u8],
+    guest_id: [u32; risc0_zkvm::sha::DIGEST_WORDS],
+    guest_path: &str,
+    cache_network: String,
+) -> Result<()>
+where
+    N::TxEssence: 'static + Send + TryFrom<EthersTransaction> + Serialize + Deserialize<'static>,
+    <N::TxEssence as TryFrom<EthersTransaction>>::Error: Debug,
+    N::BlockBuildStrategy: MemDbBuildStrategy,
+{
+    let l1_cache_network = format!("{}/l1", cache_network);
+    let cache_path = |network: &String, block_no: u64| {
+        args.cache
+            .as_ref()
+            .map(|dir| cache_file_path(dir, network, block_no, "json.gz"))
+    };
+
+    // Fetch the initial data of every block, whose witnesses are combined afterwards
+    let mut inits = Vec::new();
+    for block_no in args.block_no..args.block_no + args.block_count {
+        let init_spec = chain_spec.clone();
+        let rpc_cache = cache_path(&cache_network, block_no);
+        let l1_rpc_cache = cache_path(&l1_cache_network, block_no);
+        let rpc_url = args.rpc_url.clone();
+        let l1_rpc_url = args.l1_rpc_url.clone();
+        let init = tokio::task::spawn_blocking(move || {
+            zeth_lib::host::get_initial_data::<N>(
+                init_spec,
+                rpc_cache,
+                rpc_url,
+                l1_rpc_cache,
+                l1_rpc_url,
+                block_no,
+            )
+            .expect("Could not init")
+        })
+        .await?;
+        inits.push(init);
+    }
+    let parent_hash = inits[0].init_block.hash();
+    let expected_header = inits.last().unwrap().fini_block.clone();
+
+    let rpc_cache = cache_path(&cache_network, args.block_no);
+    let rpc_url = args.rpc_url.clone();
+    let input = tokio::task::spawn_blocking(move || {
+        zeth_lib::host::get_multi_block_input(rpc_cache, rpc_url, inits)
+            .expect("Could not combine the inputs")
+    })
+    .await?;
+
+    // Verify that the blocks build correctly
+    info!("Running from memory ...");
+    let headers = ConfiguredBlockBuilder::<N>::build_multiple_from(&chain_spec, input.clone())
+        .expect("Error while building the blocks");
+    let found_hash = headers.last().unwrap().hash();
+    let expected_hash = expected_header.hash();
+    if found_hash != expected_hash {
+        error!(
+            "Final block hash mismatch {} (expected {})",
+            found_hash, expected_hash,
+        );
+
+        bail!("Invalid block hash");
+    }
+
+    info!("Final block hash derived successfully. {}", found_hash);
+
+    prove_block(
+        args,
+        chain_spec,
+        chain_spec_input,
+        guest_elf,
+        guest_id,
+        guest_path,
+        input,
+        expected_header,
+        None,
+        Some(parent_hash),
+    )
+    .await
+}
+
+/// Executes the transactions of the given input and writes their struct log traces.
+fn write_traces<N: NetworkStrategyBundle>(
+    chain_spec: &ChainSpec,
+    input: Input<N::TxEssence>,
+    trace_dir: &String,
+) -> Result<()>
+where
+    N::TxExecStrategy: InspectTxExecStrategy<N::TxEssence>,
+{
+    info!("Tracing the transactions ...");
+
+    let block_builder = BlockBuilder::new(chain_spec, input)
+        .initialize_database::<MemDbInitStrategy>()?
+        .prepare_header::<N::HeaderPrepStrategy>()?;
+    let (block_builder, loggers) = N::TxExecStrategy::execute_transactions_with_inspector(
+        block_builder,
+        StructLogger::default,
+    )?;
+
+    std::fs::create_dir_all(trace_dir)?;
+    for (tx_result, logger) in zip(block_builder.tx_results(), loggers) {
+        let trace = logger.into_trace(tx_result);
+        let path = format!("{}/{}.json", trace_dir, tx_result.tx_hash);
+        std::fs::write(&path, serde_json::to_vec_pretty(&trace)?)
+            .with_context(|| format!("failed to write trace {}", path))?;
     }
+    info!(
+        "Wrote {} transaction traces to {}",
+        block_builder.tx_results().len(),
+        trace_dir
+    );
+
+    Ok(())
+}
 
+/// Runs the guest for the given input in the executor and in Bonsai (if requested).
+///
+/// The input of several consecutive blocks additionally requires the hash of the parent of
+/// the first block, `expected_parent_hash`.
+#[allow(clippy::too_many_arguments)]
+async fn prove_block<I: Serialize>(
+    args: Args,
+    chain_spec: ChainSpec,
+    chain_spec_input: bool,
+    guest_elf: &[u8],
+    guest_id: [u32; risc0_zkvm::sha::DIGEST_WORDS],
+    guest_path: &str,
+    input: I,
+    expected_header: Header,
+    expected_output_root: Option<B256>,
+    expected_parent_hash: Option<BlockHash>,
+) -> Result<()> {
     // Run in the executor (if requested)
     if let Some(segment_limit_po2) = args.local_exec {
         info!(
@@ -260,7 +981,12 @@ where
             segment_limit_po2
         );
 
-        let input = to_vec(&input).expect("Could not serialize input!");
+        let input = guest_input(
+            &chain_spec,
+            chain_spec_input,
+            args.fraud_proof.then_some(&expected_header),
+            &input,
+        );
         info!(
             "Input size: {} words ( {} MB )",
             input.len(),
@@ -317,17 +1043,15 @@ where
             session.segments.len() * (1 << segment_limit_po2)
         );
 
-        let expected_hash = init.fini_block.hash();
-        let found_hash: BlockHash = from_slice(&session.journal).unwrap();
-
-        if found_hash == expected_hash {
-            info!("Block hash (from executor): {}", found_hash);
-        } else {
-            error!(
-                "Final block hash mismatch (from executor) {} (expected {})",
-                found_hash, expected_hash,
-            );
-        }
+        verify_journal(
+            "executor",
+            &session.journal,
+            &chain_spec,
+            &expected_header,
+            expected_output_root,
+            expected_parent_hash,
+            args.fraud_proof,
+        );
     }
 
     let mut bonsai_session_uuid = args.verify_bonsai_receipt_uuid;
@@ -355,7 +1079,12 @@ where
 
         // Prepare input data and upload it.
         info!("Uploading inputs");
-        let input_data = to_vec(&input).unwrap();
+        let input_data = guest_input(
+            &chain_spec,
+            chain_spec_input,
+            args.fraud_proof.then_some(&expected_header),
+            &input,
+        );
         let input_data = bytemuck::cast_slice(&input_data).to_vec();
         let input_id = client
             .upload_input(input_data)
@@ -399,17 +1128,15 @@ where
                     .verify(guest_id)
                     .expect("Receipt verification failed");
 
-                let expected_hash = init.fini_block.hash();
-                let found_hash: BlockHash = from_slice(&receipt.journal).unwrap();
-
-                if found_hash == expected_hash {
-                    info!("Block hash (from Bonsai): {}", found_hash);
-                } else {
-                    error!(
-                        "Final block hash mismatch (from Bonsai) {} (expected {})",
-                        found_hash, expected_hash,
-                    );
-                }
+                verify_journal(
+                    "Bonsai",
+                    &receipt.journal,
+                    &chain_spec,
+                    &expected_header,
+                    expected_output_root,
+                    expected_parent_hash,
+                    args.fraud_proof,
+                );
             } else {
                 panic!("Workflow exited: {}", res.status);
             }
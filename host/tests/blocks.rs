@@ -45,6 +45,8 @@ fn empty_blocks(#[files("testdata/ethereum/*.json.gz")] path: PathBuf) {
         ETH_MAINNET_CHAIN_SPEC.clone(),
         rpc_cache,
         None,
+        None,
+        None,
         block_no,
     )
     .expect("Could not init");
@@ -0,0 +1,76 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use zeth_lib::{
+    consts::OP_MAINNET_CHAIN_SPEC,
+    derivation::derive_batches,
+    host::{
+        get_l1_blocks,
+        provider::{new_file_provider, BlockQuery, Provider},
+    },
+    optimism::L1BlockInfo,
+};
+use zeth_primitives::{
+    block::Header,
+    transactions::{optimism::OptimismTxEssence, Transaction},
+    U256,
+};
+
+/// Number of L1 blocks after the L1 origin that are searched for the batch of the block.
+const L1_BLOCK_COUNT: u64 = 12;
+
+/// Derives the transactions of an OP mainnet block from the mainnet L1 blocks following its
+/// L1 origin, which are cached in `testdata/optimism/l1/<block_no>.json.gz`.
+///
+/// The cache is created by running the test with `L1_RPC_URL` set to an L1 archive node.
+#[test]
+#[ignore = "requires the L1 blocks of OP mainnet block 107728767 to be cached"]
+fn derive_op_block_transactions() {
+    let block_no = 107728767;
+    let mut provider =
+        new_file_provider(format!("testdata/optimism/{}.json.gz", block_no)).unwrap();
+    let block = provider.get_full_block(&BlockQuery { block_no }).unwrap();
+    let transactions: Vec<Transaction<OptimismTxEssence>> = block
+        .transactions
+        .iter()
+        .cloned()
+        .map(|tx| tx.try_into().unwrap())
+        .collect();
+    let header: Header = block.try_into().unwrap();
+
+    let optimism_config = OP_MAINNET_CHAIN_SPEC.optimism_config().unwrap();
+    let l1_info = L1BlockInfo::from_deposit(&transactions[0], optimism_config, false).unwrap();
+
+    let l1_blocks = get_l1_blocks(
+        Some(format!("testdata/optimism/l1/{}.json.gz", block_no)),
+        std::env::var("L1_RPC_URL").ok(),
+        l1_info.number + 1..=l1_info.number + L1_BLOCK_COUNT,
+    )
+    .unwrap();
+    assert_eq!(l1_blocks[0].header.parent_hash, l1_info.hash);
+
+    // the batches of the batcher of the system config contain the block
+    let batches = derive_batches(optimism_config, l1_info.batcher(), &l1_blocks).unwrap();
+    let batch = batches
+        .iter()
+        .find(|batch| U256::from(batch.timestamp) == header.timestamp)
+        .expect("batch of the block not found");
+    batch
+        .verify_block(&header, &l1_info, &transactions)
+        .unwrap();
+
+    // frames posted by anyone else are ignored
+    let batches = derive_batches(optimism_config, Default::default(), &l1_blocks).unwrap();
+    assert!(batches.is_empty());
+}
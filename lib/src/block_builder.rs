@@ -12,35 +12,176 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
-use revm::{Database, DatabaseCommit};
+use core::{fmt::Debug, mem};
+
+use anyhow::anyhow;
+use revm::{
+    primitives::{EVMError, SpecId},
+    Database, DatabaseCommit,
+};
+use thiserror::Error as ThisError;
 use zeth_primitives::{
     block::Header,
+    receipt::Receipt,
     transactions::{ethereum::EthereumTxEssence, optimism::OptimismTxEssence, TxEssence},
+    trie::Error as TrieError,
+    Address, B256, B64, U256,
 };
 
 use crate::{
     consts::ChainSpec,
-    execution::{ethereum::EthTxExecStrategy, optimism::OpTxExecStrategy, TxExecStrategy},
-    finalization::{BlockBuildStrategy, BuildFromMemDbStrategy},
+    execution::{
+        ethereum::EthTxExecStrategy, optimism::OpTxExecStrategy, TxExecStrategy, TxResult,
+    },
+    finalization::{BlockBuildStrategy, BuildFromMemDbStrategy, MemDbBuildStrategy},
+    guest_mem_forget,
     initialization::{DbInitStrategy, MemDbInitStrategy},
-    input::Input,
+    input::{Input, MultiBlockInput},
     mem_db::MemDb,
-    preparation::{EthHeaderPrepStrategy, HeaderPrepStrategy},
+    optimism::output_root,
+    preparation::{EthHeaderPrepStrategy, HeaderPrepStrategy, OpHeaderPrepStrategy},
 };
 
+/// Error returned when building a block.
+#[derive(Debug, ThisError)]
+pub enum BlockBuildError {
+    /// Returned when the protocol version of the block is not supported.
+    #[error("unsupported protocol version {0:?}")]
+    UnsupportedSpecId(SpecId),
+    /// Returned when the block number overflows.
+    #[error("invalid block number")]
+    InvalidBlockNumber,
+    /// Returned when the gas limit is out of the bounds given by the parent.
+    #[error("invalid gas limit {0}")]
+    InvalidGasLimit(U256),
+    /// Returned when the timestamp is not after the timestamp of the parent.
+    #[error("invalid timestamp {0}")]
+    InvalidTimestamp(U256),
+    /// Returned when the extra data exceeds the maximum size.
+    #[error("invalid extra data: {0} bytes")]
+    InvalidExtraData(usize),
+    /// Returned when the proof-of-work nonce is set after the Paris update.
+    #[error("invalid nonce {0}")]
+    InvalidNonce(B64),
+    /// Returned when the block contains too many ommers or ommers after the Paris update.
+    #[error("invalid number of ommers: {0}")]
+    InvalidOmmers(usize),
+    /// Returned when an ommer is not a valid header of a recent side chain or a duplicate.
+    #[error("invalid ommer {0}")]
+    InvalidOmmer(usize),
+    /// Returned when the Ethash seal of the block or of an ommer, given by its number, does
+    /// not satisfy its difficulty.
+    #[error("invalid seal of block {0}")]
+    InvalidSeal(u64),
+    /// Returned when the parent beacon block root is missing after the Cancun update.
+    #[error("missing parent beacon block root")]
+    MissingParentBeaconBlockRoot,
+    /// Returned when the L1 attributes of an OP-stack block do not follow the parent.
+    #[error("invalid L1 attributes: {0}")]
+    InvalidL1Attributes(String),
+    /// Returned when the mix hash of an OP-stack block is not the randao of its L1 origin.
+    #[error("invalid mix hash {0}")]
+    InvalidMixHash(B256),
+    /// Returned when the deposits of an OP-stack block differ from those of its L1 origin.
+    #[error("invalid deposit {tx_no}")]
+    InvalidDeposit { tx_no: usize },
+    /// Returned when the state trie does not match the state root of the parent.
+    #[error("state root mismatch: expected {expected}, got {found}")]
+    StateRootMismatch { expected: B256, found: B256 },
+    /// Returned when a storage trie does not match the storage root of its account.
+    #[error("storage root mismatch for {address}: expected {expected}, got {found}")]
+    StorageRootMismatch {
+        address: Address,
+        expected: B256,
+        found: B256,
+    },
+    /// Returned when the L1 origin of an OP-stack block is not the L1 block referenced by
+    /// its L1 attributes.
+    #[error("L1 origin mismatch: expected {expected}, got {found}")]
+    L1OriginMismatch { expected: B256, found: B256 },
+    /// Returned when the L1 receipts do not match the receipts root of the L1 origin.
+    #[error("L1 receipts mismatch: expected {expected}, got {found}")]
+    L1ReceiptsMismatch { expected: B256, found: B256 },
+    /// Returned when the input does not contain the body and the header fields of the
+    /// claimed block.
+    #[error("input does not match the claimed header")]
+    ClaimMismatch,
+    /// Returned when the ancestor headers do not form a chain ending at the parent.
+    #[error("invalid ancestor {0}")]
+    InvalidAncestor(u64),
+    /// Returned when the ancestor headers or their ommers required to validate the ommers
+    /// are not part of the input.
+    #[error("missing ancestors to validate the ommers")]
+    MissingAncestors,
+    /// Returned when the state of an account, its storage or code is not part of the
+    /// input.
+    #[error("missing witness for {0}")]
+    MissingWitness(Address),
+    /// Returned when the hash of a block is not part of the input.
+    #[error("missing block hash of block {0}")]
+    MissingBlockHash(u64),
+    /// Returned when the signature of a transaction is invalid.
+    #[error("invalid signature of transaction {tx_no}")]
+    InvalidSignature { tx_no: usize },
+    /// Returned when the gas limit of a transaction exceeds the remaining block gas.
+    #[error("gas of transaction {tx_no} exceeds block limit")]
+    TxGasExceedsBlock { tx_no: usize },
+    /// Returned when the excess blob gas or the blob gas used of the header or its parent
+    /// exceed 64 bits.
+    #[error("invalid blob gas {0}")]
+    InvalidBlobGas(U256),
+    /// Returned when the blob gas of a transaction exceeds the remaining block blob gas.
+    #[error("blob gas of transaction {tx_no} exceeds block limit")]
+    BlobGasExceedsBlock { tx_no: usize },
+    /// Returned when a transaction is rejected by the EVM.
+    #[error("invalid transaction {tx_no}: {reason}")]
+    InvalidTransaction { tx_no: usize, reason: String },
+    /// Returned when a trie operation fails.
+    #[error(transparent)]
+    Trie(#[from] TrieError),
+    /// Unspecified error.
+    #[error(transparent)]
+    Unspecified(#[from] anyhow::Error),
+}
+
+impl BlockBuildError {
+    /// Converts the error of a trie operation on the state of the given account.
+    pub(crate) fn from_trie(address: Address, err: TrieError) -> Self {
+        match err {
+            TrieError::NodeNotResolved(_) => BlockBuildError::MissingWitness(address),
+            err => BlockBuildError::Trie(err),
+        }
+    }
+
+    /// Converts the error of the EVM when executing the given transaction.
+    pub(crate) fn from_evm<E>(tx_no: usize, err: EVMError<E>) -> Self
+    where
+        E: Debug + Into<BlockBuildError>,
+    {
+        match err {
+            EVMError::Database(db_err) => db_err.into(),
+            err => BlockBuildError::InvalidTransaction {
+                tx_no,
+                reason: format!("{:?}", err),
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct BlockBuilder<'a, D, E: TxEssence> {
     pub(crate) chain_spec: &'a ChainSpec,
     pub(crate) input: Input<E>,
     pub(crate) db: Option<D>,
     pub(crate) header: Option<Header>,
+    pub(crate) receipts: Vec<Receipt>,
+    pub(crate) tx_results: Vec<TxResult>,
 }
 
 impl<D, E> BlockBuilder<'_, D, E>
 where
     D: Database + DatabaseCommit,
-    <D as Database>::Error: core::fmt::Debug,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
     E: TxEssence,
 {
     /// Creates a new block builder.
@@ -49,6 +190,8 @@ where
             chain_spec,
             db: None,
             header: None,
+            receipts: Vec::new(),
+            tx_results: Vec::new(),
             input,
         }
     }
@@ -60,22 +203,26 @@ where
     }
 
     /// Initializes the database from the input tries.
-    pub fn initialize_database<T: DbInitStrategy<E, Database = D>>(self) -> Result<Self> {
+    pub fn initialize_database<T: DbInitStrategy<E, Database = D>>(
+        self,
+    ) -> Result<Self, BlockBuildError> {
         T::initialize_database(self)
     }
 
     /// Initializes the header. This must be called before executing transactions.
-    pub fn prepare_header<T: HeaderPrepStrategy>(self) -> Result<Self> {
+    pub fn prepare_header<T: HeaderPrepStrategy>(self) -> Result<Self, BlockBuildError> {
         T::prepare_header(self)
     }
 
     /// Executes the transactions.
-    pub fn execute_transactions<T: TxExecStrategy<E>>(self) -> Result<Self> {
+    pub fn execute_transactions<T: TxExecStrategy<E>>(self) -> Result<Self, BlockBuildError> {
         T::execute_transactions(self)
     }
 
     /// Builds the block and returns the header.
-    pub fn build<T: BlockBuildStrategy<E, Database = D>>(self) -> Result<T::Output> {
+    pub fn build<T: BlockBuildStrategy<E, Database = D>>(
+        self,
+    ) -> Result<T::Output, BlockBuildError> {
         T::build(self)
     }
 
@@ -88,6 +235,50 @@ where
     pub fn mut_db(&mut self) -> Option<&mut D> {
         self.db.as_mut()
     }
+
+    /// Returns the receipts of the executed transactions.
+    pub fn receipts(&self) -> &[Receipt] {
+        &self.receipts
+    }
+
+    /// Returns the results of the executed transactions.
+    pub fn tx_results(&self) -> &[TxResult] {
+        &self.tx_results
+    }
+}
+
+impl<E: TxEssence> BlockBuilder<'_, MemDb, E> {
+    /// Builds the block using the strategy `S` and returns its header together with a
+    /// block builder for the given next block.
+    ///
+    /// The next block builder reuses the database, the updated state tries and the code
+    /// not deployed yet, so the parent header, the state witness, the contracts and the
+    /// ancestor headers and ommers of `next` are ignored.
+    pub fn build_and_continue<S: MemDbBuildStrategy>(
+        mut self,
+        next: Input<E>,
+    ) -> Result<(Header, Self), BlockBuildError> {
+        let header = S::build_in_place(&mut self)?;
+
+        // the built block becomes the parent of the next one
+        let mut db = self.db.take().expect("Database was not initialized");
+        db.insert_block_hash(header.number, header.hash());
+        let input = Input {
+            parent_header: header.clone(),
+            parent_state_trie: mem::take(&mut self.input.parent_state_trie),
+            parent_storage: mem::take(&mut self.input.parent_storage),
+            contracts: mem::take(&mut self.input.contracts),
+            ancestor_headers: vec![],
+            ancestor_ommers: vec![],
+            ..next
+        };
+        let chain_spec = self.chain_spec;
+
+        // Leak memory, save cycles
+        guest_mem_forget(self);
+
+        Ok((header, BlockBuilder::new(chain_spec, input).with_db(db)))
+    }
 }
 
 pub trait NetworkStrategyBundle {
@@ -108,12 +299,13 @@ where
 
 impl<N: NetworkStrategyBundle> ConfiguredBlockBuilder<'_, N>
 where
-    <N::Database as Database>::Error: core::fmt::Debug,
+    <N::Database as Database>::Error: Debug + Into<BlockBuildError>,
 {
     pub fn build_from(
         chain_spec: &ChainSpec,
         input: Input<N::TxEssence>,
-    ) -> Result<<N::BlockBuildStrategy as BlockBuildStrategy<N::TxEssence>>::Output> {
+    ) -> Result<<N::BlockBuildStrategy as BlockBuildStrategy<N::TxEssence>>::Output, BlockBuildError>
+    {
         Self::new(chain_spec, input)
             .initialize_database()?
             .prepare_header()?
@@ -136,21 +328,21 @@ where
     }
 
     /// Initializes the database from the input tries.
-    pub fn initialize_database(self) -> Result<Self> {
+    pub fn initialize_database(self) -> Result<Self, BlockBuildError> {
         Ok(ConfiguredBlockBuilder(
             N::DbInitStrategy::initialize_database(self.0)?,
         ))
     }
 
     /// Initializes the header. This must be called before executing transactions.
-    pub fn prepare_header(self) -> Result<Self> {
+    pub fn prepare_header(self) -> Result<Self, BlockBuildError> {
         Ok(ConfiguredBlockBuilder(
             N::HeaderPrepStrategy::prepare_header(self.0)?,
         ))
     }
 
     /// Executes the transactions.
-    pub fn execute_transactions(self) -> Result<Self> {
+    pub fn execute_transactions(self) -> Result<Self, BlockBuildError> {
         Ok(ConfiguredBlockBuilder(
             N::TxExecStrategy::execute_transactions(self.0)?,
         ))
@@ -159,7 +351,8 @@ where
     /// Builds the block and returns the header.
     pub fn build(
         self,
-    ) -> Result<<N::BlockBuildStrategy as BlockBuildStrategy<N::TxEssence>>::Output> {
+    ) -> Result<<N::BlockBuildStrategy as BlockBuildStrategy<N::TxEssence>>::Output, BlockBuildError>
+    {
         N::BlockBuildStrategy::build(self.0)
     }
 
@@ -172,6 +365,52 @@ where
     pub fn mut_db(&mut self) -> Option<&mut N::Database> {
         self.0.db.as_mut()
     }
+
+    /// Returns the receipts of the executed transactions.
+    pub fn receipts(&self) -> &[Receipt] {
+        &self.0.receipts
+    }
+
+    /// Returns the results of the executed transactions.
+    pub fn tx_results(&self) -> &[TxResult] {
+        &self.0.tx_results
+    }
+}
+
+impl<N> ConfiguredBlockBuilder<'_, N>
+where
+    N: NetworkStrategyBundle<Database = MemDb>,
+    N::BlockBuildStrategy: MemDbBuildStrategy,
+{
+    /// Builds the consecutive blocks of the given input and returns their headers.
+    pub fn build_multiple_from(
+        chain_spec: &ChainSpec,
+        input: MultiBlockInput<N::TxEssence>,
+    ) -> Result<Vec<Header>, BlockBuildError> {
+        let mut inputs = input.blocks.into_iter();
+        let first = inputs
+            .next()
+            .ok_or_else(|| anyhow!("Invalid input: no blocks"))?;
+
+        let mut headers = Vec::with_capacity(inputs.len() + 1);
+        let mut builder = Self::new(chain_spec, first).initialize_database()?;
+        for next in inputs {
+            let (header, next_builder) = builder
+                .prepare_header()?
+                .execute_transactions()?
+                .0
+                .build_and_continue::<N::BlockBuildStrategy>(next)?;
+            headers.push(header);
+            builder = ConfiguredBlockBuilder(next_builder);
+        }
+        let mut builder = builder.prepare_header()?.execute_transactions()?.0;
+        headers.push(N::BlockBuildStrategy::build_in_place(&mut builder)?);
+
+        // Leak memory, save cycles
+        guest_mem_forget(builder);
+
+        Ok(headers)
+    }
 }
 
 pub struct EthereumStrategyBundle {}
@@ -187,15 +426,116 @@ impl NetworkStrategyBundle for EthereumStrategyBundle {
 
 pub type EthereumBlockBuilder<'a> = ConfiguredBlockBuilder<'a, EthereumStrategyBundle>;
 
+#[cfg(feature = "ethash")]
+pub struct EthereumPowStrategyBundle {}
+
+#[cfg(feature = "ethash")]
+impl NetworkStrategyBundle for EthereumPowStrategyBundle {
+    type Database = MemDb;
+    type TxEssence = EthereumTxEssence;
+    type DbInitStrategy = MemDbInitStrategy;
+    type HeaderPrepStrategy = crate::preparation::PowHeaderPrepStrategy;
+    type TxExecStrategy = crate::execution::ethereum::PowTxExecStrategy;
+    type BlockBuildStrategy = crate::finalization::PowBuildFromMemDbStrategy;
+}
+
+#[cfg(feature = "ethash")]
+pub type EthereumPowBlockBuilder<'a> = ConfiguredBlockBuilder<'a, EthereumPowStrategyBundle>;
+
 pub struct OptimismStrategyBundle {}
 
 impl NetworkStrategyBundle for OptimismStrategyBundle {
     type Database = MemDb;
     type TxEssence = OptimismTxEssence;
     type DbInitStrategy = MemDbInitStrategy;
-    type HeaderPrepStrategy = EthHeaderPrepStrategy;
+    type HeaderPrepStrategy = OpHeaderPrepStrategy;
     type TxExecStrategy = OpTxExecStrategy;
     type BlockBuildStrategy = BuildFromMemDbStrategy;
 }
 
 pub type OptimismBlockBuilder<'a> = ConfiguredBlockBuilder<'a, OptimismStrategyBundle>;
+
+impl OptimismBlockBuilder<'_> {
+    /// Builds the block of the given input and returns its header and its output root.
+    pub fn build_with_output_root(
+        chain_spec: &ChainSpec,
+        input: Input<OptimismTxEssence>,
+    ) -> Result<(Header, B256), BlockBuildError> {
+        let message_passer = chain_spec
+            .optimism_config()
+            .ok_or_else(|| anyhow!("Invalid chain spec: Optimism config missing"))?
+            .l2_to_l1_message_passer;
+        let mut block_builder = Self::new(chain_spec, input)
+            .initialize_database()?
+            .prepare_header()?
+            .execute_transactions()?
+            .0;
+        let header = BuildFromMemDbStrategy::apply_state_updates(&mut None, &mut block_builder)?;
+
+        // the storage tries of the input now contain the state after the block
+        let (storage_trie, _) = block_builder
+            .input
+            .parent_storage
+            .get(&message_passer)
+            .ok_or(BlockBuildError::MissingWitness(message_passer))?;
+        let output_root = output_root(header.state_root, storage_trie.hash(), header.hash());
+
+        // Leak memory, save cycles
+        guest_mem_forget(block_builder);
+
+        Ok((header, output_root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{address, withdrawal::Withdrawal};
+
+    use super::*;
+    use crate::genesis::{build_genesis_state, child_of_genesis, Genesis, DEV_GENESIS};
+
+    #[test]
+    fn build_multiple_blocks() {
+        let mut genesis: Genesis = serde_json::from_str(DEV_GENESIS).unwrap();
+        let (chain_spec, first) = child_of_genesis::<EthereumTxEssence>(&genesis);
+
+        // each block withdraws 1 gwei to the allocated account
+        let recipient = address!("0000000000000000000000000000000000000001");
+        let withdrawal = |index: u64| Withdrawal {
+            index,
+            validator_index: 0,
+            address: recipient,
+            amount: 1,
+        };
+        let first = Input {
+            withdrawals: vec![withdrawal(0)],
+            ..first
+        };
+        let second = Input {
+            parent_header: Default::default(),
+            timestamp: U256::from(24),
+            withdrawals: vec![withdrawal(1)],
+            parent_state_trie: Default::default(),
+            parent_storage: Default::default(),
+            contracts: vec![],
+            ..first.clone()
+        };
+        let input = MultiBlockInput {
+            blocks: vec![first.clone(), second],
+        };
+
+        let headers = EthereumBlockBuilder::build_multiple_from(&chain_spec, input).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(
+            headers[0],
+            EthereumBlockBuilder::build_from(&chain_spec, first).unwrap()
+        );
+        assert_eq!(headers[1].parent_hash, headers[0].hash());
+        assert_eq!(headers[1].number, 2);
+
+        // the final state must contain the withdrawals of both blocks
+        genesis.alloc.get_mut(&recipient).unwrap().balance = U256::from(2_000_000_001u64);
+        let expected_state = build_genesis_state(&genesis.alloc).unwrap();
+        assert_eq!(headers[1].state_root, expected_state.state_trie.hash());
+    }
+}
@@ -17,11 +17,11 @@
 use core::str::FromStr;
 use std::collections::BTreeMap;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail, Result};
 use once_cell::sync::Lazy;
 use revm::primitives::SpecId;
 use serde::{Deserialize, Serialize};
-use zeth_primitives::{uint, BlockNumber, ChainId, U256};
+use zeth_primitives::{address, keccak::keccak, uint, Address, BlockNumber, ChainId, B256, U256};
 
 /// U256 representation of 0.
 pub const ZERO: U256 = U256::ZERO;
@@ -42,8 +42,33 @@ pub const MAX_BLOCK_HASH_AGE: u64 = 256;
 /// Multiplier for converting gwei to wei.
 pub const GWEI_TO_WEI: U256 = uint!(1_000_000_000_U256);
 
-/// Minimum supported protocol version: Paris (Block no. 15537394).
-pub const MIN_SPEC_ID: SpecId = SpecId::MERGE;
+/// Address of the EIP-4788 beacon roots contract.
+pub const BEACON_ROOTS_ADDRESS: Address = address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+/// Size of the ring buffer in which the beacon roots contract stores the roots.
+pub const BEACON_ROOTS_HISTORY_BUFFER_LENGTH: u64 = 8191;
+
+/// Minimum supported protocol version: Byzantium (Block no. 4370000).
+pub const MIN_SPEC_ID: SpecId = SpecId::BYZANTIUM;
+
+/// Base fee of the first block after the London update.
+pub const INITIAL_BASE_FEE: U256 = uint!(1_000_000_000_U256);
+
+/// Minimum the difficulty may ever be.
+pub const MIN_DIFFICULTY: U256 = uint!(131_072_U256);
+/// The bound divisor of the difficulty, used in the update calculations.
+pub const DIFFICULTY_BOUND_DIVISOR: U256 = uint!(2048_U256);
+/// Number of blocks after which the exponential difficulty bomb doubles.
+pub const DIFFICULTY_BOMB_PERIOD: u64 = 100_000;
+
+/// Static block reward before the Constantinople update.
+pub const BYZANTIUM_BLOCK_REWARD: U256 = uint!(3_000_000_000_000_000_000_U256);
+/// Static block reward after the Constantinople update.
+pub const CONSTANTINOPLE_BLOCK_REWARD: U256 = uint!(2_000_000_000_000_000_000_U256);
+
+/// Maximum number of ommers a block may include.
+pub const MAX_OMMERS: usize = 2;
+/// Maximum difference between the block number and the number of an included ommer.
+pub const MAX_OMMER_DEPTH: u64 = 6;
 
 /// The Ethereum mainnet specification.
 pub static ETH_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
@@ -52,50 +77,227 @@ pub static ETH_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
         hard_forks: BTreeMap::from([
             (SpecId::FRONTIER, ForkCondition::Block(0)),
             // previous versions not supported
+            (SpecId::BYZANTIUM, ForkCondition::Block(4370000)),
+            // Constantinople is overwritten by Petersburg
+            (SpecId::PETERSBURG, ForkCondition::Block(7280000)),
+            (SpecId::ISTANBUL, ForkCondition::Block(9069000)),
+            (SpecId::MUIR_GLACIER, ForkCondition::Block(9200000)),
+            (SpecId::BERLIN, ForkCondition::Block(12244000)),
+            (SpecId::LONDON, ForkCondition::Block(12965000)),
+            (SpecId::ARROW_GLACIER, ForkCondition::Block(13773000)),
+            (SpecId::GRAY_GLACIER, ForkCondition::Block(15050000)),
             (SpecId::MERGE, ForkCondition::Block(15537394)),
-            (SpecId::SHANGHAI, ForkCondition::Block(17034870)),
-            (SpecId::CANCUN, ForkCondition::TBD),
+            (SpecId::SHANGHAI, ForkCondition::Timestamp(1681338455)),
+            (SpecId::CANCUN, ForkCondition::Timestamp(1710338135)),
         ]),
-        eip_1559_constants: Eip1559Constants {
-            base_fee_change_denominator: uint!(8_U256),
-            base_fee_max_increase_denominator: uint!(8_U256),
-            base_fee_max_decrease_denominator: uint!(8_U256),
-            elasticity_multiplier: uint!(2_U256),
-        },
+        eip_1559_constants: BTreeMap::from([(
+            SpecId::LONDON,
+            Eip1559Constants {
+                base_fee_change_denominator: uint!(8_U256),
+                base_fee_max_increase_denominator: uint!(8_U256),
+                base_fee_max_decrease_denominator: uint!(8_U256),
+                elasticity_multiplier: uint!(2_U256),
+            },
+        )]),
+        optimism_forks: BTreeMap::new(),
+        optimism_config: None,
     }
 });
 
+/// The Sepolia testnet specification.
+pub static SEPOLIA_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| ChainSpec {
+    chain_id: 11155111,
+    hard_forks: BTreeMap::from([
+        (SpecId::FRONTIER, ForkCondition::Block(0)),
+        // all previous versions are activated at genesis
+        (SpecId::LONDON, ForkCondition::Block(0)),
+        (SpecId::MERGE, ForkCondition::Block(1450409)),
+        (SpecId::SHANGHAI, ForkCondition::Timestamp(1677557088)),
+        (SpecId::CANCUN, ForkCondition::Timestamp(1706655072)),
+    ]),
+    eip_1559_constants: BTreeMap::from([(SpecId::LONDON, Eip1559Constants::default())]),
+    optimism_forks: BTreeMap::new(),
+    optimism_config: None,
+});
+
+/// The Holesky testnet specification.
+pub static HOLESKY_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| ChainSpec {
+    chain_id: 17000,
+    hard_forks: BTreeMap::from([
+        // all previous versions are activated at genesis
+        (SpecId::MERGE, ForkCondition::Block(0)),
+        (SpecId::SHANGHAI, ForkCondition::Timestamp(1696000704)),
+        (SpecId::CANCUN, ForkCondition::Timestamp(1707305664)),
+    ]),
+    eip_1559_constants: BTreeMap::from([(SpecId::LONDON, Eip1559Constants::default())]),
+    optimism_forks: BTreeMap::new(),
+    optimism_config: None,
+});
+
 /// The optimism mainnet specification.
-pub static OP_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| ChainSpec {
-    chain_id: 10,
-    hard_forks: BTreeMap::from([(SpecId::MERGE, ForkCondition::Block(0))]),
-    eip_1559_constants: Eip1559Constants {
-        base_fee_change_denominator: uint!(50_U256),
-        base_fee_max_increase_denominator: uint!(10_U256),
-        base_fee_max_decrease_denominator: uint!(50_U256),
-        elasticity_multiplier: uint!(6_U256),
-    },
+pub static OP_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
+    ChainSpec::new_optimism(
+        10,
+        address!("bEb5Fc579115071764c7423A4f12eDde41f106Ed"),
+        address!("FF00000000000000000000000000000000000010"),
+        address!("229047fed2591dbec1eF1118d64F7aF3dB9EB290"),
+        ForkCondition::Block(105235063),
+        1704992401,
+        1708560000,
+        1710374401,
+    )
+});
+
+/// The optimism Sepolia testnet specification.
+pub static OP_SEPOLIA_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
+    ChainSpec::new_optimism(
+        11155420,
+        address!("16Fc5058F25648194471939df75CF27A2fdC48BC"),
+        address!("ff00000000000000000000000000000011155420"),
+        address!("034edD2A225f7f429A63E0f1D2084B9E0A93b538"),
+        ForkCondition::Block(0),
+        1699981200,
+        1703203200,
+        1708534800,
+    )
+});
+
+/// The Base mainnet specification.
+pub static BASE_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
+    ChainSpec::new_optimism(
+        8453,
+        address!("49048044D57e1C92A77f79988d21Fa8fAF74E97e"),
+        address!("Ff00000000000000000000000000000000008453"),
+        address!("73a79Fab69143498Ed3712e519A88a918e1f4072"),
+        ForkCondition::Block(0),
+        1704992401,
+        1708560000,
+        1710374401,
+    )
+});
+
+/// The Zora mainnet specification.
+pub static ZORA_MAINNET_CHAIN_SPEC: Lazy<ChainSpec> = Lazy::new(|| {
+    ChainSpec::new_optimism(
+        7777777,
+        address!("1a0ad011913A150f69f6A19DF447A0CfD9551054"),
+        address!("6F54Ca6F6EdE96662024Ffd61BFd18f3f4e34DFf"),
+        address!("A3cAB0126d5F504B071b81a3e8A2BBBF17930d86"),
+        ForkCondition::Block(0),
+        1704992401,
+        1708560000,
+        1710374401,
+    )
 });
 
 /// The condition at which a fork is activated.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ForkCondition {
     /// The fork is activated with a certain block.
     Block(BlockNumber),
+    /// The fork is activated with a specific timestamp.
+    Timestamp(u64),
     /// The fork is not yet active.
     TBD,
 }
 
 impl ForkCondition {
     /// Returns whether the condition has been met.
-    pub fn active(&self, block_number: BlockNumber) -> bool {
+    pub fn active(&self, block_number: BlockNumber, timestamp: u64) -> bool {
         match self {
             ForkCondition::Block(block) => *block <= block_number,
+            ForkCondition::Timestamp(ts) => *ts <= timestamp,
             ForkCondition::TBD => false,
         }
     }
 }
 
+/// Upgrades of the Optimism protocol.
+///
+/// Upgrades that also change the EVM rules are additionally part of the revm fork schedule:
+/// Canyon activates Shanghai and Ecotone activates Cancun.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OptimismFork {
+    Bedrock,
+    Regolith,
+    Canyon,
+    Delta,
+    Ecotone,
+}
+
+/// Parameters and addresses of the system accounts of an OP-stack chain.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OptimismConfig {
+    /// Sender of the L1 attributes deposit transaction.
+    pub l1_info_depositor: Address,
+    /// Predeploy storing the L1 block attributes.
+    pub l1_block: Address,
+    /// Predeploy providing the L1 fee parameters.
+    pub gas_price_oracle: Address,
+    /// Vault receiving the L2 base fees.
+    pub base_fee_vault: Address,
+    /// Vault receiving the L1 data fees.
+    pub l1_fee_vault: Address,
+    /// Predeploy storing the withdrawals, whose storage root is part of the output root.
+    #[serde(default = "default_l2_to_l1_message_passer")]
+    pub l2_to_l1_message_passer: Address,
+    /// L1 contract emitting the deposit events, not shared between chains.
+    #[serde(default)]
+    pub optimism_portal: Address,
+    /// L1 address receiving the batcher transactions, not shared between chains.
+    #[serde(default)]
+    pub batch_inbox: Address,
+    /// L1 contract emitting the system config updates, not shared between chains.
+    #[serde(default)]
+    pub system_config: Address,
+    /// Number of seconds between two consecutive L2 blocks.
+    #[serde(default = "default_block_time")]
+    pub block_time: u64,
+}
+
+impl OptimismConfig {
+    /// Returns an error if any of the L1 addresses, which are not shared between chains, is
+    /// unset. Without them, no deposits or system config updates could be derived.
+    pub fn validate(&self) -> Result<()> {
+        for (name, address) in [
+            ("optimism portal", self.optimism_portal),
+            ("batch inbox", self.batch_inbox),
+            ("system config", self.system_config),
+        ] {
+            if address == Address::ZERO {
+                bail!("Invalid Optimism config: {} address missing", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_l2_to_l1_message_passer() -> Address {
+    address!("4200000000000000000000000000000000000016")
+}
+
+fn default_block_time() -> u64 {
+    2
+}
+
+impl Default for OptimismConfig {
+    /// Defaults to the values shared by all superchain members, leaving the L1 addresses unset
+    fn default() -> Self {
+        Self {
+            l1_info_depositor: address!("DeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001"),
+            l1_block: address!("4200000000000000000000000000000000000015"),
+            gas_price_oracle: address!("420000000000000000000000000000000000000F"),
+            base_fee_vault: address!("4200000000000000000000000000000000000019"),
+            l1_fee_vault: address!("420000000000000000000000000000000000001A"),
+            l2_to_l1_message_passer: default_l2_to_l1_message_passer(),
+            optimism_portal: Address::ZERO,
+            batch_inbox: Address::ZERO,
+            system_config: Address::ZERO,
+            block_time: default_block_time(),
+        }
+    }
+}
+
 /// [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559) parameters.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Eip1559Constants {
@@ -122,7 +324,11 @@ impl Default for Eip1559Constants {
 pub struct ChainSpec {
     chain_id: ChainId,
     hard_forks: BTreeMap<SpecId, ForkCondition>,
-    eip_1559_constants: Eip1559Constants,
+    eip_1559_constants: BTreeMap<SpecId, Eip1559Constants>,
+    #[serde(default)]
+    optimism_forks: BTreeMap<OptimismFork, ForkCondition>,
+    #[serde(default)]
+    optimism_config: Option<OptimismConfig>,
 }
 
 impl ChainSpec {
@@ -135,25 +341,346 @@ impl ChainSpec {
         ChainSpec {
             chain_id,
             hard_forks: BTreeMap::from([(spec_id, ForkCondition::Block(0))]),
-            eip_1559_constants,
+            eip_1559_constants: BTreeMap::from([(spec_id, eip_1559_constants)]),
+            optimism_forks: BTreeMap::new(),
+            optimism_config: None,
+        }
+    }
+    /// Creates a new configuration for an OP-stack chain following the superchain upgrade
+    /// schedule, where all upgrades after Bedrock are activated by timestamp.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_optimism(
+        chain_id: ChainId,
+        optimism_portal: Address,
+        batch_inbox: Address,
+        system_config: Address,
+        bedrock: ForkCondition,
+        canyon_timestamp: u64,
+        delta_timestamp: u64,
+        ecotone_timestamp: u64,
+    ) -> Self {
+        ChainSpec {
+            chain_id,
+            hard_forks: BTreeMap::from([
+                (SpecId::MERGE, ForkCondition::Block(0)),
+                // Canyon
+                (SpecId::SHANGHAI, ForkCondition::Timestamp(canyon_timestamp)),
+                // Ecotone
+                (SpecId::CANCUN, ForkCondition::Timestamp(ecotone_timestamp)),
+            ]),
+            eip_1559_constants: BTreeMap::from([
+                (
+                    SpecId::MERGE,
+                    Eip1559Constants {
+                        base_fee_change_denominator: uint!(50_U256),
+                        base_fee_max_increase_denominator: uint!(10_U256),
+                        base_fee_max_decrease_denominator: uint!(50_U256),
+                        elasticity_multiplier: uint!(6_U256),
+                    },
+                ),
+                (
+                    SpecId::SHANGHAI,
+                    Eip1559Constants {
+                        base_fee_change_denominator: uint!(250_U256),
+                        base_fee_max_increase_denominator: uint!(10_U256),
+                        base_fee_max_decrease_denominator: uint!(250_U256),
+                        elasticity_multiplier: uint!(6_U256),
+                    },
+                ),
+            ]),
+            optimism_forks: BTreeMap::from([
+                (OptimismFork::Bedrock, bedrock),
+                (OptimismFork::Regolith, ForkCondition::Timestamp(0)),
+                (
+                    OptimismFork::Canyon,
+                    ForkCondition::Timestamp(canyon_timestamp),
+                ),
+                (
+                    OptimismFork::Delta,
+                    ForkCondition::Timestamp(delta_timestamp),
+                ),
+                (
+                    OptimismFork::Ecotone,
+                    ForkCondition::Timestamp(ecotone_timestamp),
+                ),
+            ]),
+            optimism_config: Some(OptimismConfig {
+                optimism_portal,
+                batch_inbox,
+                system_config,
+                ..Default::default()
+            }),
         }
     }
     /// Returns the network chain ID.
     pub fn chain_id(&self) -> ChainId {
         self.chain_id
     }
-    /// Returns the revm specification ID for `block_number`.
-    pub fn spec_id(&self, block_number: BlockNumber) -> SpecId {
+    /// Returns the hash of the specification, which commits to all its fields.
+    pub fn hash(&self) -> B256 {
+        keccak(bincode::serialize(self).unwrap()).into()
+    }
+    /// Returns the revm specification ID for `block_number` and `timestamp`.
+    pub fn spec_id(&self, block_number: BlockNumber, timestamp: u64) -> Result<SpecId> {
         for (spec_id, fork) in self.hard_forks.iter().rev() {
-            if fork.active(block_number) {
-                return *spec_id;
+            if fork.active(block_number, timestamp) {
+                return Ok(*spec_id);
             }
         }
-        unreachable!()
+        Err(anyhow!(
+            "No hard fork active at block {} and timestamp {}",
+            block_number,
+            timestamp
+        ))
+    }
+    /// Returns the Eip1559 constants active in `spec_id`, if any.
+    pub fn gas_constants(&self, spec_id: SpecId) -> Option<&Eip1559Constants> {
+        self.eip_1559_constants
+            .range(..=spec_id)
+            .next_back()
+            .map(|(_, constants)| constants)
+    }
+    /// Returns the configuration of the Optimism system accounts, if any.
+    pub fn optimism_config(&self) -> Option<&OptimismConfig> {
+        self.optimism_config.as_ref()
     }
-    /// Returns the Eip1559 constants
-    pub fn gas_constants(&self) -> &Eip1559Constants {
-        &self.eip_1559_constants
+    /// Returns whether the Optimism `fork` is active for `block_number` and `timestamp`.
+    pub fn is_optimism_fork_active(
+        &self,
+        fork: OptimismFork,
+        block_number: BlockNumber,
+        timestamp: u64,
+    ) -> bool {
+        self.optimism_forks
+            .get(&fork)
+            .is_some_and(|condition| condition.active(block_number, timestamp))
+    }
+    /// Returns whether the Optimism `fork` is activated exactly at `timestamp`.
+    pub fn is_optimism_fork_activation(&self, fork: OptimismFork, timestamp: u64) -> bool {
+        self.optimism_forks.get(&fork) == Some(&ForkCondition::Timestamp(timestamp))
+    }
+}
+
+/// Chain configuration as found in the `config` field of a geth-style `genesis.json`.
+///
+/// Only the chain ID, the fork schedule and the EIP-1559 parameters are read. As geth
+/// schedules the Paris update by total difficulty, its block is taken from the
+/// `mergeNetsplitBlock`, unless the chain merged at genesis. The EIP-1559 parameters of
+/// L1 chains default to the Ethereum values and can be given in the additional
+/// `eip1559Elasticity` and `eip1559Denominator` fields.
+#[cfg(not(target_os = "zkvm"))]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethChainConfig {
+    pub chain_id: ChainId,
+    pub homestead_block: Option<BlockNumber>,
+    #[serde(rename = "eip150Block")]
+    pub eip150_block: Option<BlockNumber>,
+    #[serde(rename = "eip158Block")]
+    pub eip158_block: Option<BlockNumber>,
+    pub byzantium_block: Option<BlockNumber>,
+    pub constantinople_block: Option<BlockNumber>,
+    pub petersburg_block: Option<BlockNumber>,
+    pub istanbul_block: Option<BlockNumber>,
+    pub muir_glacier_block: Option<BlockNumber>,
+    pub berlin_block: Option<BlockNumber>,
+    pub london_block: Option<BlockNumber>,
+    pub arrow_glacier_block: Option<BlockNumber>,
+    pub gray_glacier_block: Option<BlockNumber>,
+    /// Must be the block of the Paris update, which is not the case for all networks.
+    pub merge_netsplit_block: Option<BlockNumber>,
+    /// Only used to detect a merge at genesis, large values may lose precision.
+    pub terminal_total_difficulty: Option<serde_json::Number>,
+    #[serde(default)]
+    pub terminal_total_difficulty_passed: bool,
+    pub shanghai_time: Option<u64>,
+    pub cancun_time: Option<u64>,
+    pub bedrock_block: Option<BlockNumber>,
+    pub regolith_time: Option<u64>,
+    pub canyon_time: Option<u64>,
+    pub delta_time: Option<u64>,
+    pub ecotone_time: Option<u64>,
+    /// Elasticity multiplier of L1 chains, which is not part of geth configs.
+    #[serde(rename = "eip1559Elasticity")]
+    pub eip1559_elasticity: Option<u64>,
+    /// Base fee change denominator of L1 chains, which is not part of geth configs.
+    #[serde(rename = "eip1559Denominator")]
+    pub eip1559_denominator: Option<u64>,
+    pub optimism: Option<GethOptimismConfig>,
+}
+
+/// L1 configuration as found in the `rollup.json` of an OP-stack rollup node, which
+/// provides the addresses missing from the `genesis.json`.
+#[cfg(not(target_os = "zkvm"))]
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RollupConfig {
+    pub block_time: u64,
+    pub batch_inbox_address: Address,
+    pub deposit_contract_address: Address,
+    pub l1_system_config_address: Address,
+}
+
+/// EIP-1559 parameters as found in the `optimism` field of an OP-stack `genesis.json`.
+#[cfg(not(target_os = "zkvm"))]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethOptimismConfig {
+    #[serde(rename = "eip1559Elasticity")]
+    pub eip1559_elasticity: u64,
+    #[serde(rename = "eip1559Denominator")]
+    pub eip1559_denominator: u64,
+    #[serde(rename = "eip1559DenominatorCanyon")]
+    pub eip1559_denominator_canyon: Option<u64>,
+}
+
+#[cfg(not(target_os = "zkvm"))]
+impl TryFrom<GethChainConfig> for ChainSpec {
+    type Error = anyhow::Error;
+
+    fn try_from(config: GethChainConfig) -> Result<Self> {
+        ChainSpec::from_geth_config(config, None)
+    }
+}
+
+#[cfg(not(target_os = "zkvm"))]
+impl ChainSpec {
+    /// Creates the chain spec from a geth-style chain config, which requires the rollup
+    /// config for OP-stack chains.
+    pub fn from_geth_config(
+        config: GethChainConfig,
+        rollup_config: Option<RollupConfig>,
+    ) -> Result<Self> {
+        use anyhow::Context;
+
+        // a terminal total difficulty of zero means that the chain merged at genesis,
+        // otherwise the merge block cannot be derived from the total difficulty alone
+        let merge_block = if config
+            .terminal_total_difficulty
+            .as_ref()
+            .is_some_and(|ttd| ttd.as_u64() == Some(0))
+        {
+            Some(0)
+        } else {
+            config.merge_netsplit_block
+        };
+        if config.terminal_total_difficulty_passed && merge_block.is_none() {
+            bail!("terminalTotalDifficultyPassed requires the mergeNetsplitBlock");
+        }
+
+        let block_forks = [
+            (SpecId::HOMESTEAD, config.homestead_block),
+            (SpecId::TANGERINE, config.eip150_block),
+            (SpecId::SPURIOUS_DRAGON, config.eip158_block),
+            (SpecId::BYZANTIUM, config.byzantium_block),
+            (SpecId::CONSTANTINOPLE, config.constantinople_block),
+            (SpecId::PETERSBURG, config.petersburg_block),
+            (SpecId::ISTANBUL, config.istanbul_block),
+            (SpecId::MUIR_GLACIER, config.muir_glacier_block),
+            (SpecId::BERLIN, config.berlin_block),
+            (SpecId::LONDON, config.london_block),
+            (SpecId::ARROW_GLACIER, config.arrow_glacier_block),
+            (SpecId::GRAY_GLACIER, config.gray_glacier_block),
+            (SpecId::MERGE, merge_block),
+        ];
+        let time_forks = [
+            (SpecId::SHANGHAI, config.shanghai_time),
+            (SpecId::CANCUN, config.cancun_time),
+        ];
+
+        let mut hard_forks = BTreeMap::from([(SpecId::FRONTIER, ForkCondition::Block(0))]);
+        for (spec_id, block) in block_forks {
+            if let Some(block) = block {
+                hard_forks.insert(spec_id, ForkCondition::Block(block));
+            }
+        }
+        for (spec_id, timestamp) in time_forks {
+            if let Some(timestamp) = timestamp {
+                hard_forks.insert(spec_id, ForkCondition::Timestamp(timestamp));
+            }
+        }
+
+        let Some(optimism) = config.optimism else {
+            if rollup_config.is_some() {
+                bail!("Rollup config given for a chain without an Optimism config");
+            }
+            let mut eip_1559_constants = Eip1559Constants::default();
+            if let Some(denominator) = config.eip1559_denominator {
+                eip_1559_constants.base_fee_change_denominator = U256::from(denominator);
+                eip_1559_constants.base_fee_max_increase_denominator = U256::from(denominator);
+                eip_1559_constants.base_fee_max_decrease_denominator = U256::from(denominator);
+            }
+            if let Some(elasticity) = config.eip1559_elasticity {
+                eip_1559_constants.elasticity_multiplier = U256::from(elasticity);
+            }
+            return Ok(ChainSpec {
+                chain_id: config.chain_id,
+                hard_forks,
+                eip_1559_constants: BTreeMap::from([(SpecId::LONDON, eip_1559_constants)]),
+                optimism_forks: BTreeMap::new(),
+                optimism_config: None,
+            });
+        };
+
+        if config.eip1559_elasticity.is_some() || config.eip1559_denominator.is_some() {
+            bail!("The EIP-1559 parameters of OP-stack chains belong in the Optimism config");
+        }
+        let rollup_config =
+            rollup_config.context("OP-stack chain configs require a rollup config")?;
+
+        let op_constants = |denominator: u64| Eip1559Constants {
+            base_fee_change_denominator: U256::from(denominator),
+            base_fee_max_increase_denominator: uint!(10_U256),
+            base_fee_max_decrease_denominator: U256::from(denominator),
+            elasticity_multiplier: U256::from(optimism.eip1559_elasticity),
+        };
+        let mut eip_1559_constants =
+            BTreeMap::from([(SpecId::LONDON, op_constants(optimism.eip1559_denominator))]);
+        if let Some(denominator) = optimism.eip1559_denominator_canyon {
+            eip_1559_constants.insert(SpecId::SHANGHAI, op_constants(denominator));
+        }
+
+        let optimism_forks = [
+            (
+                OptimismFork::Bedrock,
+                config.bedrock_block.map(ForkCondition::Block),
+            ),
+            (
+                OptimismFork::Regolith,
+                config.regolith_time.map(ForkCondition::Timestamp),
+            ),
+            (
+                OptimismFork::Canyon,
+                config.canyon_time.map(ForkCondition::Timestamp),
+            ),
+            (
+                OptimismFork::Delta,
+                config.delta_time.map(ForkCondition::Timestamp),
+            ),
+            (
+                OptimismFork::Ecotone,
+                config.ecotone_time.map(ForkCondition::Timestamp),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(fork, condition)| Some((fork, condition?)))
+        .collect();
+
+        let optimism_config = OptimismConfig {
+            optimism_portal: rollup_config.deposit_contract_address,
+            batch_inbox: rollup_config.batch_inbox_address,
+            system_config: rollup_config.l1_system_config_address,
+            block_time: rollup_config.block_time,
+            ..Default::default()
+        };
+        optimism_config.validate()?;
+
+        Ok(ChainSpec {
+            chain_id: config.chain_id,
+            hard_forks,
+            eip_1559_constants,
+            optimism_forks,
+            optimism_config: Some(optimism_config),
+        })
     }
 }
 
@@ -164,6 +691,16 @@ pub enum Network {
     Ethereum,
     /// The Optimism Mainnet
     Optimism,
+    /// The Sepolia Testnet
+    Sepolia,
+    /// The Holesky Testnet
+    Holesky,
+    /// The Optimism Sepolia Testnet
+    OptimismSepolia,
+    /// The Base Mainnet
+    Base,
+    /// The Zora Mainnet
+    Zora,
 }
 
 impl FromStr for Network {
@@ -173,6 +710,11 @@ impl FromStr for Network {
         match s.to_lowercase().as_str() {
             "ethereum" => Ok(Network::Ethereum),
             "optimism" => Ok(Network::Optimism),
+            "sepolia" => Ok(Network::Sepolia),
+            "holesky" => Ok(Network::Holesky),
+            "optimism-sepolia" => Ok(Network::OptimismSepolia),
+            "base" => Ok(Network::Base),
+            "zora" => Ok(Network::Zora),
             _ => bail!("Unknown network"),
         }
     }
@@ -183,6 +725,11 @@ impl ToString for Network {
         match self {
             Network::Ethereum => String::from("ethereum"),
             Network::Optimism => String::from("optimism"),
+            Network::Sepolia => String::from("sepolia"),
+            Network::Holesky => String::from("holesky"),
+            Network::OptimismSepolia => String::from("optimism-sepolia"),
+            Network::Base => String::from("base"),
+            Network::Zora => String::from("zora"),
         }
     }
 }
@@ -193,9 +740,313 @@ mod tests {
 
     #[test]
     fn revm_spec_id() {
-        assert!(ETH_MAINNET_CHAIN_SPEC.spec_id(15537393) < SpecId::MERGE);
-        assert_eq!(ETH_MAINNET_CHAIN_SPEC.spec_id(15537394), SpecId::MERGE);
-        assert_eq!(ETH_MAINNET_CHAIN_SPEC.spec_id(17034869), SpecId::MERGE);
-        assert_eq!(ETH_MAINNET_CHAIN_SPEC.spec_id(17034870), SpecId::SHANGHAI);
+        assert!(ETH_MAINNET_CHAIN_SPEC.spec_id(4369999, 1508131303).unwrap() < SpecId::BYZANTIUM);
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC.spec_id(4370000, 1508131331).unwrap(),
+            SpecId::BYZANTIUM
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(12244000, 1618481223)
+                .unwrap(),
+            SpecId::BERLIN
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(12965000, 1628166822)
+                .unwrap(),
+            SpecId::LONDON
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(15537393, 1663224162)
+                .unwrap(),
+            SpecId::GRAY_GLACIER
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(15537394, 1663224179)
+                .unwrap(),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(17034869, 1681338443)
+                .unwrap(),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(17034870, 1681338479)
+                .unwrap(),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(19426586, 1710338123)
+                .unwrap(),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC
+                .spec_id(19426587, 1710338135)
+                .unwrap(),
+            SpecId::CANCUN
+        );
+    }
+
+    #[test]
+    fn testnet_spec_id() {
+        assert_eq!(
+            SEPOLIA_CHAIN_SPEC.spec_id(1450408, 1655733000).unwrap(),
+            SpecId::LONDON
+        );
+        assert_eq!(
+            SEPOLIA_CHAIN_SPEC.spec_id(1450409, 1655733012).unwrap(),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            SEPOLIA_CHAIN_SPEC.spec_id(2990908, 1677557088).unwrap(),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            SEPOLIA_CHAIN_SPEC.spec_id(5187023, 1706655072).unwrap(),
+            SpecId::CANCUN
+        );
+        assert_eq!(
+            HOLESKY_CHAIN_SPEC.spec_id(0, 1695902100).unwrap(),
+            SpecId::MERGE
+        );
+        assert_eq!(
+            HOLESKY_CHAIN_SPEC.spec_id(6698, 1696000704).unwrap(),
+            SpecId::SHANGHAI
+        );
+        assert_eq!(
+            HOLESKY_CHAIN_SPEC.spec_id(894733, 1707305664).unwrap(),
+            SpecId::CANCUN
+        );
+    }
+
+    #[test]
+    fn gas_constants() {
+        assert_eq!(ETH_MAINNET_CHAIN_SPEC.gas_constants(SpecId::BERLIN), None);
+        assert_eq!(
+            ETH_MAINNET_CHAIN_SPEC.gas_constants(SpecId::CANCUN),
+            Some(&Eip1559Constants::default())
+        );
+
+        // Canyon activates Shanghai and changes the base fee change denominator
+        assert_eq!(
+            OP_MAINNET_CHAIN_SPEC
+                .gas_constants(SpecId::MERGE)
+                .unwrap()
+                .base_fee_change_denominator,
+            uint!(50_U256)
+        );
+        assert_eq!(
+            OP_MAINNET_CHAIN_SPEC
+                .gas_constants(SpecId::SHANGHAI)
+                .unwrap()
+                .base_fee_change_denominator,
+            uint!(250_U256)
+        );
+    }
+
+    #[test]
+    fn optimism_forks() {
+        assert!(!OP_MAINNET_CHAIN_SPEC.is_optimism_fork_active(
+            OptimismFork::Bedrock,
+            105235062,
+            1686068903
+        ));
+        assert!(OP_MAINNET_CHAIN_SPEC.is_optimism_fork_active(
+            OptimismFork::Regolith,
+            105235063,
+            1686068905
+        ));
+        assert!(!OP_MAINNET_CHAIN_SPEC.is_optimism_fork_active(
+            OptimismFork::Ecotone,
+            117387811,
+            1710374399
+        ));
+        assert!(OP_MAINNET_CHAIN_SPEC.is_optimism_fork_active(
+            OptimismFork::Ecotone,
+            117387812,
+            1710374401
+        ));
+        assert_eq!(
+            OP_MAINNET_CHAIN_SPEC
+                .spec_id(117387812, 1710374401)
+                .unwrap(),
+            SpecId::CANCUN
+        );
+        assert!(!ETH_MAINNET_CHAIN_SPEC.is_optimism_fork_active(
+            OptimismFork::Bedrock,
+            15537394,
+            1663224179
+        ));
+        assert_eq!(ETH_MAINNET_CHAIN_SPEC.optimism_config(), None);
+        assert_eq!(
+            BASE_MAINNET_CHAIN_SPEC.optimism_config(),
+            Some(&OptimismConfig {
+                optimism_portal: address!("49048044D57e1C92A77f79988d21Fa8fAF74E97e"),
+                batch_inbox: address!("Ff00000000000000000000000000000000008453"),
+                system_config: address!("73a79Fab69143498Ed3712e519A88a918e1f4072"),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn geth_optimism_chain_config() {
+        let config: GethChainConfig = serde_json::from_str(
+            r#"{
+                "chainId": 8453,
+                "londonBlock": 0,
+                "mergeNetsplitBlock": 0,
+                "terminalTotalDifficulty": 0,
+                "terminalTotalDifficultyPassed": true,
+                "shanghaiTime": 1704992401,
+                "cancunTime": 1710374401,
+                "bedrockBlock": 0,
+                "regolithTime": 0,
+                "canyonTime": 1704992401,
+                "deltaTime": 1708560000,
+                "ecotoneTime": 1710374401,
+                "optimism": {
+                    "eip1559Elasticity": 6,
+                    "eip1559Denominator": 50,
+                    "eip1559DenominatorCanyon": 250
+                }
+            }"#,
+        )
+        .unwrap();
+        let rollup_config: RollupConfig = serde_json::from_str(
+            r#"{
+                "block_time": 2,
+                "batch_inbox_address": "0xff00000000000000000000000000000000008453",
+                "deposit_contract_address": "0x49048044d57e1c92a77f79988d21fa8faf74e97e",
+                "l1_system_config_address": "0x73a79fab69143498ed3712e519a88a918e1f4072"
+            }"#,
+        )
+        .unwrap();
+        // the L1 addresses are not part of the geth config
+        ChainSpec::try_from(config.clone()).unwrap_err();
+        let chain_spec = ChainSpec::from_geth_config(config, Some(rollup_config)).unwrap();
+        let expected = &BASE_MAINNET_CHAIN_SPEC;
+
+        assert_eq!(chain_spec.chain_id(), expected.chain_id());
+        assert_eq!(chain_spec.optimism_config(), expected.optimism_config());
+        assert_eq!(chain_spec.optimism_forks, expected.optimism_forks);
+        for spec_id in [SpecId::MERGE, SpecId::SHANGHAI, SpecId::CANCUN] {
+            assert_eq!(
+                chain_spec.gas_constants(spec_id),
+                expected.gas_constants(spec_id)
+            );
+        }
+    }
+
+    #[test]
+    fn geth_chain_config() {
+        let config: GethChainConfig = serde_json::from_str(
+            r#"{
+                "chainId": 1337,
+                "homesteadBlock": 0,
+                "eip150Block": 0,
+                "eip155Block": 0,
+                "eip158Block": 0,
+                "byzantiumBlock": 0,
+                "constantinopleBlock": 0,
+                "petersburgBlock": 0,
+                "istanbulBlock": 0,
+                "berlinBlock": 0,
+                "londonBlock": 0,
+                "mergeNetsplitBlock": 10,
+                "shanghaiTime": 1000,
+                "terminalTotalDifficulty": 58750000000000000000000,
+                "terminalTotalDifficultyPassed": true,
+                "eip1559Elasticity": 4
+            }"#,
+        )
+        .unwrap();
+        let chain_spec = ChainSpec::try_from(config).unwrap();
+
+        assert_eq!(chain_spec.chain_id(), 1337);
+        assert_eq!(chain_spec.gas_constants(SpecId::BERLIN), None);
+        assert_eq!(
+            chain_spec.gas_constants(SpecId::MERGE),
+            Some(&Eip1559Constants {
+                elasticity_multiplier: U256::from(4),
+                ..Default::default()
+            })
+        );
+        assert_eq!(chain_spec.spec_id(9, 999).unwrap(), SpecId::LONDON);
+        assert_eq!(chain_spec.spec_id(10, 999).unwrap(), SpecId::MERGE);
+        assert_eq!(chain_spec.spec_id(11, 1000).unwrap(), SpecId::SHANGHAI);
+    }
+
+    #[test]
+    fn geth_chain_config_merged_at_genesis() {
+        let config: GethChainConfig = serde_json::from_str(
+            r#"{
+                "chainId": 1337,
+                "londonBlock": 0,
+                "terminalTotalDifficulty": 0,
+                "terminalTotalDifficultyPassed": true
+            }"#,
+        )
+        .unwrap();
+        let chain_spec = ChainSpec::try_from(config).unwrap();
+        assert_eq!(chain_spec.spec_id(0, 0).unwrap(), SpecId::MERGE);
+    }
+
+    #[test]
+    fn geth_chain_config_without_merge_block() {
+        let config: GethChainConfig = serde_json::from_str(
+            r#"{
+                "chainId": 1337,
+                "londonBlock": 0,
+                "terminalTotalDifficulty": 100,
+                "terminalTotalDifficultyPassed": true
+            }"#,
+        )
+        .unwrap();
+        ChainSpec::try_from(config).unwrap_err();
+    }
+
+    #[test]
+    fn optimism_config_without_l1_addresses() {
+        OptimismConfig::default().validate().unwrap_err();
+        for chain_spec in [
+            &OP_MAINNET_CHAIN_SPEC,
+            &OP_SEPOLIA_CHAIN_SPEC,
+            &BASE_MAINNET_CHAIN_SPEC,
+            &ZORA_MAINNET_CHAIN_SPEC,
+        ] {
+            chain_spec.optimism_config().unwrap().validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn spec_id_without_hard_forks() {
+        let chain_spec = ChainSpec {
+            hard_forks: BTreeMap::new(),
+            ..ETH_MAINNET_CHAIN_SPEC.clone()
+        };
+        chain_spec.spec_id(0, 0).unwrap_err();
+    }
+
+    #[test]
+    fn chain_spec_hash() {
+        assert_eq!(
+            OP_MAINNET_CHAIN_SPEC.hash(),
+            OP_MAINNET_CHAIN_SPEC.clone().hash()
+        );
+        // specs of the same chain differ in their fork schedule
+        let mut chain_spec = OP_MAINNET_CHAIN_SPEC.clone();
+        chain_spec
+            .optimism_forks
+            .insert(OptimismFork::Ecotone, ForkCondition::TBD);
+        assert_ne!(chain_spec.hash(), OP_MAINNET_CHAIN_SPEC.hash());
     }
 }
@@ -0,0 +1,724 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Derivation of the sequenced transactions of OP-stack blocks from the batches posted to
+//! L1.
+//!
+//! The batcher sends transactions to the batch inbox, whose call data consists of frames.
+//! The frames of a channel together form a zlib stream, which contains the RLP-encoded
+//! batches. Each singular batch lists the non-deposit transactions of one L2 block.
+//!
+//! The derivation is not yet performed by any guest, i.e. the proofs of OP-stack blocks do
+//! not attest that their transactions have been posted to L1. As the L1 blocks containing
+//! the batches follow the L1 origin, such a guest must also commit the hash of the last of
+//! these blocks, so that it can be checked against L1.
+
+use std::{collections::BTreeMap, iter::zip};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{
+    block::Header,
+    rlp,
+    transactions::{
+        ethereum::EthereumTxEssence, optimism::OptimismTxEssence, Transaction, TxEssence,
+    },
+    trie::MptNode,
+    Address, Bytes, RlpBytes, B256, U256,
+};
+
+use crate::{
+    consts::OptimismConfig,
+    optimism::{L1BlockInfo, DEPOSIT_TX_TYPE},
+};
+
+/// Version of the call data of batcher transactions.
+const DERIVATION_VERSION_0: u8 = 0;
+/// Version of singular batches.
+const SINGULAR_BATCH_VERSION: u8 = 0;
+/// Version of span batches, introduced with Delta.
+const SPAN_BATCH_VERSION: u8 = 1;
+/// Length of a channel ID.
+const CHANNEL_ID_LENGTH: usize = 16;
+
+/// Maximum size of the decompressed data of a channel.
+pub const MAX_RLP_BYTES_PER_CHANNEL: usize = 10_000_000;
+/// Number of L1 blocks after which an incomplete channel is dropped.
+pub const CHANNEL_TIMEOUT: u64 = 300;
+
+/// Identifier of a channel, chosen by the batcher.
+pub type ChannelId = [u8; CHANNEL_ID_LENGTH];
+
+/// L1 block, whose transactions may include batcher transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct L1Block {
+    /// Header of the block.
+    pub header: Header,
+    /// Transactions of the block.
+    pub transactions: Vec<Transaction<EthereumTxEssence>>,
+}
+
+/// Chunk of the data of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Channel the frame belongs to.
+    pub channel_id: ChannelId,
+    /// Position of the frame within the channel.
+    pub number: u16,
+    /// Chunk of the compressed channel data.
+    pub data: Vec<u8>,
+    /// Whether the frame is the last of the channel.
+    pub is_last: bool,
+}
+
+impl Frame {
+    /// Decodes the frames of the call data of a batcher transaction.
+    pub fn decode_all(data: &[u8]) -> Result<Vec<Frame>> {
+        let (version, mut buf) = data.split_first().context("empty batcher data")?;
+        ensure!(
+            *version == DERIVATION_VERSION_0,
+            "unsupported derivation version: {}",
+            version
+        );
+
+        let mut frames = Vec::new();
+        while !buf.is_empty() {
+            frames.push(Frame::decode(&mut buf)?);
+        }
+        ensure!(!frames.is_empty(), "no frames");
+
+        Ok(frames)
+    }
+
+    /// Decodes a single frame and advances the buffer past it.
+    fn decode(buf: &mut &[u8]) -> Result<Frame> {
+        ensure!(buf.len() >= CHANNEL_ID_LENGTH + 6, "frame header too short");
+        let (channel_id, rest) = buf.split_at(CHANNEL_ID_LENGTH);
+        let (number, rest) = rest.split_at(2);
+        let (length, rest) = rest.split_at(4);
+        let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+        ensure!(rest.len() > length, "frame data too short");
+        let (data, rest) = rest.split_at(length);
+        let is_last = match rest[0] {
+            0 => false,
+            1 => true,
+            flag => bail!("invalid is_last flag: {}", flag),
+        };
+        *buf = &rest[1..];
+
+        Ok(Frame {
+            channel_id: channel_id.try_into().unwrap(),
+            number: u16::from_be_bytes(number.try_into().unwrap()),
+            data: data.to_vec(),
+            is_last,
+        })
+    }
+}
+
+/// Channel, which is assembled from its frames in the order of their numbers.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    /// Number of the L1 block containing the first received frame.
+    open_block: u64,
+    /// Received frames by their number.
+    frames: BTreeMap<u16, Frame>,
+    /// Number of the last frame, once it has been received.
+    last_number: Option<u16>,
+}
+
+impl Channel {
+    /// Creates an empty channel opened in the given L1 block.
+    pub fn new(open_block: u64) -> Self {
+        Channel {
+            open_block,
+            frames: BTreeMap::new(),
+            last_number: None,
+        }
+    }
+
+    /// Adds the given frame. Duplicate frames and frames after the last one are ignored.
+    pub fn add_frame(&mut self, frame: Frame) {
+        if self.frames.contains_key(&frame.number) {
+            return;
+        }
+        if let Some(last_number) = self.last_number {
+            if frame.is_last || frame.number > last_number {
+                return;
+            }
+        }
+        if frame.is_last {
+            self.last_number = Some(frame.number);
+            // frames after the last one can never be part of the channel
+            self.frames.retain(|&number, _| number < frame.number);
+        }
+        self.frames.insert(frame.number, frame);
+    }
+
+    /// Returns whether all frames up to the last one have been received.
+    pub fn is_ready(&self) -> bool {
+        self.last_number
+            .is_some_and(|last_number| self.frames.len() == last_number as usize + 1)
+    }
+
+    /// Returns whether the channel can no longer be completed in the given L1 block.
+    pub fn is_timed_out(&self, block_no: u64) -> bool {
+        block_no > self.open_block + CHANNEL_TIMEOUT
+    }
+
+    /// Decompresses the data of the ready channel and decodes its batches.
+    pub fn batches(&self) -> Result<Vec<SingularBatch>> {
+        ensure!(self.is_ready(), "channel not ready");
+        let data: Vec<u8> = self
+            .frames
+            .values()
+            .flat_map(|frame| frame.data.iter().copied())
+            .collect();
+
+        SingularBatch::decode_all(&decompress(&data)?)
+    }
+}
+
+/// Batch containing the non-deposit transactions of a single L2 block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SingularBatch {
+    /// Hash of the parent of the L2 block.
+    pub parent_hash: B256,
+    /// Number of the L1 origin of the L2 block.
+    pub epoch_num: u64,
+    /// Hash of the L1 origin of the L2 block.
+    pub epoch_hash: B256,
+    /// Timestamp of the L2 block.
+    pub timestamp: u64,
+    /// EIP-2718 encoded transactions of the L2 block, following the deposits.
+    pub transactions: Vec<Bytes>,
+}
+
+impl SingularBatch {
+    /// Decodes the batches of the given decompressed channel data.
+    pub fn decode_all(data: &[u8]) -> Result<Vec<SingularBatch>> {
+        let mut buf = data;
+        let mut batches = Vec::new();
+        while !buf.is_empty() {
+            let batch = rlp_payload(&mut buf, false)?;
+            let (version, mut content) = batch.split_first().context("empty batch")?;
+            match *version {
+                SINGULAR_BATCH_VERSION => batches.push(SingularBatch::decode(&mut content)?),
+                SPAN_BATCH_VERSION => bail!("unsupported span batch"),
+                _ => bail!("invalid batch version: {}", version),
+            }
+            ensure!(content.is_empty(), "trailing batch data");
+        }
+
+        Ok(batches)
+    }
+
+    /// Decodes the RLP list of a singular batch and advances the buffer past it.
+    fn decode(buf: &mut &[u8]) -> Result<SingularBatch> {
+        let mut fields = rlp_payload(buf, true)?;
+        let batch = SingularBatch {
+            parent_hash: rlp_decode(&mut fields)?,
+            epoch_num: rlp_decode(&mut fields)?,
+            epoch_hash: rlp_decode(&mut fields)?,
+            timestamp: rlp_decode(&mut fields)?,
+            transactions: {
+                let mut list = rlp_payload(&mut fields, true)?;
+                let mut transactions = Vec::new();
+                while !list.is_empty() {
+                    transactions.push(rlp_payload(&mut list, false)?.to_vec().into());
+                }
+                transactions
+            },
+        };
+        ensure!(fields.is_empty(), "unexpected batch fields");
+
+        Ok(batch)
+    }
+
+    /// Verifies that the batch sequences the L2 block with the given header, L1 attributes
+    /// and transactions, i.e. that exactly the batched transactions follow the deposits.
+    pub fn verify_block(
+        &self,
+        header: &Header,
+        l1_info: &L1BlockInfo,
+        transactions: &[Transaction<OptimismTxEssence>],
+    ) -> Result<()> {
+        ensure!(
+            self.parent_hash == header.parent_hash,
+            "parent hash mismatch: {} (expected {})",
+            self.parent_hash,
+            header.parent_hash
+        );
+        ensure!(
+            U256::from(self.timestamp) == header.timestamp,
+            "timestamp mismatch: {} (expected {})",
+            self.timestamp,
+            header.timestamp
+        );
+        ensure!(
+            self.epoch_num == l1_info.number && self.epoch_hash == l1_info.hash,
+            "epoch mismatch: {} (expected {})",
+            self.epoch_hash,
+            l1_info.hash
+        );
+
+        let deposits = transactions
+            .iter()
+            .take_while(|tx| tx.essence.tx_type() == DEPOSIT_TX_TYPE)
+            .count();
+        let sequenced = &transactions[deposits..];
+        ensure!(
+            sequenced.len() == self.transactions.len(),
+            "expected {} sequenced transactions, found {}",
+            self.transactions.len(),
+            sequenced.len()
+        );
+        for (tx_no, (tx, batched)) in zip(sequenced, &self.transactions).enumerate() {
+            let tx_no = deposits + tx_no;
+            // deposits can only be derived from L1 events
+            ensure!(
+                tx.essence.tx_type() != DEPOSIT_TX_TYPE,
+                "unexpected deposit: {}",
+                tx_no
+            );
+            ensure!(
+                tx.to_rlp() == batched[..],
+                "transaction {} does not match the batch",
+                tx_no
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Derives the singular batches that the `batcher` posted within the given consecutive L1
+/// blocks.
+///
+/// The transactions of each block are verified against its header and the blocks must
+/// form a chain, so that the batches are backed by the hash of the last block, which the
+/// caller must trust or verify. As in the rollup node, invalid batcher data
+/// and invalid channels are skipped. Channels containing span batches are not supported
+/// and skipped as well.
+pub fn derive_batches(
+    config: &OptimismConfig,
+    batcher: Address,
+    l1_blocks: &[L1Block],
+) -> Result<Vec<SingularBatch>> {
+    let mut channels: HashMap<ChannelId, Channel> = HashMap::new();
+    let mut batches = Vec::new();
+    for (block_no, block) in l1_blocks.iter().enumerate() {
+        let header = &block.header;
+        if block_no > 0 {
+            ensure!(
+                header.parent_hash == l1_blocks[block_no - 1].header.hash(),
+                "L1 block {} is not a child of the previous block",
+                header.number
+            );
+        }
+        let mut tx_trie = MptNode::default();
+        for (tx_no, tx) in block.transactions.iter().enumerate() {
+            tx_trie
+                .insert_rlp(&tx_no.to_rlp(), tx)
+                .context("failed to insert transaction")?;
+        }
+        ensure!(
+            tx_trie.hash() == header.transactions_root,
+            "transactions of L1 block {} do not match its header",
+            header.number
+        );
+
+        channels.retain(|_, channel| !channel.is_timed_out(header.number));
+        for tx in &block.transactions {
+            if tx.essence.to() != Some(config.batch_inbox) {
+                continue;
+            }
+            // only the batcher of the system config may submit batches
+            if tx.recover_from().ok() != Some(batcher) {
+                continue;
+            }
+            let Ok(frames) = Frame::decode_all(tx.essence.data()) else {
+                continue;
+            };
+            for frame in frames {
+                let channel_id = frame.channel_id;
+                let channel = channels
+                    .entry(channel_id)
+                    .or_insert_with(|| Channel::new(header.number));
+                channel.add_frame(frame);
+                if channel.is_ready() {
+                    let channel = channels.remove(&channel_id).unwrap();
+                    if let Ok(channel_batches) = channel.batches() {
+                        batches.extend(channel_batches);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(batches)
+}
+
+/// Decompresses the zlib stream of a channel.
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    // the lower nibble of the first byte of a zlib stream encodes the compression method
+    let method = data.first().context("empty channel")? & 0x0F;
+    ensure!(
+        method == 8 || method == 15,
+        "unsupported compression: {}",
+        method
+    );
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, MAX_RLP_BYTES_PER_CHANNEL)
+        .map_err(|err| anyhow!("invalid channel data: {}", err))
+}
+
+/// Returns the payload of the next RLP item, which must be a list if `list` is set, and
+/// advances the buffer past it.
+fn rlp_payload<'a>(buf: &mut &'a [u8], list: bool) -> Result<&'a [u8]> {
+    let header = rlp::Header::decode(buf).map_err(|err| anyhow!("invalid RLP: {}", err))?;
+    ensure!(header.list == list, "unexpected RLP item");
+    ensure!(buf.len() >= header.payload_length, "RLP item too short");
+    let (payload, rest) = buf.split_at(header.payload_length);
+    *buf = rest;
+
+    Ok(payload)
+}
+
+/// Decodes the next RLP item and advances the buffer past it.
+fn rlp_decode<T: rlp::Decodable>(buf: &mut &[u8]) -> Result<T> {
+    T::decode(buf).map_err(|err| anyhow!("invalid RLP: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::{
+        k256::ecdsa::SigningKey,
+        types::{TransactionRequest, H160},
+    };
+    use zeth_primitives::{
+        address, hex,
+        keccak::keccak,
+        rlp::Encodable,
+        signature::TxSignature,
+        transactions::{
+            ethereum::{TransactionKind, TxEssenceLegacy},
+            optimism::TxEssenceOptimismDeposited,
+        },
+    };
+
+    use super::*;
+
+    /// Frame 1 of the channel decoded in [decode_channel].
+    const FRAME_1: &str = concat!(
+        "0102030405060708090a0b0c0d0e0f10000100000019",
+        "0190b240890068627e71a2e5deda7def9b980e00004a45118b",
+        "01",
+    );
+    /// Frame 0 of the channel decoded in [decode_channel].
+    const FRAME_0: &str = concat!(
+        "0102030405060708090a0b0c0d0e0f1000000000000a",
+        "789cdb11c4f0c37f8120",
+        "00",
+    );
+
+    /// Returns a legacy transaction with the given call data, signed with the given key.
+    fn signed_tx(key: &SigningKey, to: Address, data: &str) -> Transaction<EthereumTxEssence> {
+        let data = hex::decode(data).unwrap();
+        let request = TransactionRequest::new()
+            .nonce(0)
+            .gas_price(0)
+            .gas(100_000)
+            .to(H160(to.into_array()))
+            .value(0)
+            .data(data.clone());
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(request.sighash().as_bytes())
+            .unwrap();
+        Transaction {
+            essence: EthereumTxEssence::Legacy(TxEssenceLegacy {
+                gas_limit: U256::from(100_000),
+                to: TransactionKind::Call(to),
+                data: data.into(),
+                ..Default::default()
+            }),
+            signature: TxSignature {
+                v: 27 + recovery_id.is_y_odd() as u64,
+                r: U256::from_be_bytes::<32>(signature.r().to_bytes().into()),
+                s: U256::from_be_bytes::<32>(signature.s().to_bytes().into()),
+            },
+        }
+    }
+
+    /// Returns the address of the given key.
+    fn key_address(key: &SigningKey) -> Address {
+        let public_key = key.verifying_key().to_encoded_point(false);
+        Address::from_slice(&keccak(&public_key.as_bytes()[1..])[12..])
+    }
+
+    /// Encodes the given singular batch as an item of the channel data.
+    fn encode_batch(batch: &SingularBatch) -> Vec<u8> {
+        let mut fields = Vec::new();
+        batch.parent_hash.encode(&mut fields);
+        batch.epoch_num.encode(&mut fields);
+        batch.epoch_hash.encode(&mut fields);
+        batch.timestamp.encode(&mut fields);
+        batch.transactions.encode(&mut fields);
+        let mut content = vec![SINGULAR_BATCH_VERSION];
+        rlp::Header {
+            list: true,
+            payload_length: fields.len(),
+        }
+        .encode(&mut content);
+        content.extend(fields);
+        content.as_slice().to_rlp()
+    }
+
+    /// Returns the call data of a batcher transaction containing the whole channel with
+    /// the given uncompressed data in a single frame.
+    fn channel_data(channel_id: ChannelId, data: &[u8]) -> String {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+        let mut frame = vec![DERIVATION_VERSION_0];
+        frame.extend(channel_id);
+        frame.extend(0u16.to_be_bytes());
+        frame.extend((compressed.len() as u32).to_be_bytes());
+        frame.extend(compressed);
+        frame.push(1);
+        hex::encode(frame)
+    }
+
+    /// Returns the L1 blocks with the given transactions, chained on top of each other.
+    fn l1_blocks(transactions: Vec<Vec<Transaction<EthereumTxEssence>>>) -> Vec<L1Block> {
+        let mut blocks: Vec<L1Block> = Vec::new();
+        for (number, transactions) in transactions.into_iter().enumerate() {
+            let mut tx_trie = MptNode::default();
+            for (tx_no, tx) in transactions.iter().enumerate() {
+                tx_trie.insert_rlp(&tx_no.to_rlp(), tx).unwrap();
+            }
+            let header = Header {
+                parent_hash: blocks
+                    .last()
+                    .map(|block| block.header.hash())
+                    .unwrap_or_default(),
+                number: number as u64 + 1,
+                transactions_root: tx_trie.hash(),
+                ..Default::default()
+            };
+            blocks.push(L1Block {
+                header,
+                transactions,
+            });
+        }
+        blocks
+    }
+
+    #[test]
+    fn decode_channel() {
+        // frames 1 and 0 of a channel, posted out of order in a single transaction
+        let data = hex::decode(concat!(
+            "00",
+            "0102030405060708090a0b0c0d0e0f10000100000019",
+            "0190b240890068627e71a2e5deda7def9b980e00004a45118b",
+            "01",
+            "0102030405060708090a0b0c0d0e0f1000000000000a",
+            "789cdb11c4f0c37f8120",
+            "00",
+        ))
+        .unwrap();
+        let frames = Frame::decode_all(&data).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].number, 1);
+        assert!(frames[0].is_last);
+
+        let mut channel = Channel::new(0);
+        let mut frames = frames.into_iter();
+        channel.add_frame(frames.next().unwrap());
+        assert!(!channel.is_ready());
+        channel.add_frame(frames.next().unwrap());
+        assert!(channel.is_ready());
+
+        let batches = channel.batches().unwrap();
+        assert_eq!(
+            batches,
+            vec![SingularBatch {
+                parent_hash: B256::repeat_byte(0x11),
+                epoch_num: 100,
+                epoch_hash: B256::repeat_byte(0x22),
+                timestamp: 1000,
+                transactions: vec![
+                    hex::decode("deadbeef").unwrap().into(),
+                    hex::decode("02c0").unwrap().into(),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn derive_across_blocks() {
+        let config = OptimismConfig {
+            batch_inbox: address!("ff00000000000000000000000000000000000010"),
+            ..Default::default()
+        };
+        let batcher_key = SigningKey::from_slice(&[0x01; 32]).unwrap();
+        let batcher = key_address(&batcher_key);
+        let other_key = SigningKey::from_slice(&[0x02; 32]).unwrap();
+
+        // the frames of the channel are posted in two blocks, while the whole channel
+        // posted by anyone else and the frames sent elsewhere are ignored
+        let blocks = l1_blocks(vec![
+            vec![
+                signed_tx(
+                    &other_key,
+                    config.batch_inbox,
+                    &format!("00{FRAME_1}{FRAME_0}"),
+                ),
+                signed_tx(&batcher_key, config.batch_inbox, &format!("00{FRAME_1}")),
+            ],
+            vec![
+                signed_tx(&batcher_key, Address::ZERO, &format!("00{FRAME_0}")),
+                signed_tx(&batcher_key, config.batch_inbox, &format!("00{FRAME_0}")),
+            ],
+        ]);
+        let batches = derive_batches(&config, batcher, &blocks).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].timestamp, 1000);
+        // the channel is incomplete without the frame of the first block
+        assert!(derive_batches(&config, batcher, &blocks[1..])
+            .unwrap()
+            .is_empty());
+
+        // the transactions must match the header
+        let mut tampered = blocks.clone();
+        tampered[1].transactions.pop();
+        assert!(derive_batches(&config, batcher, &tampered).is_err());
+
+        // the blocks must form a chain
+        let mut tampered = blocks;
+        tampered[1].header.parent_hash = B256::ZERO;
+        assert!(derive_batches(&config, batcher, &tampered).is_err());
+    }
+
+    #[test]
+    fn derive_and_verify_block() {
+        let config = OptimismConfig {
+            batch_inbox: address!("ff00000000000000000000000000000000000010"),
+            ..Default::default()
+        };
+        let batcher_key = SigningKey::from_slice(&[0x01; 32]).unwrap();
+        let batcher = key_address(&batcher_key);
+        let l1_info = L1BlockInfo {
+            number: 100,
+            hash: B256::repeat_byte(0x22),
+            ..Default::default()
+        };
+
+        // the L2 block consists of the L1 attributes deposit and a sequenced transaction
+        let user_key = SigningKey::from_slice(&[0x03; 32]).unwrap();
+        let sequenced = signed_tx(&user_key, Address::ZERO, "deadbeef");
+        let transactions = vec![
+            Transaction {
+                essence: OptimismTxEssence::OptimismDeposited(TxEssenceOptimismDeposited::default()),
+                signature: TxSignature {
+                    v: 0,
+                    r: U256::ZERO,
+                    s: U256::ZERO,
+                },
+            },
+            Transaction {
+                essence: OptimismTxEssence::Ethereum(sequenced.essence),
+                signature: sequenced.signature,
+            },
+        ];
+        let header = Header {
+            parent_hash: B256::repeat_byte(0x11),
+            number: 1000,
+            timestamp: U256::from(2000),
+            ..Default::default()
+        };
+
+        let batch = SingularBatch {
+            parent_hash: header.parent_hash,
+            epoch_num: l1_info.number,
+            epoch_hash: l1_info.hash,
+            timestamp: 2000,
+            transactions: vec![transactions[1].to_rlp().into()],
+        };
+        // a channel with a span batch is skipped as a whole
+        let mut span_channel = encode_batch(&batch);
+        span_channel.extend([SPAN_BATCH_VERSION, 0xc0].as_slice().to_rlp());
+        let blocks = l1_blocks(vec![vec![
+            signed_tx(
+                &batcher_key,
+                config.batch_inbox,
+                &channel_data([0x01; CHANNEL_ID_LENGTH], &span_channel),
+            ),
+            signed_tx(
+                &batcher_key,
+                config.batch_inbox,
+                &channel_data([0x02; CHANNEL_ID_LENGTH], &encode_batch(&batch)),
+            ),
+        ]]);
+
+        let batches = derive_batches(&config, batcher, &blocks).unwrap();
+        assert_eq!(batches, vec![batch.clone()]);
+        batches[0]
+            .verify_block(&header, &l1_info, &transactions)
+            .unwrap();
+
+        // the block must contain exactly the batched transactions
+        batch
+            .verify_block(&header, &l1_info, &transactions[..1])
+            .unwrap_err();
+        let mut extra = transactions.clone();
+        extra.push(transactions[1].clone());
+        batch.verify_block(&header, &l1_info, &extra).unwrap_err();
+        // and belong to the epoch of the batch
+        let other_epoch = L1BlockInfo {
+            number: 101,
+            ..l1_info.clone()
+        };
+        batch
+            .verify_block(&header, &other_epoch, &transactions)
+            .unwrap_err();
+        // where deposits cannot be sequenced
+        let mut deposit_batch = batch.clone();
+        deposit_batch
+            .transactions
+            .push(transactions[0].to_rlp().into());
+        let mut deposits = transactions.clone();
+        deposits.push(transactions[0].clone());
+        deposit_batch
+            .verify_block(&header, &l1_info, &deposits)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn invalid_frames() {
+        // unknown derivation version
+        assert!(Frame::decode_all(&[0x01]).is_err());
+        // no frames
+        assert!(Frame::decode_all(&[0x00]).is_err());
+        // invalid is_last flag
+        let mut data = vec![0x00];
+        data.extend([0u8; CHANNEL_ID_LENGTH]);
+        data.extend([0, 0, 0, 0, 0, 1, 0xff, 2]);
+        assert!(Frame::decode_all(&data).is_err());
+    }
+
+    #[test]
+    fn channel_timeout() {
+        let channel = Channel::new(100);
+        assert!(!channel.is_timed_out(100 + CHANNEL_TIMEOUT));
+        assert!(channel.is_timed_out(100 + CHANNEL_TIMEOUT + 1));
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Light verification of the Ethash proof-of-work used before the Paris update.
+//!
+//! Verifying a seal only requires the cache of its epoch, from which the 128 accessed
+//! items of the full dataset are derived on demand. Generating the cache is expensive,
+//! as it takes between 16 MB and 84 MB of memory and four Keccak-512 hashes per 64 bytes,
+//! so it should be shared by all headers of the same epoch.
+//!
+//! For reference, the cache of epoch 0 has 262,139 items and takes about 1.05M hashes,
+//! the cache of epoch 517 at the Paris update has 1,320,947 items and takes about 5.28M
+//! hashes. Natively, this takes about 1.1 s and 5.2 s respectively, while deriving the
+//! dataset items of a seal takes about 6 ms. The cache therefore dominates the cycle
+//! count of proving a block. As it has not been measured in the zkVM yet, this module is
+//! only available with the opt-in `ethash` feature.
+
+use zeth_primitives::{
+    block::Header,
+    keccak::{keccak, keccak512},
+    B256, U256,
+};
+
+/// Number of blocks sharing the same cache and dataset.
+pub const EPOCH_LENGTH: u64 = 30_000;
+
+const CACHE_BYTES_INIT: u64 = 1 << 24;
+const CACHE_BYTES_GROWTH: u64 = 1 << 17;
+const DATASET_BYTES_INIT: u64 = 1 << 30;
+const DATASET_BYTES_GROWTH: u64 = 1 << 23;
+const HASH_BYTES: u64 = 64;
+const MIX_BYTES: u64 = 128;
+const DATASET_PARENTS: u32 = 256;
+const CACHE_ROUNDS: usize = 3;
+const ACCESSES: u32 = 64;
+const FNV_PRIME: u32 = 0x01000193;
+
+/// 64-byte item of the cache or the dataset as little-endian words.
+type Node = [u32; 16];
+
+/// Returns the epoch of the given block number.
+#[inline]
+pub fn epoch(number: u64) -> u64 {
+    number / EPOCH_LENGTH
+}
+
+/// Returns the size of the cache in bytes.
+pub fn cache_size(epoch: u64) -> u64 {
+    let mut size = CACHE_BYTES_INIT + CACHE_BYTES_GROWTH * epoch - HASH_BYTES;
+    while !is_prime(size / HASH_BYTES) {
+        size -= 2 * HASH_BYTES;
+    }
+    size
+}
+
+/// Returns the size of the full dataset in bytes.
+pub fn dataset_size(epoch: u64) -> u64 {
+    let mut size = DATASET_BYTES_INIT + DATASET_BYTES_GROWTH * epoch - MIX_BYTES;
+    while !is_prime(size / MIX_BYTES) {
+        size -= 2 * MIX_BYTES;
+    }
+    size
+}
+
+/// Ethash cache of an epoch.
+pub struct EthashCache {
+    epoch: u64,
+    nodes: Vec<Node>,
+    dataset_rows: u32,
+}
+
+impl EthashCache {
+    /// Generates the cache of the given epoch.
+    pub fn new(epoch: u64) -> Self {
+        let mut seed = [0u8; 32];
+        for _ in 0..epoch {
+            seed = keccak(seed);
+        }
+
+        // sequentially fill the cache
+        let n = (cache_size(epoch) / HASH_BYTES) as usize;
+        let mut nodes = Vec::with_capacity(n);
+        nodes.push(hash_node(seed));
+        for i in 1..n {
+            nodes.push(hash_node(node_bytes(&nodes[i - 1])));
+        }
+        // apply the low-round RandMemoHash
+        for _ in 0..CACHE_ROUNDS {
+            for i in 0..n {
+                let v = nodes[i][0] as usize % n;
+                let mut node = nodes[(i + n - 1) % n];
+                node.iter_mut()
+                    .zip(&nodes[v])
+                    .for_each(|(word, other)| *word ^= other);
+                nodes[i] = hash_node(node_bytes(&node));
+            }
+        }
+
+        EthashCache {
+            epoch,
+            nodes,
+            dataset_rows: (dataset_size(epoch) / MIX_BYTES) as u32,
+        }
+    }
+
+    /// Returns the epoch of the cache.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Computes the mix hash and the result of the proof-of-work for the given seal hash
+    /// and nonce.
+    pub fn hashimoto(&self, seal_hash: B256, nonce: u64) -> (B256, B256) {
+        let mut seed = [0u8; 40];
+        seed[..32].copy_from_slice(seal_hash.as_slice());
+        seed[32..].copy_from_slice(&nonce.to_le_bytes());
+        let seed = keccak512(seed);
+        let seed_head = u32::from_le_bytes(seed[..4].try_into().unwrap());
+
+        // start the mix with the replicated seed
+        let mut mix = [0u32; 32];
+        for (i, word) in mix.iter_mut().enumerate() {
+            let j = 4 * (i % 16);
+            *word = u32::from_le_bytes(seed[j..j + 4].try_into().unwrap());
+        }
+        // mix in random rows of the dataset
+        for i in 0..ACCESSES {
+            let row = fnv(i ^ seed_head, mix[i as usize % mix.len()]) % self.dataset_rows;
+            let items = [self.dataset_item(2 * row), self.dataset_item(2 * row + 1)];
+            mix.iter_mut()
+                .zip(items.iter().flatten())
+                .for_each(|(word, item)| *word = fnv(*word, *item));
+        }
+        // compress the mix
+        let mut mix_hash = B256::ZERO;
+        for i in 0..8 {
+            let words = &mix[4 * i..4 * i + 4];
+            let word = fnv(fnv(fnv(words[0], words[1]), words[2]), words[3]);
+            mix_hash[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+        }
+
+        let result = keccak([seed.as_slice(), mix_hash.as_slice()].concat());
+        (mix_hash, result.into())
+    }
+
+    /// Returns whether the Ethash seal, i.e. the `mix_hash` and `nonce`, of the header
+    /// satisfies its difficulty.
+    pub fn verify_seal(&self, header: &Header) -> bool {
+        if epoch(header.number) != self.epoch || header.difficulty == U256::ZERO {
+            return false;
+        }
+        let (mix_hash, result) =
+            self.hashimoto(header.seal_hash(), u64::from_be_bytes(header.nonce.0));
+        if mix_hash != header.mix_hash {
+            return false;
+        }
+
+        // the result must not exceed 2^256 / difficulty
+        let mut target = U256::MAX / header.difficulty;
+        if header.difficulty > U256::from(1) && header.difficulty.is_power_of_two() {
+            target += U256::from(1);
+        }
+        U256::from_be_bytes(result.0) <= target
+    }
+
+    /// Computes the item of the full dataset with the given index.
+    fn dataset_item(&self, index: u32) -> Node {
+        let n = self.nodes.len() as u32;
+        let mut mix = self.nodes[(index % n) as usize];
+        mix[0] ^= index;
+        let mut mix = hash_node(node_bytes(&mix));
+        for j in 0..DATASET_PARENTS {
+            let parent = fnv(index ^ j, mix[j as usize % mix.len()]) % n;
+            mix.iter_mut()
+                .zip(&self.nodes[parent as usize])
+                .for_each(|(word, other)| *word = fnv(*word, *other));
+        }
+        hash_node(node_bytes(&mix))
+    }
+}
+
+#[inline]
+fn fnv(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(FNV_PRIME) ^ b
+}
+
+fn is_prime(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|i| i * i <= n).all(|i| n % i != 0)
+}
+
+#[inline]
+fn hash_node(data: impl AsRef<[u8]>) -> Node {
+    let hash = keccak512(data);
+    let mut node = Node::default();
+    for (i, word) in node.iter_mut().enumerate() {
+        *word = u32::from_le_bytes(hash[4 * i..4 * i + 4].try_into().unwrap());
+    }
+    node
+}
+
+#[inline]
+fn node_bytes(node: &Node) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    for (i, word) in node.iter().enumerate() {
+        bytes[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{
+        address, b256, block::EMPTY_LIST_HASH, hex, trie::EMPTY_ROOT, Bytes, B64,
+    };
+
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(cache_size(0), 16776896);
+        assert_eq!(dataset_size(0), 1073739904);
+        assert_eq!(cache_size(1), 16907456);
+        assert_eq!(dataset_size(1), 1082130304);
+        assert_eq!(cache_size(517), 84540608);
+        assert_eq!(dataset_size(517), 5410650496);
+    }
+
+    #[test]
+    fn verify_first_block() {
+        // first block after the genesis
+        let mut header = Header {
+            parent_hash: b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"),
+            ommers_hash: EMPTY_LIST_HASH,
+            beneficiary: address!("05a56e2d52c817161883f50c441c3228cfe54d9f"),
+            state_root: b256!("d67e4d450343046425ae4271474353857ab860dbc0a1dde64b41b5cd3a532bf3"),
+            transactions_root: EMPTY_ROOT,
+            receipts_root: EMPTY_ROOT,
+            difficulty: U256::from(17171480576u64),
+            number: 1,
+            gas_limit: U256::from(5000),
+            timestamp: U256::from(1438269988),
+            extra_data: Bytes::from_static(b"Geth/v1.0.0/linux/go1.4.2"),
+            mix_hash: b256!("969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f59"),
+            nonce: B64::new(0x539bd4979fef1ec4u64.to_be_bytes()),
+            ..Default::default()
+        };
+        assert_eq!(
+            header.hash(),
+            b256!("88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6")
+        );
+
+        let cache = EthashCache::new(epoch(header.number));
+        assert_eq!(
+            node_bytes(&cache.nodes[0]),
+            hex!("5e493e76a1318e50815c6ce77950425532964ebbb8dcf94718991fa9a82eaf37658de68ca6fe078884e803da3a26a4aa56420a6867ebcd9ab0f29b08d1c48fed")
+        );
+        let (mix_hash, result) =
+            cache.hashimoto(header.seal_hash(), u64::from_be_bytes(header.nonce.0));
+        assert_eq!(mix_hash, header.mix_hash);
+        assert_eq!(
+            result,
+            b256!("000000002bc095dd4de049873e6302c3f14a7f2e5b5a1f60cdf1f1798164d610")
+        );
+        assert!(cache.verify_seal(&header));
+
+        // any other nonce results in a different mix hash
+        header.nonce = B64::new(0x539bd4979fef1ec5u64.to_be_bytes());
+        assert!(!cache.verify_seal(&header));
+    }
+}
@@ -12,242 +12,314 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt::Debug, mem::take};
+use std::fmt::Debug;
 
-use anyhow::{anyhow, bail, Context};
 #[cfg(not(target_os = "zkvm"))]
 use log::debug;
+#[cfg(not(target_os = "zkvm"))]
+use revm::Inspector;
 use revm::{
-    primitives::{Account, Address, ResultAndState, SpecId, TransactTo, TxEnv},
+    primitives::{
+        Account, Address, Env, ExecutionResult, SpecId, StorageSlot, TransactTo, TxEnv, B256,
+        KECCAK_EMPTY, MAX_BLOB_GAS_PER_BLOCK,
+    },
     Database, DatabaseCommit, EVM,
 };
-use ruint::aliases::U256;
+use ruint::{aliases::U256, uint};
 use zeth_primitives::{
-    receipt::Receipt,
+    block::Header,
     transactions::{
         ethereum::{EthereumTxEssence, TransactionKind},
-        TxEssence,
+        Transaction, TxEssence,
     },
-    trie::MptNode,
-    Bloom, RlpBytes,
 };
 
+#[cfg(not(target_os = "zkvm"))]
+use crate::execution::{inspect_with_hooks, InspectTxExecStrategy};
 use crate::{
-    block_builder::BlockBuilder,
-    consts,
-    consts::{GWEI_TO_WEI, MIN_SPEC_ID},
-    execution::TxExecStrategy,
-    guest_mem_forget,
+    block_builder::{BlockBuildError, BlockBuilder},
+    consts::{
+        ChainSpec, BEACON_ROOTS_ADDRESS, BEACON_ROOTS_HISTORY_BUFFER_LENGTH,
+        BYZANTIUM_BLOCK_REWARD, CONSTANTINOPLE_BLOCK_REWARD, MAX_OMMER_DEPTH,
+    },
+    execution::{execute_with_hooks, TxExecHooks, TxExecStrategy},
+    input::Input,
 };
 
 pub struct EthTxExecStrategy {}
 
 impl TxExecStrategy<EthereumTxEssence> for EthTxExecStrategy {
     fn execute_transactions<D>(
-        mut block_builder: BlockBuilder<D, EthereumTxEssence>,
-    ) -> anyhow::Result<BlockBuilder<D, EthereumTxEssence>>
+        block_builder: BlockBuilder<D, EthereumTxEssence>,
+    ) -> Result<BlockBuilder<D, EthereumTxEssence>, BlockBuildError>
     where
         D: Database + DatabaseCommit,
-        <D as Database>::Error: Debug,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
     {
-        let header = block_builder
-            .header
-            .as_mut()
-            .expect("Header is not initialized");
-        // Compute the spec id
-        let spec_id = block_builder.chain_spec.spec_id(header.number);
-        if !SpecId::enabled(spec_id, MIN_SPEC_ID) {
-            bail!(
-                "Invalid protocol version: expected >= {:?}, got {:?}",
-                MIN_SPEC_ID,
-                spec_id,
-            )
-        }
+        execute_with_hooks::<EthTxExecHooks, _, _, _>(block_builder, |evm| evm.transact())
+    }
+}
 
-        #[cfg(not(target_os = "zkvm"))]
-        {
-            use chrono::{TimeZone, Utc};
-            use log::info;
-            let dt = Utc
-                .timestamp_opt(block_builder.input.timestamp.try_into().unwrap(), 0)
-                .unwrap();
-
-            info!("Block no. {}", header.number);
-            info!("  EVM spec ID: {:?}", spec_id);
-            info!("  Timestamp: {}", dt);
-            info!("  Transactions: {}", block_builder.input.transactions.len());
-            info!("  Withdrawals: {}", block_builder.input.withdrawals.len());
-            info!("  Fee Recipient: {:?}", block_builder.input.beneficiary);
-            info!("  Gas limit: {}", block_builder.input.gas_limit);
-            info!("  Base fee per gas: {}", header.base_fee_per_gas);
-            info!("  Extra data: {:?}", block_builder.input.extra_data);
+#[cfg(not(target_os = "zkvm"))]
+impl InspectTxExecStrategy<EthereumTxEssence> for EthTxExecStrategy {
+    fn execute_transactions_with_inspector<D, I, F>(
+        block_builder: BlockBuilder<D, EthereumTxEssence>,
+        new_inspector: F,
+    ) -> Result<(BlockBuilder<D, EthereumTxEssence>, Vec<I>), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+        I: Inspector<D>,
+        F: FnMut() -> I,
+    {
+        inspect_with_hooks::<EthTxExecHooks, _, _, _, _>(block_builder, new_inspector)
+    }
+}
+
+/// Execution hooks of Ethereum, which only need to track the blob gas of the block.
+#[derive(Debug, Clone)]
+pub struct EthTxExecHooks {
+    /// Blob gas used over all transactions.
+    blob_gas_used: u64,
+    /// Whether blob gas is accounted in the header, i.e. after the Cancun update.
+    cancun: bool,
+}
+
+impl TxExecHooks<EthereumTxEssence> for EthTxExecHooks {
+    fn before_block<D>(
+        _chain_spec: &ChainSpec,
+        _input: &Input<EthereumTxEssence>,
+        _header: &Header,
+        evm: &mut EVM<D>,
+    ) -> Result<Self, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        Ok(EthTxExecHooks {
+            blob_gas_used: 0,
+            cancun: evm.env.cfg.spec_id >= SpecId::CANCUN,
+        })
+    }
+
+    fn before_tx<D>(
+        &mut self,
+        _tx_no: usize,
+        _tx: &Transaction<EthereumTxEssence>,
+        _tx_from: Address,
+        _evm: &mut EVM<D>,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        Ok(())
+    }
+
+    fn fill_env(
+        &mut self,
+        tx_no: usize,
+        env: &mut Env,
+        essence: &EthereumTxEssence,
+        tx_from: Address,
+    ) -> Result<(), BlockBuildError> {
+        fill_eth_tx_env(tx_no, &mut env.tx, essence, tx_from)
+    }
+
+    fn after_tx<D>(
+        &mut self,
+        tx_no: usize,
+        _tx: &Transaction<EthereumTxEssence>,
+        result: &ExecutionResult,
+        evm: &mut EVM<D>,
+    ) -> Result<U256, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        // verify the blob gas of the block
+        self.blob_gas_used += evm.env.tx.get_total_blob_gas();
+        if self.blob_gas_used > MAX_BLOB_GAS_PER_BLOCK {
+            return Err(BlockBuildError::BlobGasExceedsBlock { tx_no });
         }
 
-        // initialize the EVM
-        let mut evm = EVM::new();
-
-        // set the EVM configuration
-        evm.env.cfg.chain_id = block_builder.chain_spec.chain_id();
-        evm.env.cfg.spec_id = spec_id;
-
-        // set the EVM block environment
-        evm.env.block.number = header.number.try_into().unwrap();
-        evm.env.block.coinbase = block_builder.input.beneficiary;
-        evm.env.block.timestamp = header.timestamp;
-        evm.env.block.difficulty = U256::ZERO;
-        evm.env.block.prevrandao = Some(header.mix_hash);
-        evm.env.block.basefee = header.base_fee_per_gas;
-        evm.env.block.gas_limit = block_builder.input.gas_limit;
-
-        evm.database(block_builder.db.take().unwrap());
-
-        // bloom filter over all transaction logs
-        let mut logs_bloom = Bloom::default();
-        // keep track of the gas used over all transactions
-        let mut cumulative_gas_used = consts::ZERO;
-
-        // process all the transactions
-        let mut tx_trie = MptNode::default();
-        let mut receipt_trie = MptNode::default();
-        for (tx_no, tx) in take(&mut block_builder.input.transactions)
-            .into_iter()
-            .enumerate()
-        {
-            // verify the transaction signature
-            let tx_from = tx
-                .recover_from()
-                .with_context(|| format!("Error recovering address for transaction {}", tx_no))?;
+        Ok(result.gas_used().try_into().unwrap())
+    }
 
-            #[cfg(not(target_os = "zkvm"))]
-            {
-                let tx_hash = tx.hash();
-                debug!("Tx no. {} (hash: {})", tx_no, tx_hash);
-                debug!("  Type: {}", tx.essence.tx_type());
-                debug!("  Fr: {:?}", tx_from);
-                debug!("  To: {:?}", tx.essence.to().unwrap_or_default());
-            }
+    fn after_block<D>(
+        self,
+        _input: &Input<EthereumTxEssence>,
+        header: &mut Header,
+        _db: &mut D,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        header.blob_gas_used = self.cancun.then(|| U256::from(self.blob_gas_used));
 
-            // verify transaction gas
-            let block_available_gas = block_builder.input.gas_limit - cumulative_gas_used;
-            if block_available_gas < tx.essence.gas_limit() {
-                bail!("Error at transaction {}: gas exceeds block limit", tx_no);
-            }
+        Ok(())
+    }
+}
 
-            // process the transaction
-            fill_eth_tx_env(&mut evm.env.tx, &tx.essence, tx_from);
-            let ResultAndState { result, state } = evm
-                .transact()
-                .map_err(|evm_err| anyhow!("Error at transaction {}: {:?}", tx_no, evm_err))?;
+/// Transaction execution for proof-of-work blocks before the Paris update.
+///
+/// After all transactions have been executed, the static block reward and the ommer
+/// rewards are credited to the respective beneficiaries.
+pub struct PowTxExecStrategy {}
 
-            let gas_used = result.gas_used().try_into().unwrap();
-            cumulative_gas_used = cumulative_gas_used.checked_add(gas_used).unwrap();
+impl TxExecStrategy<EthereumTxEssence> for PowTxExecStrategy {
+    fn execute_transactions<D>(
+        block_builder: BlockBuilder<D, EthereumTxEssence>,
+    ) -> Result<BlockBuilder<D, EthereumTxEssence>, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        execute_with_hooks::<PowTxExecHooks, _, _, _>(block_builder, |evm| evm.transact())
+    }
+}
 
-            #[cfg(not(target_os = "zkvm"))]
-            debug!("  Ok: {:?}", result);
-
-            // create the receipt from the EVM result
-            let receipt = Receipt::new(
-                tx.essence.tx_type(),
-                result.is_success(),
-                cumulative_gas_used,
-                result.logs().into_iter().map(|log| log.into()).collect(),
-            );
-
-            // accumulate logs to the block bloom filter
-            logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
-
-            // Add receipt and tx to tries
-            let trie_key = tx_no.to_rlp();
-            tx_trie
-                .insert_rlp(&trie_key, tx)
-                .context("failed to insert transaction")?;
-            receipt_trie
-                .insert_rlp(&trie_key, receipt)
-                .context("failed to insert receipt")?;
-
-            // update account states
-            #[cfg(not(target_os = "zkvm"))]
-            for (address, account) in &state {
-                if account.is_touched() {
-                    // log account
-                    debug!(
-                        "  State {:?} (is_selfdestructed={}, is_loaded_as_not_existing={}, is_created={}, is_empty={})",
-                        address,
-                        account.is_selfdestructed(),
-                        account.is_loaded_as_not_existing(),
-                        account.is_created(),
-                        account.is_empty(),
-                    );
-                    // log balance changes
-                    debug!(
-                        "     After balance: {} (Nonce: {})",
-                        account.info.balance, account.info.nonce
-                    );
-
-                    // log state changes
-                    for (addr, slot) in &account.storage {
-                        if slot.is_changed() {
-                            debug!("    Storage address: {:?}", addr);
-                            debug!("      Before: {:?}", slot.original_value());
-                            debug!("       After: {:?}", slot.present_value());
-                        }
-                    }
-                }
-            }
+#[cfg(not(target_os = "zkvm"))]
+impl InspectTxExecStrategy<EthereumTxEssence> for PowTxExecStrategy {
+    fn execute_transactions_with_inspector<D, I, F>(
+        block_builder: BlockBuilder<D, EthereumTxEssence>,
+        new_inspector: F,
+    ) -> Result<(BlockBuilder<D, EthereumTxEssence>, Vec<I>), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+        I: Inspector<D>,
+        F: FnMut() -> I,
+    {
+        inspect_with_hooks::<PowTxExecHooks, _, _, _, _>(block_builder, new_inspector)
+    }
+}
 
-            evm.db().unwrap().commit(state);
-        }
+/// Execution hooks of proof-of-work blocks, which extend the Ethereum hooks by crediting
+/// the block and ommer rewards at the end of the block.
+#[derive(Debug, Clone)]
+pub struct PowTxExecHooks {
+    eth: EthTxExecHooks,
+    spec_id: SpecId,
+}
 
-        let mut db = evm.take_db();
+impl TxExecHooks<EthereumTxEssence> for PowTxExecHooks {
+    fn before_block<D>(
+        chain_spec: &ChainSpec,
+        input: &Input<EthereumTxEssence>,
+        header: &Header,
+        evm: &mut EVM<D>,
+    ) -> Result<Self, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        Ok(PowTxExecHooks {
+            eth: EthTxExecHooks::before_block(chain_spec, input, header, evm)?,
+            spec_id: evm.env.cfg.spec_id,
+        })
+    }
 
-        // process withdrawals unconditionally after any transactions
-        let mut withdrawals_trie = MptNode::default();
-        for (i, withdrawal) in take(&mut block_builder.input.withdrawals)
-            .into_iter()
-            .enumerate()
-        {
-            // the withdrawal amount is given in Gwei
-            let amount_wei = GWEI_TO_WEI
-                .checked_mul(withdrawal.amount.try_into().unwrap())
-                .unwrap();
+    fn before_tx<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<EthereumTxEssence>,
+        tx_from: Address,
+        evm: &mut EVM<D>,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        self.eth.before_tx(tx_no, tx, tx_from, evm)
+    }
+
+    fn fill_env(
+        &mut self,
+        tx_no: usize,
+        env: &mut Env,
+        essence: &EthereumTxEssence,
+        tx_from: Address,
+    ) -> Result<(), BlockBuildError> {
+        self.eth.fill_env(tx_no, env, essence, tx_from)
+    }
+
+    fn after_tx<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<EthereumTxEssence>,
+        result: &ExecutionResult,
+        evm: &mut EVM<D>,
+    ) -> Result<U256, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        self.eth.after_tx(tx_no, tx, result, evm)
+    }
+
+    fn after_block<D>(
+        self,
+        input: &Input<EthereumTxEssence>,
+        header: &mut Header,
+        db: &mut D,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        let block_reward = if self.spec_id < SpecId::CONSTANTINOPLE {
+            BYZANTIUM_BLOCK_REWARD
+        } else {
+            CONSTANTINOPLE_BLOCK_REWARD
+        };
+
+        let mut beneficiary_reward = block_reward;
+        for (i, ommer) in input.ommers.iter().enumerate() {
+            // the ommer reward decreases with the depth of the ommer
+            let depth = header
+                .number
+                .checked_sub(ommer.number)
+                .filter(|depth| (1..=MAX_OMMER_DEPTH).contains(depth))
+                .ok_or(BlockBuildError::InvalidOmmer(i))?;
+            let ommer_reward = U256::from(8 - depth) * block_reward / uint!(8_U256);
 
             #[cfg(not(target_os = "zkvm"))]
             {
-                debug!("Withdrawal no. {}", withdrawal.index);
-                debug!("  Recipient: {:?}", withdrawal.address);
-                debug!("  Value: {}", amount_wei);
+                debug!("Ommer no. {}", ommer.number);
+                debug!("  Recipient: {:?}", ommer.beneficiary);
+                debug!("  Value: {}", ommer_reward);
             }
-            // Credit withdrawal amount
-            increase_account_balance(&mut db, withdrawal.address, amount_wei)?;
-            // Add withdrawal to trie
-            withdrawals_trie
-                .insert_rlp(&i.to_rlp(), withdrawal)
-                .context("failed to insert withdrawal")?;
+            increase_account_balance(db, ommer.beneficiary, ommer_reward)?;
+
+            // the beneficiary receives an additional reward for each included ommer
+            beneficiary_reward += block_reward / uint!(32_U256);
         }
 
-        // Update result header with computed values
-        header.transactions_root = tx_trie.hash();
-        header.receipts_root = receipt_trie.hash();
-        header.logs_bloom = logs_bloom;
-        header.gas_used = cumulative_gas_used;
-        header.withdrawals_root = if spec_id < SpecId::SHANGHAI {
-            None
-        } else {
-            Some(withdrawals_trie.hash())
-        };
+        #[cfg(not(target_os = "zkvm"))]
+        {
+            debug!("Block reward");
+            debug!("  Recipient: {:?}", header.beneficiary);
+            debug!("  Value: {}", beneficiary_reward);
+        }
+        increase_account_balance(db, header.beneficiary, beneficiary_reward)?;
 
-        // Leak memory, save cycles
-        guest_mem_forget([tx_trie, receipt_trie, withdrawals_trie]);
-        // Return block builder with updated database
-        Ok(block_builder.with_db(db))
+        self.eth.after_block(input, header, db)
     }
 }
 
-pub fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &EthereumTxEssence, caller: Address) {
+pub fn fill_eth_tx_env(
+    tx_no: usize,
+    tx_env: &mut TxEnv,
+    essence: &EthereumTxEssence,
+    caller: Address,
+) -> Result<(), BlockBuildError> {
+    let gas_limit = tx_gas_limit(tx_no, essence.gas_limit())?;
     match essence {
         EthereumTxEssence::Legacy(tx) => {
             tx_env.caller = caller;
-            tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
+            tx_env.gas_limit = gas_limit;
             tx_env.gas_price = tx.gas_price;
             tx_env.gas_priority_fee = None;
             tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
@@ -260,10 +332,12 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &EthereumTxEssence, caller:
             tx_env.chain_id = tx.chain_id;
             tx_env.nonce = Some(tx.nonce);
             tx_env.access_list.clear();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
         }
         EthereumTxEssence::Eip2930(tx) => {
             tx_env.caller = caller;
-            tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
+            tx_env.gas_limit = gas_limit;
             tx_env.gas_price = tx.gas_price;
             tx_env.gas_priority_fee = None;
             tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
@@ -276,10 +350,12 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &EthereumTxEssence, caller:
             tx_env.chain_id = Some(tx.chain_id);
             tx_env.nonce = Some(tx.nonce);
             tx_env.access_list = tx.access_list.clone().into();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
         }
         EthereumTxEssence::Eip1559(tx) => {
             tx_env.caller = caller;
-            tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
+            tx_env.gas_limit = gas_limit;
             tx_env.gas_price = tx.max_fee_per_gas;
             tx_env.gas_priority_fee = Some(tx.max_priority_fee_per_gas);
             tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
@@ -292,29 +368,51 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &EthereumTxEssence, caller:
             tx_env.chain_id = Some(tx.chain_id);
             tx_env.nonce = Some(tx.nonce);
             tx_env.access_list = tx.access_list.clone().into();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
+        }
+        EthereumTxEssence::Eip4844(tx) => {
+            tx_env.caller = caller;
+            tx_env.gas_limit = gas_limit;
+            tx_env.gas_price = tx.max_fee_per_gas;
+            tx_env.gas_priority_fee = Some(tx.max_priority_fee_per_gas);
+            tx_env.transact_to = TransactTo::Call(tx.to);
+            tx_env.value = tx.value;
+            tx_env.data = tx.data.clone();
+            tx_env.chain_id = Some(tx.chain_id);
+            tx_env.nonce = Some(tx.nonce);
+            tx_env.access_list = tx.access_list.clone().into();
+            tx_env.blob_hashes = tx.blob_versioned_hashes.clone();
+            tx_env.max_fee_per_blob_gas = Some(tx.max_fee_per_blob_gas);
         }
     };
+
+    Ok(())
+}
+
+/// Converts the gas limit of the transaction to the 64-bit value used by the EVM.
+pub(crate) fn tx_gas_limit(tx_no: usize, gas_limit: U256) -> Result<u64, BlockBuildError> {
+    gas_limit
+        .try_into()
+        .map_err(|_| BlockBuildError::InvalidTransaction {
+            tx_no,
+            reason: format!("gas limit {} exceeds 64 bits", gas_limit),
+        })
 }
 
 pub fn increase_account_balance<D>(
     db: &mut D,
     address: Address,
     amount_wei: U256,
-) -> anyhow::Result<()>
+) -> Result<(), BlockBuildError>
 where
     D: Database + DatabaseCommit,
-    <D as Database>::Error: Debug,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
 {
     // Read account from database
     let mut account: Account = db
         .basic(address)
-        .map_err(|db_err| {
-            anyhow!(
-                "Error increasing account balance for {}: {:?}",
-                address,
-                db_err
-            )
-        })?
+        .map_err(Into::<BlockBuildError>::into)?
         .unwrap_or_default()
         .into();
     // Credit withdrawal amount
@@ -325,3 +423,135 @@ where
 
     Ok(())
 }
+
+/// Applies the [EIP-4788](https://eips.ethereum.org/EIPS/eip-4788) system call, i.e. stores
+/// the timestamp and the parent beacon block root in the ring buffer of the beacon roots
+/// contract.
+pub fn apply_beacon_root_contract_call<D>(
+    db: &mut D,
+    timestamp: U256,
+    parent_beacon_block_root: B256,
+) -> Result<(), BlockBuildError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
+{
+    // Read account from database
+    let info = db
+        .basic(BEACON_ROOTS_ADDRESS)
+        .map_err(Into::<BlockBuildError>::into)?;
+    // The call does nothing if the contract has not been deployed
+    let Some(info) = info.filter(|info| info.code_hash != KECCAK_EMPTY) else {
+        return Ok(());
+    };
+
+    let timestamp_index = timestamp % U256::from(BEACON_ROOTS_HISTORY_BUFFER_LENGTH);
+    let root_index = timestamp_index + U256::from(BEACON_ROOTS_HISTORY_BUFFER_LENGTH);
+
+    let mut account: Account = info.into();
+    for (index, value) in [
+        (timestamp_index, timestamp),
+        (root_index, U256::from_be_bytes(parent_beacon_block_root.0)),
+    ] {
+        // Load the previous value so that the slot is part of the witness
+        let original = db
+            .storage(BEACON_ROOTS_ADDRESS, index)
+            .map_err(Into::<BlockBuildError>::into)?;
+        account
+            .storage
+            .insert(index, StorageSlot::new_changed(original, value));
+    }
+    account.mark_touch();
+    // Commit changes to database
+    db.commit([(BEACON_ROOTS_ADDRESS, account)].into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use revm::primitives::{AccountInfo, Bytecode};
+    use zeth_primitives::{b256, transactions::ethereum::TxEssenceLegacy, Bytes};
+
+    use super::*;
+    use crate::{
+        genesis::{child_of_genesis, Genesis, DEV_GENESIS},
+        mem_db::MemDb,
+    };
+
+    #[test]
+    fn beacon_root_contract_call() {
+        let timestamp = U256::from(1710338135);
+        let root = b256!("ecb1a6a8a0a0d2d8ae8eb09aefbbd54d3ac9e1e75fcc3e9a24a39c6e0c5dd4f1");
+        let timestamp_index = timestamp % U256::from(BEACON_ROOTS_HISTORY_BUFFER_LENGTH);
+        let root_index = timestamp_index + U256::from(BEACON_ROOTS_HISTORY_BUFFER_LENGTH);
+
+        let mut db = MemDb::default();
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        db.insert_account_info(
+            BEACON_ROOTS_ADDRESS,
+            AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code),
+        );
+        db.insert_account_storage(&BEACON_ROOTS_ADDRESS, timestamp_index, U256::ZERO);
+        db.insert_account_storage(&BEACON_ROOTS_ADDRESS, root_index, U256::ZERO);
+
+        apply_beacon_root_contract_call(&mut db, timestamp, root).unwrap();
+        assert_eq!(
+            db.storage(BEACON_ROOTS_ADDRESS, timestamp_index).unwrap(),
+            timestamp
+        );
+        assert_eq!(
+            db.storage(BEACON_ROOTS_ADDRESS, root_index).unwrap(),
+            U256::from_be_bytes(root.0)
+        );
+    }
+
+    #[test]
+    fn beacon_root_contract_not_deployed() {
+        let mut db = MemDb::default();
+        db.insert_account_info(BEACON_ROOTS_ADDRESS, AccountInfo::default());
+
+        apply_beacon_root_contract_call(&mut db, U256::from(1), B256::ZERO).unwrap();
+        assert!(db.accounts[&BEACON_ROOTS_ADDRESS].storage.is_empty());
+    }
+
+    #[test]
+    fn ommer_too_recent() {
+        let hooks = PowTxExecHooks {
+            eth: EthTxExecHooks {
+                blob_gas_used: 0,
+                cancun: false,
+            },
+            spec_id: SpecId::PETERSBURG,
+        };
+        let mut header = Header {
+            number: 10,
+            ..Default::default()
+        };
+        let genesis: Genesis = serde_json::from_str(DEV_GENESIS).unwrap();
+        let (_, input) = child_of_genesis::<EthereumTxEssence>(&genesis);
+        let input = Input {
+            ommers: vec![header.clone()],
+            ..input
+        };
+
+        // the depth of the ommer must not underflow, even if it has not been validated
+        let err = hooks
+            .after_block(&input, &mut header, &mut MemDb::default())
+            .unwrap_err();
+        assert!(matches!(err, BlockBuildError::InvalidOmmer(0)));
+    }
+
+    #[test]
+    fn oversized_gas_limit() {
+        let essence = EthereumTxEssence::Legacy(TxEssenceLegacy {
+            gas_limit: U256::from(u64::MAX) + U256::from(1),
+            ..Default::default()
+        });
+        let err = fill_eth_tx_env(2, &mut TxEnv::default(), &essence, Address::ZERO).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockBuildError::InvalidTransaction { tx_no: 2, .. }
+        ));
+    }
+}
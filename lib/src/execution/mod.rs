@@ -12,20 +12,590 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::fmt::Debug;
+use core::{fmt::Debug, mem::take};
 
-use anyhow::Result;
-use revm::{Database, DatabaseCommit};
-use zeth_primitives::transactions::TxEssence;
+use anyhow::Context;
+#[cfg(not(target_os = "zkvm"))]
+use log::debug;
+#[cfg(not(target_os = "zkvm"))]
+use revm::Inspector;
+use revm::{
+    primitives::{
+        BlobExcessGasAndPrice, EVMError, EVMResult, Env, ExecutionResult, Halt, Output,
+        ResultAndState, SpecId,
+    },
+    Database, DatabaseCommit, EVM,
+};
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{
+    block::Header,
+    receipt::Receipt,
+    transactions::{Transaction, TxEssence},
+    trie::MptNode,
+    Address, Bloom, Bytes, RlpBytes, TxHash, U256,
+};
 
-use crate::block_builder::BlockBuilder;
+use crate::{
+    block_builder::{BlockBuildError, BlockBuilder},
+    consts,
+    consts::{ChainSpec, GWEI_TO_WEI, MIN_SPEC_ID},
+    execution::ethereum::{apply_beacon_root_contract_call, increase_account_balance},
+    guest_mem_forget,
+    input::Input,
+};
 
 pub mod ethereum;
 pub mod optimism;
 
+/// Summary of the execution of a single transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxResult {
+    /// Hash of the transaction.
+    pub tx_hash: TxHash,
+    /// Recovered sender of the transaction.
+    pub from: Address,
+    /// Gas used by the transaction, as accounted in the block.
+    pub gas_used: U256,
+    /// Outcome of the transaction.
+    pub outcome: TxOutcome,
+}
+
+/// Outcome of the execution of a single transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxOutcome {
+    /// The transaction succeeded, possibly creating a contract.
+    Success {
+        output: Bytes,
+        contract_address: Option<Address>,
+    },
+    /// The transaction was reverted.
+    Revert { output: Bytes },
+    /// The transaction was halted.
+    Halt { reason: Halt },
+}
+
+impl TxResult {
+    /// Creates the summary of the given EVM result.
+    pub(crate) fn new(
+        tx_hash: TxHash,
+        from: Address,
+        gas_used: U256,
+        result: &ExecutionResult,
+    ) -> Self {
+        let outcome = match result {
+            ExecutionResult::Success { output, .. } => match output {
+                Output::Call(output) => TxOutcome::Success {
+                    output: output.clone(),
+                    contract_address: None,
+                },
+                Output::Create(output, contract_address) => TxOutcome::Success {
+                    output: output.clone(),
+                    contract_address: *contract_address,
+                },
+            },
+            ExecutionResult::Revert { output, .. } => TxOutcome::Revert {
+                output: output.clone(),
+            },
+            ExecutionResult::Halt { reason, .. } => TxOutcome::Halt { reason: *reason },
+        };
+        TxResult {
+            tx_hash,
+            from,
+            gas_used,
+            outcome,
+        }
+    }
+}
+
 pub trait TxExecStrategy<E: TxEssence> {
-    fn execute_transactions<D>(block_builder: BlockBuilder<D, E>) -> Result<BlockBuilder<D, E>>
+    fn execute_transactions<D>(
+        block_builder: BlockBuilder<D, E>,
+    ) -> Result<BlockBuilder<D, E>, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>;
+}
+
+/// Transaction execution strategy supporting a revm [Inspector], e.g. for tracing.
+#[cfg(not(target_os = "zkvm"))]
+pub trait InspectTxExecStrategy<E: TxEssence>: TxExecStrategy<E> {
+    /// Executes the transactions like [TxExecStrategy::execute_transactions], but runs each
+    /// transaction with a new inspector created by `new_inspector`. The inspectors are
+    /// returned in the order of the transactions.
+    fn execute_transactions_with_inspector<D, I, F>(
+        block_builder: BlockBuilder<D, E>,
+        new_inspector: F,
+    ) -> Result<(BlockBuilder<D, E>, Vec<I>), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+        I: Inspector<D>,
+        F: FnMut() -> I;
+}
+
+/// Network-specific hooks of the transaction loop shared by all execution strategies.
+///
+/// The hooks are created once per block and can keep block-wide values, e.g. values read
+/// from system contracts, as well as values carried from one hook to the next.
+pub trait TxExecHooks<E: TxEssence>: Sized {
+    /// Verifies the network-specific rules of the block and creates the hooks. When called,
+    /// the EVM block environment has been set up and the beacon root has been stored.
+    fn before_block<D>(
+        chain_spec: &ChainSpec,
+        input: &Input<E>,
+        header: &Header,
+        evm: &mut EVM<D>,
+    ) -> Result<Self, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>;
+
+    /// Returns the gas that must be available in the block to include the transaction.
+    fn required_block_gas(&self, tx: &Transaction<E>) -> U256 {
+        tx.essence.gas_limit()
+    }
+
+    /// Applies the state changes required before the transaction is executed, e.g.
+    /// charging fees that are not handled by the EVM.
+    fn before_tx<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<E>,
+        tx_from: Address,
+        evm: &mut EVM<D>,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>;
+
+    /// Fills the EVM environment to execute the transaction.
+    fn fill_env(
+        &mut self,
+        tx_no: usize,
+        env: &mut Env,
+        essence: &E,
+        tx_from: Address,
+    ) -> Result<(), BlockBuildError>;
+
+    /// Handles an error of the EVM when executing the transaction. By default, the error
+    /// invalidates the block; otherwise, the returned result is included instead.
+    fn on_tx_error<D>(
+        &mut self,
+        tx_no: usize,
+        _tx: &Transaction<E>,
+        _tx_from: Address,
+        err: EVMError<<D as Database>::Error>,
+        _evm: &mut EVM<D>,
+    ) -> Result<ResultAndState, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        Err(BlockBuildError::from_evm(tx_no, err))
+    }
+
+    /// Applies the state changes required after the state of the transaction has been
+    /// committed and returns the gas used by the transaction, as accounted in the block.
+    fn after_tx<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<E>,
+        result: &ExecutionResult,
+        evm: &mut EVM<D>,
+    ) -> Result<U256, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>;
+
+    /// Returns the receipt of the transaction, e.g. extended by network-specific fields.
+    fn finalize_receipt(&mut self, receipt: Receipt) -> Receipt {
+        receipt
+    }
+
+    /// Applies the state changes at the end of the block, after all withdrawals have been
+    /// processed, and sets the remaining network-specific header fields.
+    fn after_block<D>(
+        self,
+        input: &Input<E>,
+        header: &mut Header,
+        db: &mut D,
+    ) -> Result<(), BlockBuildError>
     where
         D: Database + DatabaseCommit,
-        <D as Database>::Error: Debug;
+        <D as Database>::Error: Debug + Into<BlockBuildError>;
+}
+
+/// Executes the transactions and withdrawals of the block using the given hooks, where
+/// `transact` runs the current transaction in the EVM.
+pub fn execute_with_hooks<H, E, D, T>(
+    mut block_builder: BlockBuilder<D, E>,
+    mut transact: T,
+) -> Result<BlockBuilder<D, E>, BlockBuildError>
+where
+    H: TxExecHooks<E>,
+    E: TxEssence,
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
+    T: FnMut(&mut EVM<D>) -> EVMResult<<D as Database>::Error>,
+{
+    let header = block_builder
+        .header
+        .as_mut()
+        .expect("Header is not initialized");
+    let timestamp: u64 = header
+        .timestamp
+        .try_into()
+        .map_err(|_| BlockBuildError::InvalidTimestamp(header.timestamp))?;
+    // Compute the spec id
+    let spec_id = block_builder.chain_spec.spec_id(header.number, timestamp)?;
+    if !SpecId::enabled(spec_id, MIN_SPEC_ID) {
+        return Err(BlockBuildError::UnsupportedSpecId(spec_id));
+    }
+
+    #[cfg(not(target_os = "zkvm"))]
+    {
+        use chrono::{TimeZone, Utc};
+        use log::info;
+        let dt = Utc.timestamp_opt(timestamp as i64, 0).unwrap();
+
+        info!("Block no. {}", header.number);
+        info!("  EVM spec ID: {:?}", spec_id);
+        info!("  Timestamp: {}", dt);
+        info!("  Transactions: {}", block_builder.input.transactions.len());
+        info!("  Withdrawals: {}", block_builder.input.withdrawals.len());
+        info!("  Fee Recipient: {:?}", block_builder.input.beneficiary);
+        info!("  Gas limit: {}", block_builder.input.gas_limit);
+        info!("  Base fee per gas: {:?}", header.base_fee_per_gas);
+        info!("  Extra data: {:?}", block_builder.input.extra_data);
+    }
+
+    // initialize the EVM
+    let mut evm = EVM::new();
+
+    // set the EVM configuration
+    evm.env.cfg.chain_id = block_builder.chain_spec.chain_id();
+    evm.env.cfg.spec_id = spec_id;
+
+    // set the EVM block environment
+    evm.env.block.number = header.number.try_into().unwrap();
+    evm.env.block.coinbase = block_builder.input.beneficiary;
+    evm.env.block.timestamp = header.timestamp;
+    evm.env.block.difficulty = header.difficulty;
+    evm.env.block.prevrandao = if spec_id < SpecId::MERGE {
+        None
+    } else {
+        Some(header.mix_hash)
+    };
+    evm.env.block.basefee = header.base_fee_per_gas.unwrap_or_default();
+    evm.env.block.gas_limit = block_builder.input.gas_limit;
+    evm.env.block.blob_excess_gas_and_price = header
+        .excess_blob_gas
+        .map(|excess_blob_gas| {
+            excess_blob_gas
+                .try_into()
+                .map(BlobExcessGasAndPrice::new)
+                .map_err(|_| BlockBuildError::InvalidBlobGas(excess_blob_gas))
+        })
+        .transpose()?;
+
+    let mut db = block_builder.db.take().unwrap();
+
+    // store the parent beacon block root before any transaction is processed
+    if let Some(parent_beacon_block_root) = header.parent_beacon_block_root {
+        #[cfg(not(target_os = "zkvm"))]
+        debug!("Parent beacon block root: {}", parent_beacon_block_root);
+
+        apply_beacon_root_contract_call(&mut db, header.timestamp, parent_beacon_block_root)?;
+    }
+
+    evm.database(db);
+
+    let mut hooks = H::before_block(
+        block_builder.chain_spec,
+        &block_builder.input,
+        header,
+        &mut evm,
+    )?;
+
+    // bloom filter over all transaction logs
+    let mut logs_bloom = Bloom::default();
+    // keep track of the gas used over all transactions
+    let mut cumulative_gas_used = consts::ZERO;
+
+    // process all the transactions
+    let mut tx_trie = MptNode::default();
+    let mut receipt_trie = MptNode::default();
+    for (tx_no, tx) in take(&mut block_builder.input.transactions)
+        .into_iter()
+        .enumerate()
+    {
+        // verify the transaction signature
+        let tx_from = tx
+            .recover_from()
+            .map_err(|_| BlockBuildError::InvalidSignature { tx_no })?;
+
+        let tx_hash = tx.hash();
+
+        #[cfg(not(target_os = "zkvm"))]
+        {
+            debug!("Tx no. {} (hash: {})", tx_no, tx_hash);
+            debug!("  Type: {}", tx.essence.tx_type());
+            debug!("  Fr: {:?}", tx_from);
+            debug!("  To: {:?}", tx.essence.to().unwrap_or_default());
+        }
+
+        // verify transaction gas
+        let block_available_gas = block_builder.input.gas_limit - cumulative_gas_used;
+        if block_available_gas < hooks.required_block_gas(&tx) {
+            return Err(BlockBuildError::TxGasExceedsBlock { tx_no });
+        }
+
+        // process the transaction
+        hooks.before_tx(tx_no, &tx, tx_from, &mut evm)?;
+        hooks.fill_env(tx_no, &mut evm.env, &tx.essence, tx_from)?;
+        let ResultAndState { result, state } = match transact(&mut evm) {
+            Ok(result_and_state) => result_and_state,
+            Err(evm_err) => hooks.on_tx_error(tx_no, &tx, tx_from, evm_err, &mut evm)?,
+        };
+
+        #[cfg(not(target_os = "zkvm"))]
+        debug!("  Ok: {:?}", result);
+
+        // update account states
+        #[cfg(not(target_os = "zkvm"))]
+        for (address, account) in &state {
+            if account.is_touched() {
+                // log account
+                debug!(
+                    "  State {:?} (is_selfdestructed={}, is_loaded_as_not_existing={}, is_created={}, is_empty={})",
+                    address,
+                    account.is_selfdestructed(),
+                    account.is_loaded_as_not_existing(),
+                    account.is_created(),
+                    account.is_empty(),
+                );
+                // log balance changes
+                debug!(
+                    "     After balance: {} (Nonce: {})",
+                    account.info.balance, account.info.nonce
+                );
+
+                // log state changes
+                for (addr, slot) in &account.storage {
+                    if slot.is_changed() {
+                        debug!("    Storage address: {:?}", addr);
+                        debug!("      Before: {:?}", slot.original_value());
+                        debug!("       After: {:?}", slot.present_value());
+                    }
+                }
+            }
+        }
+
+        evm.db().unwrap().commit(state);
+
+        let gas_used = hooks.after_tx(tx_no, &tx, &result, &mut evm)?;
+        cumulative_gas_used = cumulative_gas_used.checked_add(gas_used).unwrap();
+
+        // create the receipt from the EVM result
+        let receipt = hooks.finalize_receipt(Receipt::new(
+            tx.essence.tx_type(),
+            result.is_success(),
+            cumulative_gas_used,
+            result.logs().into_iter().map(|log| log.into()).collect(),
+        ));
+
+        // accumulate logs to the block bloom filter
+        logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
+
+        // Add receipt and tx to tries
+        let trie_key = tx_no.to_rlp();
+        tx_trie
+            .insert_rlp(&trie_key, tx)
+            .context("failed to insert transaction")?;
+        receipt_trie
+            .insert_rlp(&trie_key, &receipt)
+            .context("failed to insert receipt")?;
+        block_builder.receipts.push(receipt);
+        block_builder
+            .tx_results
+            .push(TxResult::new(tx_hash, tx_from, gas_used, &result));
+    }
+
+    let mut db = evm.take_db();
+
+    // process withdrawals unconditionally after any transactions
+    let mut withdrawals_trie = MptNode::default();
+    for (i, withdrawal) in take(&mut block_builder.input.withdrawals)
+        .into_iter()
+        .enumerate()
+    {
+        // the withdrawal amount is given in Gwei
+        let amount_wei = GWEI_TO_WEI
+            .checked_mul(withdrawal.amount.try_into().unwrap())
+            .unwrap();
+
+        #[cfg(not(target_os = "zkvm"))]
+        {
+            debug!("Withdrawal no. {}", withdrawal.index);
+            debug!("  Recipient: {:?}", withdrawal.address);
+            debug!("  Value: {}", amount_wei);
+        }
+        // Credit withdrawal amount
+        increase_account_balance(&mut db, withdrawal.address, amount_wei)?;
+        // Add withdrawal to trie
+        withdrawals_trie
+            .insert_rlp(&i.to_rlp(), withdrawal)
+            .context("failed to insert withdrawal")?;
+    }
+
+    // Update result header with computed values
+    header.transactions_root = tx_trie.hash();
+    header.receipts_root = receipt_trie.hash();
+    header.logs_bloom = logs_bloom;
+    header.gas_used = cumulative_gas_used;
+    header.withdrawals_root = if spec_id < SpecId::SHANGHAI {
+        None
+    } else {
+        Some(withdrawals_trie.hash())
+    };
+
+    hooks.after_block(&block_builder.input, header, &mut db)?;
+
+    // Leak memory, save cycles
+    guest_mem_forget([tx_trie, receipt_trie, withdrawals_trie]);
+    // Return block builder with updated database
+    Ok(block_builder.with_db(db))
+}
+
+/// Executes the transactions like [execute_with_hooks], but runs each transaction with a
+/// new inspector created by `new_inspector`. The inspectors are returned in the order of
+/// the transactions.
+#[cfg(not(target_os = "zkvm"))]
+pub fn inspect_with_hooks<H, E, D, I, F>(
+    block_builder: BlockBuilder<D, E>,
+    mut new_inspector: F,
+) -> Result<(BlockBuilder<D, E>, Vec<I>), BlockBuildError>
+where
+    H: TxExecHooks<E>,
+    E: TxEssence,
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
+    I: Inspector<D>,
+    F: FnMut() -> I,
+{
+    let mut inspectors = Vec::new();
+    let block_builder = execute_with_hooks::<H, _, _, _>(block_builder, |evm| {
+        let mut inspector = new_inspector();
+        let result = evm.inspect(&mut inspector);
+        inspectors.push(inspector);
+        result
+    })?;
+    Ok((block_builder, inspectors))
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{address, transactions::ethereum::EthereumTxEssence};
+
+    use super::*;
+    use crate::{
+        execution::ethereum::EthTxExecHooks,
+        genesis::{child_of_genesis, Genesis, DEV_GENESIS},
+        initialization::MemDbInitStrategy,
+        preparation::EthHeaderPrepStrategy,
+    };
+
+    /// Hooks extending Ethereum by crediting a fixed reward at the end of each block.
+    struct RewardHooks(EthTxExecHooks);
+
+    const REWARD_RECIPIENT: Address = address!("00000000000000000000000000000000000000aa");
+
+    impl TxExecHooks<EthereumTxEssence> for RewardHooks {
+        fn before_block<D>(
+            chain_spec: &ChainSpec,
+            input: &Input<EthereumTxEssence>,
+            header: &Header,
+            evm: &mut EVM<D>,
+        ) -> Result<Self, BlockBuildError>
+        where
+            D: Database + DatabaseCommit,
+            <D as Database>::Error: Debug + Into<BlockBuildError>,
+        {
+            EthTxExecHooks::before_block(chain_spec, input, header, evm).map(RewardHooks)
+        }
+
+        fn before_tx<D>(
+            &mut self,
+            tx_no: usize,
+            tx: &Transaction<EthereumTxEssence>,
+            tx_from: Address,
+            evm: &mut EVM<D>,
+        ) -> Result<(), BlockBuildError>
+        where
+            D: Database + DatabaseCommit,
+            <D as Database>::Error: Debug + Into<BlockBuildError>,
+        {
+            self.0.before_tx(tx_no, tx, tx_from, evm)
+        }
+
+        fn fill_env(
+            &mut self,
+            tx_no: usize,
+            env: &mut Env,
+            essence: &EthereumTxEssence,
+            tx_from: Address,
+        ) -> Result<(), BlockBuildError> {
+            self.0.fill_env(tx_no, env, essence, tx_from)
+        }
+
+        fn after_tx<D>(
+            &mut self,
+            tx_no: usize,
+            tx: &Transaction<EthereumTxEssence>,
+            result: &ExecutionResult,
+            evm: &mut EVM<D>,
+        ) -> Result<U256, BlockBuildError>
+        where
+            D: Database + DatabaseCommit,
+            <D as Database>::Error: Debug + Into<BlockBuildError>,
+        {
+            self.0.after_tx(tx_no, tx, result, evm)
+        }
+
+        fn after_block<D>(
+            self,
+            input: &Input<EthereumTxEssence>,
+            header: &mut Header,
+            db: &mut D,
+        ) -> Result<(), BlockBuildError>
+        where
+            D: Database + DatabaseCommit,
+            <D as Database>::Error: Debug + Into<BlockBuildError>,
+        {
+            increase_account_balance(db, REWARD_RECIPIENT, U256::from(1))?;
+            self.0.after_block(input, header, db)
+        }
+    }
+
+    #[test]
+    fn custom_hooks() {
+        let genesis: Genesis = serde_json::from_str(DEV_GENESIS).unwrap();
+        let (chain_spec, input) = child_of_genesis::<EthereumTxEssence>(&genesis);
+
+        let block_builder = BlockBuilder::new(&chain_spec, input)
+            .initialize_database::<MemDbInitStrategy>()
+            .unwrap()
+            .prepare_header::<EthHeaderPrepStrategy>()
+            .unwrap();
+        let block_builder =
+            execute_with_hooks::<RewardHooks, _, _, _>(block_builder, |evm| evm.transact())
+                .unwrap();
+
+        let header = block_builder.header.as_ref().unwrap();
+        assert_eq!(header.gas_used, U256::ZERO);
+        assert_eq!(header.withdrawals_root, Some(MptNode::default().hash()));
+        let mut db = block_builder.db.unwrap();
+        let reward_account = db.basic(REWARD_RECIPIENT).unwrap().unwrap();
+        assert_eq!(reward_account.balance, U256::from(2));
+    }
 }
@@ -12,416 +12,429 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt::Debug, mem::take, str::FromStr};
+use std::fmt::Debug;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::Context;
 #[cfg(not(target_os = "zkvm"))]
 use log::debug;
+#[cfg(not(target_os = "zkvm"))]
+use revm::Inspector;
 use revm::{
-    primitives::{Account, Address, ResultAndState, SpecId, TransactTo, TxEnv},
+    primitives::{
+        Account, Address, Bytecode, Bytes, EVMError, Env, ExecutionResult, ResultAndState, SpecId,
+        TransactTo, TxEnv, B256, KECCAK_EMPTY,
+    },
     Database, DatabaseCommit, EVM,
 };
-use ruint::aliases::U256;
+use ruint::{aliases::U256, uint};
 use zeth_primitives::{
+    block::Header,
+    keccak::keccak,
     receipt::Receipt,
     transactions::{
         ethereum::{EthereumTxEssence, TransactionKind},
         optimism::{OptimismTxEssence, TxEssenceOptimismDeposited},
-        TxEssence,
+        Transaction, TxEssence,
     },
-    trie::MptNode,
-    Bloom, RlpBytes,
+    RlpBytes,
 };
 
+#[cfg(not(target_os = "zkvm"))]
+use crate::execution::{inspect_with_hooks, InspectTxExecStrategy};
 use crate::{
-    block_builder::BlockBuilder,
-    consts,
-    consts::{GWEI_TO_WEI, MIN_SPEC_ID},
+    block_builder::{BlockBuildError, BlockBuilder},
+    consts::{ChainSpec, OptimismConfig, OptimismFork},
     execution::{
-        ethereum::{fill_eth_tx_env, increase_account_balance},
-        TxExecStrategy,
+        ethereum::{fill_eth_tx_env, increase_account_balance, tx_gas_limit},
+        execute_with_hooks, TxExecHooks, TxExecStrategy,
+    },
+    input::Input,
+    optimism::{
+        CREATE2_DEPLOYER_ADDRESS, CREATE2_DEPLOYER_CODE_HASH, L1_BASE_FEE_SLOT,
+        L1_BLOB_BASE_FEE_SLOT, L1_FEE_OVERHEAD_SLOT, L1_FEE_SCALAR_SLOT, L1_SEQUENCE_NUMBER_SLOT,
     },
-    guest_mem_forget,
 };
 
 pub struct OpTxExecStrategy {}
 
 impl TxExecStrategy<OptimismTxEssence> for OpTxExecStrategy {
     fn execute_transactions<D>(
-        mut block_builder: BlockBuilder<D, OptimismTxEssence>,
-    ) -> Result<BlockBuilder<D, OptimismTxEssence>>
+        block_builder: BlockBuilder<D, OptimismTxEssence>,
+    ) -> Result<BlockBuilder<D, OptimismTxEssence>, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        execute_with_hooks::<OpTxExecHooks, _, _, _>(block_builder, |evm| evm.transact())
+    }
+}
+
+#[cfg(not(target_os = "zkvm"))]
+impl InspectTxExecStrategy<OptimismTxEssence> for OpTxExecStrategy {
+    fn execute_transactions_with_inspector<D, I, F>(
+        block_builder: BlockBuilder<D, OptimismTxEssence>,
+        new_inspector: F,
+    ) -> Result<(BlockBuilder<D, OptimismTxEssence>, Vec<I>), BlockBuildError>
     where
         D: Database + DatabaseCommit,
-        <D as Database>::Error: Debug,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+        I: Inspector<D>,
+        F: FnMut() -> I,
     {
-        let header = block_builder
-            .header
-            .as_mut()
-            .expect("Header is not initialized");
-        // Compute the spec id
-        let spec_id = block_builder.chain_spec.spec_id(header.number);
-        if !SpecId::enabled(spec_id, MIN_SPEC_ID) {
-            bail!(
-                "Invalid protocol version: expected >= {:?}, got {:?}",
-                MIN_SPEC_ID,
-                spec_id,
-            )
+        inspect_with_hooks::<OpTxExecHooks, _, _, _, _>(block_builder, new_inspector)
+    }
+}
+
+/// Execution hooks of Optimism, handling deposits, the L1 data fee and the fee vaults.
+#[derive(Debug, Clone)]
+pub struct OpTxExecHooks {
+    optimism_config: OptimismConfig,
+    base_fee: U256,
+    regolith: bool,
+    canyon: bool,
+    ecotone: bool,
+    cancun: bool,
+    /// L1 fee parameters, read from the L1 block predeploy after the deposits.
+    l1_fee_params: Option<L1FeeParams>,
+    /// Nonce of the sender of the current deposit before its execution.
+    deposit_nonce: Option<u64>,
+    /// L1 data fee charged for the current transaction.
+    l1_gas_fees: U256,
+}
+
+impl TxExecHooks<OptimismTxEssence> for OpTxExecHooks {
+    fn before_block<D>(
+        chain_spec: &ChainSpec,
+        input: &Input<OptimismTxEssence>,
+        header: &Header,
+        evm: &mut EVM<D>,
+    ) -> Result<Self, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        let optimism_config = *chain_spec
+            .optimism_config()
+            .context("Invalid chain spec: Optimism config missing")?;
+        let timestamp: u64 = header
+            .timestamp
+            .try_into()
+            .map_err(|_| BlockBuildError::InvalidTimestamp(header.timestamp))?;
+        let regolith =
+            chain_spec.is_optimism_fork_active(OptimismFork::Regolith, header.number, timestamp);
+        let ecotone =
+            chain_spec.is_optimism_fork_active(OptimismFork::Ecotone, header.number, timestamp);
+        let canyon =
+            chain_spec.is_optimism_fork_active(OptimismFork::Canyon, header.number, timestamp);
+        // the block at the Canyon activation timestamp deploys the create2deployer
+        if chain_spec.is_optimism_fork_activation(OptimismFork::Canyon, timestamp) {
+            deploy_create2_deployer(evm.db().unwrap(), &input.contracts)?;
         }
-        let chain_id = block_builder.chain_spec.chain_id();
 
-        #[cfg(not(target_os = "zkvm"))]
-        {
-            use chrono::{TimeZone, Utc};
-            use log::info;
-            let dt = Utc
-                .timestamp_opt(block_builder.input.timestamp.try_into().unwrap(), 0)
-                .unwrap();
-
-            info!("Block no. {}", header.number);
-            info!("  EVM spec ID: {:?}", spec_id);
-            info!("  Timestamp: {}", dt);
-            info!("  Transactions: {}", block_builder.input.transactions.len());
-            info!("  Withdrawals: {}", block_builder.input.withdrawals.len());
-            info!("  Fee Recipient: {:?}", block_builder.input.beneficiary);
-            info!("  Gas limit: {}", block_builder.input.gas_limit);
-            info!("  Base fee per gas: {}", header.base_fee_per_gas);
-            info!("  Extra data: {:?}", block_builder.input.extra_data);
+        Ok(OpTxExecHooks {
+            optimism_config,
+            base_fee: header.base_fee_per_gas.unwrap_or_default(),
+            regolith,
+            canyon,
+            ecotone,
+            cancun: evm.env.cfg.spec_id >= SpecId::CANCUN,
+            l1_fee_params: None,
+            deposit_nonce: None,
+            l1_gas_fees: U256::ZERO,
+        })
+    }
+
+    fn before_tx<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<OptimismTxEssence>,
+        tx_from: Address,
+        evm: &mut EVM<D>,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        match &tx.essence {
+            OptimismTxEssence::OptimismDeposited(deposit) => {
+                // Irrevocably credit minted amount
+                let db = evm.db().unwrap();
+                increase_account_balance(db, tx_from, deposit.mint)?;
+                // Retrieve the nonce of the sender, which is reported in the receipt
+                let nonce = db
+                    .basic(tx_from)
+                    .map_err(Into::<BlockBuildError>::into)?
+                    .unwrap_or_default()
+                    .nonce;
+                self.deposit_nonce = Some(nonce);
+                self.l1_gas_fees = U256::ZERO;
+                // the deposit may update the L1 attributes
+                self.l1_fee_params = None;
+            }
+            OptimismTxEssence::Ethereum(_) => {
+                // the L1 attributes only change with the deposits, so they are read once
+                // before the first regular transaction
+                let l1_fee_params = match self.l1_fee_params {
+                    Some(l1_fee_params) => l1_fee_params,
+                    None => {
+                        let l1_fee_params = L1FeeParams::from_db(
+                            evm.db().unwrap(),
+                            self.optimism_config.l1_block,
+                            self.ecotone,
+                        )?;
+                        self.l1_fee_params = Some(l1_fee_params);
+                        l1_fee_params
+                    }
+                };
+                let l1_gas_fees = l1_fee_params.l1_cost(&tx.to_rlp());
+
+                // Deduct L1 fee from sender
+                decrease_account_balance(evm.db().unwrap(), tx_no, tx_from, l1_gas_fees)?;
+
+                self.deposit_nonce = None;
+                self.l1_gas_fees = l1_gas_fees;
+            }
         }
 
-        // initialize the EVM
-        let mut evm = EVM::new();
+        Ok(())
+    }
 
-        // set the EVM configuration
-        evm.env.cfg.chain_id = chain_id;
-        evm.env.cfg.spec_id = spec_id;
-
-        // set the EVM block environment
-        evm.env.block.number = header.number.try_into().unwrap();
-        evm.env.block.coinbase = block_builder.input.beneficiary;
-        evm.env.block.timestamp = header.timestamp;
-        evm.env.block.difficulty = U256::ZERO;
-        evm.env.block.prevrandao = Some(header.mix_hash);
-        evm.env.block.basefee = header.base_fee_per_gas;
-        evm.env.block.gas_limit = block_builder.input.gas_limit;
-
-        evm.database(block_builder.db.take().unwrap());
-
-        // bloom filter over all transaction logs
-        let mut logs_bloom = Bloom::default();
-        // keep track of the gas used over all transactions
-        let mut cumulative_gas_used = consts::ZERO;
-
-        let l1_info_depositor_address =
-            Address::from_str("0xDeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001").unwrap();
-
-        let l1_block_attr_pre_deploy =
-            Address::from_str("0x4200000000000000000000000000000000000015").unwrap();
-
-        let l1_gas_oracle_pre_deploy =
-            Address::from_str("0x420000000000000000000000000000000000000F").unwrap();
-
-        let l1_fee_vault_pre_deploy =
-            Address::from_str("0x420000000000000000000000000000000000001A").unwrap();
-
-        let base_fee_vault_pre_deploy =
-            Address::from_str("0x4200000000000000000000000000000000000019").unwrap();
-
-        let l1_fee_overhead_decimals = U256::from(10).pow(read_uint(
-            &mut evm,
-            vec![0x31u8, 0x3cu8, 0xe5u8, 0x67u8],
-            Some(chain_id),
-            header.gas_limit,
-            l1_gas_oracle_pre_deploy,
-        )?);
-
-        // process all the transactions
-        let mut tx_trie = MptNode::default();
-        let mut receipt_trie = MptNode::default();
-        for (tx_no, tx) in take(&mut block_builder.input.transactions)
-            .into_iter()
-            .enumerate()
-        {
-            // verify the transaction signature
-            let tx_from = tx
-                .recover_from()
-                .with_context(|| format!("Error recovering address for transaction {}", tx_no))?;
-
-            #[cfg(not(target_os = "zkvm"))]
+    fn required_block_gas(&self, tx: &Transaction<OptimismTxEssence>) -> U256 {
+        match &tx.essence {
+            // before Regolith, system transactions are not counted against the block gas
+            OptimismTxEssence::OptimismDeposited(deposit)
+                if deposit.is_system_tx && !self.regolith =>
             {
-                let tx_hash = tx.hash();
-                debug!("Tx no. {} (hash: {})", tx_no, tx_hash);
-                debug!("  Type: {}", tx.essence.tx_type());
-                debug!("  Fr: {:?}", tx_from);
-                debug!("  To: {:?}", tx.essence.to().unwrap_or_default());
+                U256::ZERO
             }
+            essence => essence.gas_limit(),
+        }
+    }
 
-            // verify transaction gas
-            let block_available_gas = block_builder.input.gas_limit - cumulative_gas_used;
-            if block_available_gas < tx.essence.gas_limit() {
-                bail!("Error at transaction {}: gas exceeds block limit", tx_no);
+    fn fill_env(
+        &mut self,
+        tx_no: usize,
+        env: &mut Env,
+        essence: &OptimismTxEssence,
+        tx_from: Address,
+    ) -> Result<(), BlockBuildError> {
+        match essence {
+            OptimismTxEssence::OptimismDeposited(deposit) => {
+                // Disable gas fees
+                env.cfg.disable_base_fee = true;
+                env.cfg.disable_balance_check = true;
+                fill_deposit_tx_env(tx_no, &mut env.tx, deposit, tx_from)?;
+                // System transactions are no longer supported after Regolith, but they are
+                // still included as failed deposits: revm does not know them, so they are
+                // rejected by a gas limit below the intrinsic gas, and handled by
+                // `on_tx_error` like any other failed deposit
+                if self.regolith && deposit.is_system_tx {
+                    env.tx.gas_limit = 0;
+                }
+                Ok(())
             }
+            OptimismTxEssence::Ethereum(EthereumTxEssence::Eip4844(_)) => {
+                Err(BlockBuildError::InvalidTransaction {
+                    tx_no,
+                    reason: "blob transactions are not supported on L2".to_string(),
+                })
+            }
+            OptimismTxEssence::Ethereum(transaction) => {
+                // Enable gas fees
+                env.cfg.disable_base_fee = false;
+                env.cfg.disable_balance_check = false;
+                fill_eth_tx_env(tx_no, &mut env.tx, transaction, tx_from)
+            }
+        }
+    }
 
-            let l1_gas_fees = match &tx.essence {
-                OptimismTxEssence::OptimismDeposited(deposit) => {
-                    // Disable gas fees
-                    evm.env.cfg.disable_base_fee = true;
-                    evm.env.cfg.disable_balance_check = true;
-                    // Irrevocably credit minted amount
-                    let db = evm.db().unwrap();
-                    increase_account_balance(db, tx_from, deposit.mint)?;
-                    // Retrieve effective nonce
-                    // todo: read this from contract
-                    let effective_nonce = if tx_from == l1_info_depositor_address {
-                        None
-                    } else {
-                        Some(db.basic(tx_from).unwrap().unwrap_or_default().nonce)
-                    };
-                    // Initialize tx environment
-                    fill_deposit_tx_env(&mut evm.env.tx, deposit, tx_from, effective_nonce);
+    fn on_tx_error<D>(
+        &mut self,
+        tx_no: usize,
+        tx: &Transaction<OptimismTxEssence>,
+        tx_from: Address,
+        err: EVMError<<D as Database>::Error>,
+        evm: &mut EVM<D>,
+    ) -> Result<ResultAndState, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        let deposit = match (&tx.essence, err) {
+            (OptimismTxEssence::OptimismDeposited(deposit), EVMError::Transaction(_)) => deposit,
+            (_, err) => return Err(BlockBuildError::from_evm(tx_no, err)),
+        };
+
+        #[cfg(not(target_os = "zkvm"))]
+        debug!("  Failed deposit");
 
+        // failed deposits are still included: the minted amount is kept and the nonce of
+        // the sender is increased, but all other changes are discarded
+        let mut account: Account = evm
+            .db()
+            .unwrap()
+            .basic(tx_from)
+            .map_err(Into::<BlockBuildError>::into)?
+            .unwrap_or_default()
+            .into();
+        account.info.nonce += 1;
+        account.mark_touch();
+
+        Ok(ResultAndState {
+            // failed deposits use all their gas
+            result: ExecutionResult::Revert {
+                gas_used: tx_gas_limit(tx_no, deposit.gas_limit)?,
+                output: Default::default(),
+            },
+            state: [(tx_from, account)].into(),
+        })
+    }
+
+    fn after_tx<D>(
+        &mut self,
+        _tx_no: usize,
+        tx: &Transaction<OptimismTxEssence>,
+        result: &ExecutionResult,
+        evm: &mut EVM<D>,
+    ) -> Result<U256, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        let gas_used = match &tx.essence {
+            // before Regolith, deposits report all their gas as used, except for system
+            // transactions which do not use any gas
+            OptimismTxEssence::OptimismDeposited(deposit) if !self.regolith => {
+                if deposit.is_system_tx {
                     U256::ZERO
-                }
-                OptimismTxEssence::Ethereum(transaction) => {
-                    // L1 gas fee
-                    // todo: read these values only once after processing the first system tx
-                    let l1_base_fee = read_uint(
-                        &mut evm,
-                        vec![0x5cu8, 0xf2u8, 0x49u8, 0x69u8],
-                        Some(chain_id),
-                        header.gas_limit,
-                        l1_block_attr_pre_deploy,
-                    )?;
-                    let l1_fee_overhead = read_uint(
-                        &mut evm,
-                        vec![0x8bu8, 0x23u8, 0x9fu8, 0x73u8],
-                        Some(chain_id),
-                        header.gas_limit,
-                        l1_block_attr_pre_deploy,
-                    )?;
-                    let l1_fee_scalar = read_uint(
-                        &mut evm,
-                        vec![0x9eu8, 0x8cu8, 0x49u8, 0x66u8],
-                        Some(chain_id),
-                        header.gas_limit,
-                        l1_block_attr_pre_deploy,
-                    )?;
-
-                    let tx_data = tx.to_rlp();
-                    let non_zero = tx_data.iter().filter(|b| *b > &0u8).count() as u128;
-                    let zeroes = tx_data.len() as u128 - non_zero;
-                    let l1_gas = U256::from(16u128 * non_zero + 4u128 * zeroes) + l1_fee_overhead;
-                    let l1_gas_fees =
-                        (l1_base_fee * l1_gas * l1_fee_scalar) / l1_fee_overhead_decimals;
-
-                    // Deduct L1 fee from sender
-                    decrease_account_balance(evm.db().unwrap(), tx_from, l1_gas_fees)?;
-
-                    // Enable gas fees
-                    evm.env.cfg.disable_base_fee = false;
-                    evm.env.cfg.disable_balance_check = false;
-                    // Initialize tx environment
-                    fill_eth_tx_env(&mut evm.env.tx, transaction, tx_from);
-                    l1_gas_fees
-                }
-            };
-
-            // process the transaction
-            let ResultAndState { result, state } = evm
-                .transact()
-                .map_err(|evm_err| anyhow!("Error at transaction {}: {:?}", tx_no, evm_err))?;
-
-            let gas_used = result.gas_used().try_into().unwrap();
-            cumulative_gas_used = cumulative_gas_used.checked_add(gas_used).unwrap();
-
-            #[cfg(not(target_os = "zkvm"))]
-            debug!("  Ok: {:?}", result);
-
-            // create the receipt from the EVM result
-            let receipt = Receipt::new(
-                tx.essence.tx_type(),
-                result.is_success(),
-                cumulative_gas_used,
-                result.logs().into_iter().map(|log| log.into()).collect(),
-            );
-
-            // update account states
-            #[cfg(not(target_os = "zkvm"))]
-            for (address, account) in &state {
-                if account.is_touched() {
-                    // log account
-                    debug!(
-                        "  State {:?} (is_selfdestructed={}, is_loaded_as_not_existing={}, is_created={})",
-                        address,
-                        account.is_selfdestructed(),
-                        account.is_loaded_as_not_existing(),
-                        account.is_created()
-                    );
-                    // log balance changes
-                    debug!(
-                        "     After balance: {} (Nonce: {})",
-                        account.info.balance, account.info.nonce
-                    );
-
-                    // log state changes
-                    for (addr, slot) in &account.storage {
-                        if slot.is_changed() {
-                            debug!("    Storage address: {:?}", addr);
-                            debug!("      Before: {:?}", slot.original_value());
-                            debug!("       After: {:?}", slot.present_value());
-                        }
-                    }
+                } else {
+                    deposit.gas_limit
                 }
             }
+            _ => result.gas_used().try_into().unwrap(),
+        };
 
+        if !matches!(tx.essence, OptimismTxEssence::OptimismDeposited(_)) {
             let db = evm.db().unwrap();
-
-            db.commit(state);
-
-            if !matches!(tx.essence, OptimismTxEssence::OptimismDeposited(_)) {
-                // Credit L2 base fee
-                increase_account_balance(
-                    db,
-                    base_fee_vault_pre_deploy,
-                    gas_used * header.base_fee_per_gas,
-                )?;
-                // Credit L1 gas fee
-                increase_account_balance(db, l1_fee_vault_pre_deploy, l1_gas_fees)?;
-            }
-
-            // accumulate logs to the block bloom filter
-            logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
-
-            // Add receipt and tx to tries
-            let trie_key = tx_no.to_rlp();
-            tx_trie
-                .insert_rlp(&trie_key, tx)
-                .context("failed to insert transaction")?;
-            receipt_trie
-                .insert_rlp(&trie_key, receipt)
-                .context("failed to insert receipt")?;
+            // Credit L2 base fee
+            increase_account_balance(
+                db,
+                self.optimism_config.base_fee_vault,
+                gas_used * self.base_fee,
+            )?;
+            // Credit L1 gas fee
+            increase_account_balance(db, self.optimism_config.l1_fee_vault, self.l1_gas_fees)?;
         }
 
-        let mut db = evm.take_db();
-
-        // process withdrawals unconditionally after any transactions
-        let mut withdrawals_trie = MptNode::default();
-        for (i, withdrawal) in take(&mut block_builder.input.withdrawals)
-            .into_iter()
-            .enumerate()
-        {
-            // the withdrawal amount is given in Gwei
-            let amount_wei = GWEI_TO_WEI
-                .checked_mul(withdrawal.amount.try_into().unwrap())
-                .unwrap();
+        Ok(gas_used)
+    }
 
-            #[cfg(not(target_os = "zkvm"))]
-            {
-                debug!("Withdrawal no. {}", withdrawal.index);
-                debug!("  Recipient: {:?}", withdrawal.address);
-                debug!("  Value: {}", amount_wei);
-            }
-            // Read account from database
-            let withdrawal_address = withdrawal.address;
-            let mut withdrawal_account: Account = db
-                .basic(withdrawal.address)
-                .map_err(|db_err| anyhow!("Error at withdrawal {}: {:?}", i, db_err))?
-                .unwrap_or_default()
-                .into();
-            // Credit withdrawal amount
-            withdrawal_account.info.balance = withdrawal_account
-                .info
-                .balance
-                .checked_add(amount_wei)
-                .unwrap();
-            withdrawal_account.mark_touch();
-            // Commit changes to database
-            db.commit([(withdrawal_address, withdrawal_account)].into());
-            // Add withdrawal to trie
-            withdrawals_trie
-                .insert_rlp(&i.to_rlp(), withdrawal)
-                .context("failed to insert withdrawal")?;
+    fn finalize_receipt(&mut self, receipt: Receipt) -> Receipt {
+        // deposit receipts contain the deposit nonce after Regolith, but it is only part of
+        // the receipt hash together with the receipt version introduced by Canyon
+        match self.deposit_nonce.filter(|_| self.regolith) {
+            Some(deposit_nonce) if self.canyon => receipt
+                .with_deposit_nonce(deposit_nonce)
+                .with_deposit_receipt_version(1),
+            Some(deposit_nonce) => receipt.with_deposit_nonce(deposit_nonce),
+            None => receipt,
         }
+    }
 
-        // Update result header with computed values
-        header.transactions_root = tx_trie.hash();
-        header.receipts_root = receipt_trie.hash();
-        header.logs_bloom = logs_bloom;
-        header.gas_used = cumulative_gas_used;
-        header.withdrawals_root = if spec_id < SpecId::SHANGHAI {
-            None
-        } else {
-            Some(withdrawals_trie.hash())
-        };
+    fn after_block<D>(
+        self,
+        _input: &Input<OptimismTxEssence>,
+        header: &mut Header,
+        db: &mut D,
+    ) -> Result<(), BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        // the output root commits to the storage of the message passer, so its account
+        // must be part of the witness even if no transaction touches it
+        db.basic(self.optimism_config.l2_to_l1_message_passer)
+            .map_err(Into::<BlockBuildError>::into)?;
 
-        // Leak memory, save cycles
-        guest_mem_forget([tx_trie, receipt_trie, withdrawals_trie]);
-        // Return block builder with updated database
-        Ok(block_builder.with_db(db))
+        // blob transactions are not supported on L2
+        header.blob_gas_used = self.cancun.then_some(U256::ZERO);
+
+        Ok(())
     }
 }
 
-fn read_uint<D>(
-    evm: &mut EVM<D>,
-    abi_call: Vec<u8>,
-    chain_id: Option<zeth_primitives::ChainId>,
-    gas_limit: U256,
-    address: Address,
-) -> Result<U256>
-where
-    D: Database + DatabaseCommit,
-    <D as Database>::Error: Debug,
-{
-    let op_l1_tx =
-        EthereumTxEssence::Legacy(zeth_primitives::transactions::ethereum::TxEssenceLegacy {
-            chain_id,
-            nonce: 0,
-            gas_price: U256::ZERO,
-            gas_limit,
-            to: TransactionKind::Call(address),
-            value: U256::ZERO,
-            data: abi_call.into(),
-        });
-
-    // disable base fees
-    evm.env.cfg.disable_base_fee = true;
-    evm.env.cfg.disable_balance_check = true;
-    fill_eth_tx_env(&mut evm.env.tx, &op_l1_tx, Default::default());
-
-    let Ok(ResultAndState {
-        result: execution_result,
-        ..
-    }) = evm.transact()
-    else {
-        bail!("Error during execution");
-    };
-
-    let revm::primitives::ExecutionResult::Success { output, .. } = execution_result else {
-        bail!("Result unsuccessful");
-    };
+/// L1 attributes stored in the L1 block predeploy, which determine the L1 data fee.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct L1FeeParams {
+    pub l1_base_fee: U256,
+    pub l1_fee_overhead: U256,
+    pub l1_fee_scalar: U256,
+    pub l1_base_fee_scalar: U256,
+    pub l1_blob_base_fee_scalar: U256,
+    pub l1_blob_base_fee: U256,
+}
 
-    let revm::primitives::Output::Call(result_encoded) = output else {
-        bail!("Unsupported result");
-    };
+impl L1FeeParams {
+    /// Reads the parameters from the storage of the L1 block predeploy at `l1_block`. The
+    /// Ecotone parameters are only read when `ecotone` is set and are zero otherwise.
+    pub fn from_db<D>(db: &mut D, l1_block: Address, ecotone: bool) -> Result<Self, BlockBuildError>
+    where
+        D: Database,
+        <D as Database>::Error: Debug + Into<BlockBuildError>,
+    {
+        let mut read = |slot| {
+            db.storage(l1_block, slot)
+                .map_err(Into::<BlockBuildError>::into)
+        };
+        let mut params = L1FeeParams {
+            l1_base_fee: read(L1_BASE_FEE_SLOT)?,
+            l1_fee_overhead: read(L1_FEE_OVERHEAD_SLOT)?,
+            l1_fee_scalar: read(L1_FEE_SCALAR_SLOT)?,
+            ..Default::default()
+        };
+        if ecotone {
+            // the slot packs the sequence number with the blob base fee scalar at byte
+            // offset 8 and the base fee scalar at byte offset 12, counted from the least
+            // significant byte
+            let scalars: [u8; 32] = read(L1_SEQUENCE_NUMBER_SLOT)?.to_be_bytes();
+            params.l1_base_fee_scalar =
+                U256::from(u32::from_be_bytes(scalars[16..20].try_into().unwrap()));
+            params.l1_blob_base_fee_scalar =
+                U256::from(u32::from_be_bytes(scalars[20..24].try_into().unwrap()));
+            params.l1_blob_base_fee = read(L1_BLOB_BASE_FEE_SLOT)?;
+        }
 
-    let ethers_core::abi::Token::Uint(uint_result) =
-        ethers_core::abi::decode(&[ethers_core::abi::ParamType::Uint(256)], &result_encoded)?
-            .pop()
-            .unwrap()
-    else {
-        bail!("Could not decode result");
-    };
+        Ok(params)
+    }
 
-    Ok(U256::from_limbs(uint_result.0))
+    /// Returns the L1 data fee of the transaction with the given RLP encoding.
+    pub fn l1_cost(&self, tx_data: &[u8]) -> U256 {
+        let non_zero = tx_data.iter().filter(|b| **b > 0u8).count() as u128;
+        let zeroes = tx_data.len() as u128 - non_zero;
+        let l1_gas = U256::from(16u128 * non_zero + 4u128 * zeroes);
+        // the Ecotone activation block still uses the Bedrock formula, as the scalars are
+        // only set by the first Ecotone L1 attributes transaction
+        // the gas price oracle uses 6 decimals for the Bedrock scalar
+        if self.l1_base_fee_scalar == U256::ZERO && self.l1_blob_base_fee_scalar == U256::ZERO {
+            (self.l1_base_fee * (l1_gas + self.l1_fee_overhead) * self.l1_fee_scalar)
+                / uint!(1_000_000_U256)
+        } else {
+            l1_gas
+                * (uint!(16_U256) * self.l1_base_fee * self.l1_base_fee_scalar
+                    + self.l1_blob_base_fee * self.l1_blob_base_fee_scalar)
+                / uint!(16_000_000_U256)
+        }
+    }
 }
 
 fn fill_deposit_tx_env(
+    tx_no: usize,
     tx_env: &mut TxEnv,
     tx: &TxEssenceOptimismDeposited,
     caller: Address,
-    deposit_nonce: Option<u64>,
-) {
+) -> Result<(), BlockBuildError> {
     tx_env.caller = caller; // previously overridden to tx.from
-    tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
+    tx_env.gas_limit = tx_gas_limit(tx_no, tx.gas_limit)?;
     tx_env.gas_price = U256::ZERO;
     tx_env.gas_priority_fee = None;
     tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
@@ -432,36 +445,431 @@ fn fill_deposit_tx_env(
     tx_env.value = tx.value;
     tx_env.data = tx.data.clone();
     tx_env.chain_id = None;
-    tx_env.nonce = deposit_nonce;
+    // deposits are not subject to the nonce check, but still increase the nonce
+    tx_env.nonce = None;
     tx_env.access_list.clear();
+
+    Ok(())
 }
 
 pub fn decrease_account_balance<D>(
     db: &mut D,
+    tx_no: usize,
     address: Address,
     amount_wei: U256,
-) -> anyhow::Result<()>
+) -> Result<(), BlockBuildError>
 where
     D: Database + DatabaseCommit,
-    <D as Database>::Error: Debug,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
 {
     // Read account from database
     let mut account: Account = db
         .basic(address)
-        .map_err(|db_err| {
-            anyhow!(
-                "Error decreasing account balance for {}: {:?}",
-                address,
-                db_err
-            )
-        })?
+        .map_err(Into::<BlockBuildError>::into)?
         .unwrap_or_default()
         .into();
-    // Credit withdrawal amount
-    account.info.balance = account.info.balance.checked_sub(amount_wei).unwrap();
+    // Debit the amount, which must be covered by the sender of the transaction
+    account.info.balance = account
+        .info
+        .balance
+        .checked_sub(amount_wei)
+        .ok_or_else(|| BlockBuildError::InvalidTransaction {
+            tx_no,
+            reason: format!("insufficient funds for {} wei", amount_wei),
+        })?;
     account.mark_touch();
     // Commit changes to database
     db.commit([(address, account)].into());
 
     Ok(())
 }
+
+/// Deploys the code of the create2deployer to its canonical address, unless the account
+/// already contains code.
+///
+/// The code is not part of the parent state and must be provided as one of the `contracts`.
+pub fn deploy_create2_deployer<D>(db: &mut D, contracts: &[Bytes]) -> Result<(), BlockBuildError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: Debug + Into<BlockBuildError>,
+{
+    // Read account from database
+    let mut account: Account = db
+        .basic(CREATE2_DEPLOYER_ADDRESS)
+        .map_err(Into::<BlockBuildError>::into)?
+        .unwrap_or_default()
+        .into();
+    if account.info.code_hash != KECCAK_EMPTY {
+        return Ok(());
+    }
+    // Find the code with the expected hash
+    let code = contracts
+        .iter()
+        .find(|code| B256::from(keccak(code)) == CREATE2_DEPLOYER_CODE_HASH)
+        .ok_or(BlockBuildError::MissingWitness(CREATE2_DEPLOYER_ADDRESS))?;
+    account.info.code_hash = CREATE2_DEPLOYER_CODE_HASH;
+    account.info.code = Some(Bytecode::new_raw(code.clone()));
+    account.mark_touch();
+    // Commit changes to database
+    db.commit([(CREATE2_DEPLOYER_ADDRESS, account)].into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use revm::primitives::{AccountInfo, InvalidTransaction};
+    use zeth_primitives::{
+        address,
+        signature::TxSignature,
+        transactions::ethereum::{TxEssenceEip4844, TxEssenceLegacy},
+    };
+
+    use super::*;
+    use crate::mem_db::MemDb;
+
+    fn hooks(regolith: bool) -> OpTxExecHooks {
+        OpTxExecHooks {
+            optimism_config: OptimismConfig::default(),
+            base_fee: U256::ZERO,
+            regolith,
+            canyon: false,
+            ecotone: false,
+            cancun: false,
+            l1_fee_params: None,
+            deposit_nonce: None,
+            l1_gas_fees: U256::ZERO,
+        }
+    }
+
+    fn deposit(is_system_tx: bool) -> Transaction<OptimismTxEssence> {
+        Transaction {
+            essence: OptimismTxEssence::OptimismDeposited(TxEssenceOptimismDeposited {
+                gas_limit: U256::from(1_000_000),
+                is_system_tx,
+                ..Default::default()
+            }),
+            signature: TxSignature {
+                v: 0,
+                r: U256::ZERO,
+                s: U256::ZERO,
+            },
+        }
+    }
+
+    #[test]
+    fn system_tx_gas() {
+        let tx = deposit(true);
+        assert_eq!(hooks(false).required_block_gas(&tx), U256::ZERO);
+        assert_eq!(hooks(true).required_block_gas(&tx), U256::from(1_000_000));
+        assert_eq!(
+            hooks(false).required_block_gas(&deposit(false)),
+            U256::from(1_000_000)
+        );
+    }
+
+    #[test]
+    fn failed_deposit() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let mut db = MemDb::default();
+        db.insert_account_info(
+            sender,
+            AccountInfo {
+                balance: U256::from(1),
+                nonce: 7,
+                ..Default::default()
+            },
+        );
+        let mut evm = EVM::new();
+        evm.database(db);
+
+        let tx = deposit(false);
+        let mut hooks = hooks(true);
+        let err = EVMError::Transaction(InvalidTransaction::CallGasCostMoreThanGasLimit);
+        let ResultAndState { result, state } =
+            hooks.on_tx_error(0, &tx, sender, err, &mut evm).unwrap();
+
+        assert!(!result.is_success());
+        assert_eq!(result.gas_used(), 1_000_000);
+        assert_eq!(state[&sender].info.nonce, 8);
+        assert_eq!(state[&sender].info.balance, U256::from(1));
+        evm.db().unwrap().commit(state);
+        assert_eq!(
+            hooks.after_tx(0, &tx, &result, &mut evm).unwrap(),
+            U256::from(1_000_000)
+        );
+    }
+
+    #[test]
+    fn regolith_system_tx() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let mut db = MemDb::default();
+        db.insert_account_info(
+            sender,
+            AccountInfo {
+                balance: U256::from(1),
+                nonce: 7,
+                ..Default::default()
+            },
+        );
+        let mut evm = EVM::new();
+        evm.database(db);
+
+        let mut tx = deposit(true);
+        if let OptimismTxEssence::OptimismDeposited(deposit) = &mut tx.essence {
+            deposit.mint = U256::from(10);
+        }
+        let mut hooks = hooks(true);
+        hooks.before_tx(0, &tx, sender, &mut evm).unwrap();
+        hooks
+            .fill_env(0, &mut evm.env, &tx.essence, sender)
+            .unwrap();
+        let err = evm.transact().unwrap_err();
+        let ResultAndState { result, state } =
+            hooks.on_tx_error(0, &tx, sender, err, &mut evm).unwrap();
+        evm.db().unwrap().commit(state);
+        let gas_used = hooks.after_tx(0, &tx, &result, &mut evm).unwrap();
+        let receipt = hooks.finalize_receipt(Receipt::new(
+            tx.essence.tx_type(),
+            result.is_success(),
+            gas_used,
+            vec![],
+        ));
+
+        // the system transaction fails, but keeps the mint and uses all of its gas
+        assert_eq!(gas_used, U256::from(1_000_000));
+        assert_eq!(
+            receipt,
+            Receipt::new(0x7e, false, U256::from(1_000_000), vec![]).with_deposit_nonce(7)
+        );
+        let account = evm.db().unwrap().basic(sender).unwrap().unwrap();
+        assert_eq!(account.nonce, 8);
+        assert_eq!(account.balance, U256::from(11));
+    }
+
+    #[test]
+    fn deposit_receipt_fields() {
+        let receipt = Receipt::new(0x7e, true, U256::from(21000), vec![]);
+
+        let mut bedrock = OpTxExecHooks {
+            deposit_nonce: Some(7),
+            ..hooks(false)
+        };
+        assert_eq!(bedrock.finalize_receipt(receipt.clone()), receipt);
+
+        let mut regolith = OpTxExecHooks {
+            deposit_nonce: Some(7),
+            ..hooks(true)
+        };
+        assert_eq!(
+            regolith.finalize_receipt(receipt.clone()),
+            receipt.clone().with_deposit_nonce(7)
+        );
+
+        let mut canyon = OpTxExecHooks {
+            canyon: true,
+            deposit_nonce: Some(7),
+            ..hooks(true)
+        };
+        assert_eq!(
+            canyon.finalize_receipt(receipt.clone()),
+            receipt
+                .with_deposit_nonce(7)
+                .with_deposit_receipt_version(1)
+        );
+    }
+
+    #[test]
+    fn insufficient_l1_fee() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let mut db = MemDb::default();
+        db.insert_account_info(sender, AccountInfo::default());
+        let mut evm = EVM::new();
+        evm.database(db);
+
+        let tx = Transaction {
+            essence: OptimismTxEssence::Ethereum(EthereumTxEssence::Legacy(Default::default())),
+            signature: TxSignature {
+                v: 27,
+                r: U256::ZERO,
+                s: U256::ZERO,
+            },
+        };
+        let mut hooks = hooks(true);
+        hooks.l1_fee_params = Some(L1FeeParams {
+            l1_base_fee: U256::from(1000),
+            l1_fee_overhead: U256::from(188),
+            l1_fee_scalar: U256::from(684000),
+            ..Default::default()
+        });
+        let err = hooks.before_tx(3, &tx, sender, &mut evm).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockBuildError::InvalidTransaction { tx_no: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn blob_tx() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let essence = OptimismTxEssence::Ethereum(EthereumTxEssence::Eip4844(TxEssenceEip4844 {
+            to: sender,
+            blob_versioned_hashes: vec![Default::default()],
+            ..Default::default()
+        }));
+        let mut env = Env::default();
+        let err = hooks(true)
+            .fill_env(2, &mut env, &essence, sender)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BlockBuildError::InvalidTransaction { tx_no: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn ecotone_l1_fee() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let config = OptimismConfig::default();
+        let mut db = MemDb::default();
+        db.insert_account_info(
+            sender,
+            AccountInfo {
+                balance: U256::from(1000),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(config.base_fee_vault, AccountInfo::default());
+        db.insert_account_info(config.l1_fee_vault, AccountInfo::default());
+        db.insert_account_info(config.l1_block, AccountInfo::default());
+        db.insert_account_storage(&config.l1_block, L1_BASE_FEE_SLOT, U256::from(1000));
+        db.insert_account_storage(&config.l1_block, L1_FEE_OVERHEAD_SLOT, U256::ZERO);
+        db.insert_account_storage(&config.l1_block, L1_FEE_SCALAR_SLOT, U256::ZERO);
+        let scalars = (U256::from(1368) << 96) | (U256::from(810949) << 64);
+        db.insert_account_storage(&config.l1_block, L1_SEQUENCE_NUMBER_SLOT, scalars);
+        db.insert_account_storage(&config.l1_block, L1_BLOB_BASE_FEE_SLOT, U256::from(10));
+        let mut evm = EVM::new();
+        evm.database(db);
+
+        // the encoding has 11 non-zero and 2 zero bytes, i.e. 184 L1 gas
+        let tx = Transaction {
+            essence: OptimismTxEssence::Ethereum(EthereumTxEssence::Legacy(TxEssenceLegacy {
+                data: vec![0, 0, 1].into(),
+                ..Default::default()
+            })),
+            signature: TxSignature {
+                v: 27,
+                r: U256::ZERO,
+                s: U256::ZERO,
+            },
+        };
+        assert_eq!(tx.to_rlp().len(), 13);
+        let mut hooks = OpTxExecHooks {
+            ecotone: true,
+            ..hooks(true)
+        };
+        hooks.before_tx(0, &tx, sender, &mut evm).unwrap();
+        // 184 * (16 * 1000 * 1368 + 10 * 810949) / 16e6
+        let l1_fee = U256::from(344);
+        let db = evm.db().unwrap();
+        assert_eq!(
+            db.basic(sender).unwrap().unwrap().balance,
+            U256::from(1000) - l1_fee
+        );
+
+        let result = ExecutionResult::Revert {
+            gas_used: 21000,
+            output: Default::default(),
+        };
+        hooks.after_tx(0, &tx, &result, &mut evm).unwrap();
+        let db = evm.db().unwrap();
+        assert_eq!(
+            db.basic(config.l1_fee_vault).unwrap().unwrap().balance,
+            l1_fee
+        );
+    }
+
+    #[test]
+    fn create2_deployer_code() {
+        let mut db = MemDb::default();
+        db.insert_account_info(CREATE2_DEPLOYER_ADDRESS, AccountInfo::default());
+        // the code must match the known code hash
+        let err = deploy_create2_deployer(&mut db, &[vec![0x00].into()]).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockBuildError::MissingWitness(CREATE2_DEPLOYER_ADDRESS)
+        ));
+
+        // existing code is not replaced
+        let mut db = MemDb::default();
+        let code: Bytes = vec![0x00].into();
+        let info = AccountInfo {
+            code_hash: keccak(&code).into(),
+            code: Some(Bytecode::new_raw(code)),
+            ..Default::default()
+        };
+        db.insert_account_info(CREATE2_DEPLOYER_ADDRESS, info.clone());
+        deploy_create2_deployer(&mut db, &[]).unwrap();
+        assert_eq!(
+            db.basic(CREATE2_DEPLOYER_ADDRESS)
+                .unwrap()
+                .unwrap()
+                .code_hash,
+            info.code_hash
+        );
+    }
+
+    #[test]
+    fn bedrock_l1_cost() {
+        let params = L1FeeParams {
+            l1_base_fee: U256::from(1000),
+            l1_fee_overhead: U256::from(188),
+            l1_fee_scalar: U256::from(684000),
+            ..Default::default()
+        };
+        // 1 zero byte and 2 non-zero bytes use 36 gas
+        assert_eq!(params.l1_cost(&[0, 1, 2]), U256::from(153216));
+    }
+
+    #[test]
+    fn ecotone_l1_cost() {
+        let params = L1FeeParams {
+            l1_base_fee: U256::from(1000),
+            l1_base_fee_scalar: U256::from(1368),
+            l1_blob_base_fee: U256::from(10),
+            l1_blob_base_fee_scalar: U256::from(810949),
+            ..Default::default()
+        };
+        assert_eq!(params.l1_cost(&[0, 1, 2]), U256::from(67));
+    }
+
+    #[test]
+    fn l1_fee_params_from_db() {
+        let l1_block = OptimismConfig::default().l1_block;
+        let mut db = MemDb::default();
+        db.insert_account_info(l1_block, AccountInfo::default());
+        db.insert_account_storage(&l1_block, L1_BASE_FEE_SLOT, U256::from(1000));
+        db.insert_account_storage(&l1_block, L1_FEE_OVERHEAD_SLOT, U256::from(188));
+        db.insert_account_storage(&l1_block, L1_FEE_SCALAR_SLOT, U256::from(684000));
+        // base fee scalar 1368, blob base fee scalar 810949 and sequence number 5
+        let scalars = (U256::from(1368) << 96) | (U256::from(810949) << 64) | U256::from(5);
+        db.insert_account_storage(&l1_block, L1_SEQUENCE_NUMBER_SLOT, scalars);
+        db.insert_account_storage(&l1_block, L1_BLOB_BASE_FEE_SLOT, U256::from(10));
+
+        let bedrock = L1FeeParams::from_db(&mut db, l1_block, false).unwrap();
+        assert_eq!(
+            bedrock,
+            L1FeeParams {
+                l1_base_fee: U256::from(1000),
+                l1_fee_overhead: U256::from(188),
+                l1_fee_scalar: U256::from(684000),
+                ..Default::default()
+            }
+        );
+
+        let ecotone = L1FeeParams::from_db(&mut db, l1_block, true).unwrap();
+        assert_eq!(ecotone.l1_base_fee_scalar, U256::from(1368));
+        assert_eq!(ecotone.l1_blob_base_fee_scalar, U256::from(810949));
+        assert_eq!(ecotone.l1_blob_base_fee, U256::from(10));
+    }
+}
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
 use hashbrown::HashMap;
 use revm::primitives::Address;
 use zeth_primitives::{
@@ -24,7 +23,7 @@ use zeth_primitives::{
 };
 
 use crate::{
-    block_builder::BlockBuilder,
+    block_builder::{BlockBuildError, BlockBuilder},
     guest_mem_forget,
     mem_db::{AccountState, MemDb},
 };
@@ -33,7 +32,19 @@ pub trait BlockBuildStrategy<E: TxEssence> {
     type Database;
     type Output;
 
-    fn build(block_builder: BlockBuilder<Self::Database, E>) -> Result<Self::Output>;
+    fn build(
+        block_builder: BlockBuilder<Self::Database, E>,
+    ) -> Result<Self::Output, BlockBuildError>;
+}
+
+/// Block build strategy for a [MemDb], which keeps the updated state in the input tries,
+/// so that they can be reused for building the next block.
+pub trait MemDbBuildStrategy {
+    /// Applies the changes of the database to the input tries and returns the verified
+    /// header.
+    fn build_in_place<E: TxEssence>(
+        block_builder: &mut BlockBuilder<MemDb, E>,
+    ) -> Result<Header, BlockBuildError>;
 }
 
 pub struct BuildFromMemDbStrategy {}
@@ -42,7 +53,23 @@ impl BuildFromMemDbStrategy {
     pub fn build_header<E: TxEssence>(
         debug_storage_tries: &mut Option<HashMap<Address, MptNode>>,
         mut block_builder: BlockBuilder<MemDb, E>,
-    ) -> Result<Header> {
+    ) -> Result<Header, BlockBuildError> {
+        let header = Self::apply_state_updates(debug_storage_tries, &mut block_builder)?;
+
+        // Leak memory, save cycles
+        guest_mem_forget(block_builder);
+
+        Ok(header)
+    }
+
+    /// Applies the changes of the database to the input tries and returns the header.
+    ///
+    /// Afterwards, the input tries contain the state after the block, so that they can be
+    /// reused for building the next block.
+    pub fn apply_state_updates<E: TxEssence>(
+        debug_storage_tries: &mut Option<HashMap<Address, MptNode>>,
+        block_builder: &mut BlockBuilder<MemDb, E>,
+    ) -> Result<Header, BlockBuildError> {
         let db = block_builder.db.as_ref().unwrap();
 
         // apply state updates
@@ -55,7 +82,7 @@ impl BuildFromMemDbStrategy {
                         .input
                         .parent_storage
                         .get(address)
-                        .unwrap()
+                        .ok_or(BlockBuildError::MissingWitness(*address))?
                         .0
                         .clone();
                     map.insert(*address, storage_root);
@@ -68,7 +95,9 @@ impl BuildFromMemDbStrategy {
 
             // remove deleted accounts from the state trie
             if account.state == AccountState::Deleted {
-                state_trie.delete(&state_trie_index)?;
+                state_trie
+                    .delete(&state_trie_index)
+                    .map_err(|err| BlockBuildError::from_trie(*address, err))?;
                 continue;
             }
 
@@ -77,8 +106,11 @@ impl BuildFromMemDbStrategy {
             let storage_root = {
                 // getting a mutable reference is more efficient than calling remove
                 // every account must have an entry, even newly created accounts
-                let (storage_trie, _) =
-                    block_builder.input.parent_storage.get_mut(address).unwrap();
+                let (storage_trie, _) = block_builder
+                    .input
+                    .parent_storage
+                    .get_mut(address)
+                    .ok_or(BlockBuildError::MissingWitness(*address))?;
                 // for cleared accounts always start from the empty trie
                 if account.state == AccountState::StorageCleared {
                     storage_trie.clear();
@@ -87,11 +119,12 @@ impl BuildFromMemDbStrategy {
                 // apply all new storage entries for the current account (address)
                 for (key, value) in state_storage {
                     let storage_trie_index = keccak(key.to_be_bytes::<32>());
-                    if value == &U256::ZERO {
-                        storage_trie.delete(&storage_trie_index)?;
+                    let result = if value == &U256::ZERO {
+                        storage_trie.delete(&storage_trie_index)
                     } else {
-                        storage_trie.insert_rlp(&storage_trie_index, *value)?;
-                    }
+                        storage_trie.insert_rlp(&storage_trie_index, *value)
+                    };
+                    result.map_err(|err| BlockBuildError::from_trie(*address, err))?;
                 }
 
                 // insert the storage trie for host debugging
@@ -108,7 +141,9 @@ impl BuildFromMemDbStrategy {
                 storage_root,
                 code_hash: account.info.code_hash,
             };
-            state_trie.insert_rlp(&state_trie_index, state_account)?;
+            state_trie
+                .insert_rlp(&state_trie_index, state_account)
+                .map_err(|err| BlockBuildError::from_trie(*address, err))?;
         }
 
         // update result header with the new state root
@@ -118,9 +153,6 @@ impl BuildFromMemDbStrategy {
             .expect("Header was not initialized");
         header.state_root = state_trie.hash();
 
-        // Leak memory, save cycles
-        guest_mem_forget(block_builder);
-
         Ok(header)
     }
 }
@@ -130,18 +162,81 @@ impl<E: TxEssence> BlockBuildStrategy<E> for BuildFromMemDbStrategy {
     type Output = Header;
 
     #[inline(always)]
-    fn build(block_builder: BlockBuilder<Self::Database, E>) -> Result<Self::Output> {
+    fn build(
+        block_builder: BlockBuilder<Self::Database, E>,
+    ) -> Result<Self::Output, BlockBuildError> {
         BuildFromMemDbStrategy::build_header(&mut None, block_builder)
     }
 }
 
+impl MemDbBuildStrategy for BuildFromMemDbStrategy {
+    fn build_in_place<E: TxEssence>(
+        block_builder: &mut BlockBuilder<MemDb, E>,
+    ) -> Result<Header, BlockBuildError> {
+        BuildFromMemDbStrategy::apply_state_updates(&mut None, block_builder)
+    }
+}
+
+/// Builds proof-of-work blocks like [BuildFromMemDbStrategy] and verifies the Ethash
+/// seals of the block and its ommers.
+///
+/// The cache of each epoch is only generated once, but this still dominates the cost of
+/// building small blocks, see [crate::ethash].
+#[cfg(feature = "ethash")]
+pub struct PowBuildFromMemDbStrategy {}
+
+#[cfg(feature = "ethash")]
+impl<E: TxEssence> BlockBuildStrategy<E> for PowBuildFromMemDbStrategy {
+    type Database = MemDb;
+    type Output = Header;
+
+    fn build(
+        mut block_builder: BlockBuilder<Self::Database, E>,
+    ) -> Result<Self::Output, BlockBuildError> {
+        let header = Self::build_in_place(&mut block_builder)?;
+
+        // Leak memory, save cycles
+        guest_mem_forget(block_builder);
+
+        Ok(header)
+    }
+}
+
+#[cfg(feature = "ethash")]
+impl MemDbBuildStrategy for PowBuildFromMemDbStrategy {
+    fn build_in_place<E: TxEssence>(
+        block_builder: &mut BlockBuilder<MemDb, E>,
+    ) -> Result<Header, BlockBuildError> {
+        use crate::ethash::{epoch, EthashCache};
+
+        let header = BuildFromMemDbStrategy::apply_state_updates(&mut None, block_builder)?;
+
+        let mut cache = EthashCache::new(epoch(header.number));
+        if !cache.verify_seal(&header) {
+            return Err(BlockBuildError::InvalidSeal(header.number));
+        }
+        for ommer in &block_builder.input.ommers {
+            if cache.epoch() != epoch(ommer.number) {
+                cache = EthashCache::new(epoch(ommer.number));
+            }
+            if !cache.verify_seal(ommer) {
+                return Err(BlockBuildError::InvalidSeal(ommer.number));
+            }
+        }
+
+        Ok(header)
+    }
+}
+
 pub struct DebugBuildFromMemDbStrategy {}
 
 impl<E: TxEssence> BlockBuildStrategy<E> for DebugBuildFromMemDbStrategy {
     type Database = MemDb;
     type Output = (Header, HashMap<Address, MptNode>);
 
-    fn build(block_builder: BlockBuilder<Self::Database, E>) -> Result<Self::Output> {
+    fn build(
+        block_builder: BlockBuilder<Self::Database, E>,
+    ) -> Result<Self::Output, BlockBuildError> {
         let mut storage_trace = Some(Default::default());
         let header = BuildFromMemDbStrategy::build_header(&mut storage_trace, block_builder)?;
         Ok((header, storage_trace.unwrap()))
@@ -0,0 +1,269 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verdicts on the validity of a claimed block, e.g. to prove that a block is invalid.
+
+use core::fmt::Debug;
+
+use revm::Database;
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{
+    block::Header, keccak::keccak, rlp, transactions::TxEssence, trie::MptNode, BlockHash,
+    RlpBytes, B256,
+};
+
+use crate::{
+    block_builder::{BlockBuildError, ConfiguredBlockBuilder, NetworkStrategyBundle},
+    consts::ChainSpec,
+    finalization::BlockBuildStrategy,
+    input::Input,
+};
+
+/// Consensus rule that is violated by an invalid block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockRule {
+    /// The block number must not overflow.
+    BlockNumber,
+    /// The gas limit must be within the bounds given by the parent.
+    GasLimit,
+    /// The timestamp must be after the timestamp of the parent.
+    Timestamp,
+    /// The extra data must not exceed the maximum size.
+    ExtraData,
+    /// The proof-of-work nonce must be zero after the Paris update.
+    Nonce,
+    /// The ommers must be valid recent ancestors.
+    Ommers,
+    /// The parent beacon block root must be set after the Cancun update.
+    ParentBeaconBlockRoot,
+    /// The L1 attributes of an OP-stack block must follow the parent.
+    L1Attributes,
+    /// The mix hash of an OP-stack block must be the randao of its L1 origin.
+    MixHash,
+    /// The deposits of an OP-stack block must be those emitted in its L1 origin.
+    Deposit,
+    /// Each transaction must be correctly signed.
+    Signature,
+    /// The gas of the transactions must not exceed the block gas limit.
+    TxGasLimit,
+    /// The blob gas of the transactions must not exceed the block blob gas limit.
+    BlobGasLimit,
+    /// The excess blob gas and the blob gas used must fit into 64 bits.
+    BlobGas,
+    /// Each transaction must be accepted by the EVM.
+    Transaction,
+    /// The header must match the header derived from executing the block.
+    Header,
+}
+
+/// Consensus rule violation of an invalid block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// The violated rule.
+    pub rule: BlockRule,
+    /// The index of the offending transaction, if the rule concerns a transaction.
+    pub tx_no: Option<usize>,
+}
+
+/// Verdict on the validity of a claimed block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockVerdict {
+    /// The claimed block is valid and has the given hash.
+    Valid(BlockHash),
+    /// The claimed block is invalid.
+    Invalid(Violation),
+}
+
+impl BlockBuildError {
+    /// Returns the consensus rule violation proven by this error, or `None` if the error
+    /// does not prove the block to be invalid, e.g. because of a missing witness.
+    ///
+    /// Mismatches between the input and the claimed block or its L1 origin only prove the
+    /// input to be wrong, as any prover could otherwise disprove a valid block.
+    pub fn violation(&self) -> Option<Violation> {
+        let (rule, tx_no) = match self {
+            BlockBuildError::InvalidBlockNumber => (BlockRule::BlockNumber, None),
+            BlockBuildError::InvalidGasLimit(_) => (BlockRule::GasLimit, None),
+            BlockBuildError::InvalidTimestamp(_) => (BlockRule::Timestamp, None),
+            BlockBuildError::InvalidExtraData(_) => (BlockRule::ExtraData, None),
+            BlockBuildError::InvalidNonce(_) => (BlockRule::Nonce, None),
+            BlockBuildError::InvalidOmmers(_) | BlockBuildError::InvalidOmmer(_) => {
+                (BlockRule::Ommers, None)
+            }
+            BlockBuildError::MissingParentBeaconBlockRoot => {
+                (BlockRule::ParentBeaconBlockRoot, None)
+            }
+            BlockBuildError::InvalidL1Attributes(_) => (BlockRule::L1Attributes, None),
+            BlockBuildError::InvalidMixHash(_) => (BlockRule::MixHash, None),
+            BlockBuildError::InvalidDeposit { tx_no } => (BlockRule::Deposit, Some(*tx_no)),
+            BlockBuildError::InvalidSignature { tx_no } => (BlockRule::Signature, Some(*tx_no)),
+            BlockBuildError::TxGasExceedsBlock { tx_no } => (BlockRule::TxGasLimit, Some(*tx_no)),
+            BlockBuildError::BlobGasExceedsBlock { tx_no } => {
+                (BlockRule::BlobGasLimit, Some(*tx_no))
+            }
+            BlockBuildError::InvalidBlobGas(_) => (BlockRule::BlobGas, None),
+            BlockBuildError::InvalidTransaction { tx_no, .. } => {
+                (BlockRule::Transaction, Some(*tx_no))
+            }
+            _ => return None,
+        };
+        Some(Violation { rule, tx_no })
+    }
+}
+
+/// Builds the block of the given input and returns a verdict on the claimed header.
+///
+/// The input must contain exactly the body and the header fields of the claimed block,
+/// otherwise an error is returned. Errors that do not prove the block to be invalid, like
+/// missing witnesses, are returned as well.
+pub fn verify_claimed_block<N>(
+    chain_spec: &ChainSpec,
+    claimed_header: &Header,
+    input: Input<N::TxEssence>,
+) -> Result<BlockVerdict, BlockBuildError>
+where
+    N: NetworkStrategyBundle,
+    N::BlockBuildStrategy: BlockBuildStrategy<N::TxEssence, Output = Header>,
+    <N::Database as Database>::Error: Debug + Into<BlockBuildError>,
+{
+    verify_input_matches_claim(claimed_header, &input)?;
+
+    match ConfiguredBlockBuilder::<N>::build_from(chain_spec, input) {
+        Ok(header) => {
+            let hash = header.hash();
+            if hash == claimed_header.hash() {
+                Ok(BlockVerdict::Valid(hash))
+            } else {
+                Ok(BlockVerdict::Invalid(Violation {
+                    rule: BlockRule::Header,
+                    tx_no: None,
+                }))
+            }
+        }
+        Err(err) => err.violation().map(BlockVerdict::Invalid).ok_or(err),
+    }
+}
+
+/// Verifies that the input contains the body and the header fields of the claimed block.
+fn verify_input_matches_claim<E: TxEssence>(
+    claimed_header: &Header,
+    input: &Input<E>,
+) -> Result<(), BlockBuildError> {
+    let mut tx_trie = MptNode::default();
+    for (tx_no, tx) in input.transactions.iter().enumerate() {
+        tx_trie.insert_rlp(&tx_no.to_rlp(), tx)?;
+    }
+    let withdrawals_match = match claimed_header.withdrawals_root {
+        Some(withdrawals_root) => {
+            let mut withdrawals_trie = MptNode::default();
+            for (i, withdrawal) in input.withdrawals.iter().enumerate() {
+                withdrawals_trie.insert_rlp(&i.to_rlp(), withdrawal)?;
+            }
+            withdrawals_trie.hash() == withdrawals_root
+        }
+        None => input.withdrawals.is_empty(),
+    };
+
+    if claimed_header.parent_hash != input.parent_header.hash()
+        || claimed_header.beneficiary != input.beneficiary
+        || claimed_header.gas_limit != input.gas_limit
+        || claimed_header.timestamp != input.timestamp
+        || claimed_header.extra_data != input.extra_data
+        || claimed_header.mix_hash != input.mix_hash
+        || claimed_header.nonce != input.nonce
+        || claimed_header.parent_beacon_block_root != input.parent_beacon_block_root
+        || claimed_header.ommers_hash != B256::from(keccak(rlp::encode(&input.ommers)))
+        || claimed_header.transactions_root != tx_trie.hash()
+        || !withdrawals_match
+    {
+        return Err(BlockBuildError::ClaimMismatch);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{transactions::ethereum::EthereumTxEssence, U256};
+
+    use super::*;
+    use crate::{
+        block_builder::{EthereumBlockBuilder, EthereumStrategyBundle},
+        genesis::{self, Genesis, DEV_GENESIS},
+    };
+
+    fn child_of_genesis(gas_limit: U256) -> (ChainSpec, Input<EthereumTxEssence>) {
+        let genesis: Genesis = serde_json::from_str(DEV_GENESIS).unwrap();
+        let (chain_spec, mut input) = genesis::child_of_genesis(&genesis);
+        input.gas_limit = gas_limit;
+        (chain_spec, input)
+    }
+
+    #[test]
+    fn valid_block() {
+        let (chain_spec, input) = child_of_genesis(U256::from(30_000_000));
+        let header = EthereumBlockBuilder::build_from(&chain_spec, input.clone()).unwrap();
+
+        let verdict =
+            verify_claimed_block::<EthereumStrategyBundle>(&chain_spec, &header, input).unwrap();
+        assert_eq!(verdict, BlockVerdict::Valid(header.hash()));
+    }
+
+    #[test]
+    fn invalid_state_root() {
+        let (chain_spec, input) = child_of_genesis(U256::from(30_000_000));
+        let mut header = EthereumBlockBuilder::build_from(&chain_spec, input.clone()).unwrap();
+        header.state_root = B256::ZERO;
+
+        let verdict =
+            verify_claimed_block::<EthereumStrategyBundle>(&chain_spec, &header, input).unwrap();
+        assert_eq!(
+            verdict,
+            BlockVerdict::Invalid(Violation {
+                rule: BlockRule::Header,
+                tx_no: None
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_gas_limit() {
+        let (chain_spec, valid_input) = child_of_genesis(U256::from(30_000_000));
+        let mut header = EthereumBlockBuilder::build_from(&chain_spec, valid_input).unwrap();
+        // the gas limit must not change by more than 1/1024 of the parent's limit
+        let (_, input) = child_of_genesis(U256::from(60_000_000));
+        header.gas_limit = input.gas_limit;
+
+        let verdict =
+            verify_claimed_block::<EthereumStrategyBundle>(&chain_spec, &header, input).unwrap();
+        assert_eq!(
+            verdict,
+            BlockVerdict::Invalid(Violation {
+                rule: BlockRule::GasLimit,
+                tx_no: None
+            })
+        );
+    }
+
+    #[test]
+    fn input_mismatch() {
+        let (chain_spec, input) = child_of_genesis(U256::from(30_000_000));
+        let mut header = EthereumBlockBuilder::build_from(&chain_spec, input.clone()).unwrap();
+        header.timestamp = U256::from(24);
+
+        let err = verify_claimed_block::<EthereumStrategyBundle>(&chain_spec, &header, input)
+            .unwrap_err();
+        assert!(matches!(err, BlockBuildError::ClaimMismatch));
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Construction of the genesis block and its state from a geth-style `genesis.json`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use hashbrown::HashMap;
+use revm::primitives::SpecId;
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{
+    block::Header,
+    keccak::keccak,
+    trie::{MptNode, StateAccount, EMPTY_ROOT},
+    Address, Bytes, B256, B64, U256, U64,
+};
+
+use crate::{
+    consts::{ChainSpec, GethChainConfig, INITIAL_BASE_FEE},
+    input::StorageEntry,
+};
+
+/// Account in the genesis allocation.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GenesisAccount {
+    #[serde(default)]
+    pub nonce: U64,
+    #[serde(default)]
+    pub balance: U256,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Maps each address with its initial account.
+pub type GenesisAlloc = BTreeMap<Address, GenesisAccount>;
+
+/// Geth-style `genesis.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    pub config: GethChainConfig,
+    #[serde(default)]
+    pub nonce: U64,
+    #[serde(default)]
+    pub timestamp: U256,
+    #[serde(default)]
+    pub extra_data: Bytes,
+    #[serde(default)]
+    pub gas_limit: U256,
+    #[serde(default)]
+    pub difficulty: U256,
+    #[serde(default)]
+    pub mix_hash: B256,
+    #[serde(default)]
+    pub coinbase: Address,
+    #[serde(default)]
+    pub alloc: GenesisAlloc,
+    pub base_fee_per_gas: Option<U256>,
+    pub excess_blob_gas: Option<U256>,
+    pub blob_gas_used: Option<U256>,
+}
+
+/// Fully resolved state of the genesis block.
+#[derive(Debug, Clone, Default)]
+pub struct GenesisState {
+    /// State trie containing all allocated accounts.
+    pub state_trie: MptNode,
+    /// Maps each allocated address with its storage trie and all of its storage slots.
+    pub storage: HashMap<Address, StorageEntry>,
+    /// The code of all unique contracts.
+    pub contracts: Vec<Bytes>,
+}
+
+impl Genesis {
+    /// Returns the chain specification of the genesis configuration.
+    pub fn chain_spec(&self) -> Result<ChainSpec> {
+        self.config.clone().try_into()
+    }
+
+    /// Builds the state tries of the genesis allocation.
+    pub fn state(&self) -> Result<GenesisState> {
+        build_genesis_state(&self.alloc)
+    }
+
+    /// Builds the header of block 0 with the given state root.
+    pub fn header(&self, chain_spec: &ChainSpec, state_root: B256) -> Result<Header> {
+        let timestamp = self
+            .timestamp
+            .try_into()
+            .context("Invalid timestamp: too large")?;
+        let spec_id = chain_spec.spec_id(0, timestamp)?;
+
+        Ok(Header {
+            beneficiary: self.coinbase,
+            state_root,
+            difficulty: self.difficulty,
+            gas_limit: self.gas_limit,
+            timestamp: self.timestamp,
+            extra_data: self.extra_data.clone(),
+            mix_hash: self.mix_hash,
+            nonce: B64::from(self.nonce.to_be_bytes::<8>()),
+            base_fee_per_gas: if spec_id < SpecId::LONDON {
+                None
+            } else {
+                Some(self.base_fee_per_gas.unwrap_or(INITIAL_BASE_FEE))
+            },
+            withdrawals_root: if spec_id < SpecId::SHANGHAI {
+                None
+            } else {
+                Some(EMPTY_ROOT)
+            },
+            blob_gas_used: if spec_id < SpecId::CANCUN {
+                None
+            } else {
+                Some(self.blob_gas_used.unwrap_or_default())
+            },
+            excess_blob_gas: if spec_id < SpecId::CANCUN {
+                None
+            } else {
+                Some(self.excess_blob_gas.unwrap_or_default())
+            },
+            parent_beacon_block_root: if spec_id < SpecId::CANCUN {
+                None
+            } else {
+                Some(B256::ZERO)
+            },
+            // the remaining fields are empty for the genesis block
+            ..Default::default()
+        })
+    }
+}
+
+/// Builds the state trie and the storage tries of the given genesis allocation.
+pub fn build_genesis_state(alloc: &GenesisAlloc) -> Result<GenesisState> {
+    let mut state = GenesisState::default();
+    let mut contracts = HashMap::new();
+
+    for (address, account) in alloc {
+        let mut storage_trie = MptNode::default();
+        for (slot, value) in &account.storage {
+            if value != &U256::ZERO {
+                storage_trie.insert_rlp(&keccak(slot.to_be_bytes::<32>()), *value)?;
+            }
+        }
+
+        let code_hash: B256 = keccak(&account.code).into();
+        if !account.code.is_empty() {
+            contracts.insert(code_hash, account.code.clone());
+        }
+
+        let state_account = StateAccount {
+            nonce: account.nonce.to(),
+            balance: account.balance,
+            storage_root: storage_trie.hash(),
+            code_hash,
+        };
+        state
+            .state_trie
+            .insert_rlp(&keccak(address), state_account)?;
+
+        let slots = account.storage.keys().copied().collect();
+        state.storage.insert(*address, (storage_trie, slots));
+    }
+    state.contracts = contracts.into_values().collect();
+
+    Ok(state)
+}
+
+/// Genesis of the development chain shared by the unit tests.
+#[cfg(test)]
+pub(crate) const DEV_GENESIS: &str = r#"{
+    "config": {
+        "chainId": 1337,
+        "byzantiumBlock": 0,
+        "londonBlock": 0,
+        "terminalTotalDifficulty": 0,
+        "terminalTotalDifficultyPassed": true,
+        "shanghaiTime": 0
+    },
+    "gasLimit": "0x1c9c380",
+    "difficulty": "0x0",
+    "alloc": {
+        "0x0000000000000000000000000000000000000001": {
+            "balance": "0x1"
+        },
+        "0x0000000000000000000000000000000000000002": {
+            "balance": "0x0",
+            "code": "0x6000",
+            "storage": {
+                "0x01": "0x2a"
+            }
+        },
+        "0x00000000000000000000000000000000000000aa": {
+            "balance": "0x1"
+        }
+    }
+}"#;
+
+/// Returns the chain spec of the genesis and the input of an empty block on top of it.
+#[cfg(test)]
+pub(crate) fn child_of_genesis<E: zeth_primitives::transactions::TxEssence>(
+    genesis: &Genesis,
+) -> (ChainSpec, crate::input::Input<E>) {
+    let chain_spec = genesis.chain_spec().unwrap();
+    let state = genesis.state().unwrap();
+    let parent_header = genesis
+        .header(&chain_spec, state.state_trie.hash())
+        .unwrap();
+
+    let input = crate::input::Input {
+        parent_header,
+        beneficiary: Default::default(),
+        gas_limit: U256::from(30_000_000),
+        timestamp: U256::from(12),
+        extra_data: Default::default(),
+        mix_hash: Default::default(),
+        nonce: Default::default(),
+        parent_beacon_block_root: None,
+        transactions: vec![],
+        withdrawals: vec![],
+        ommers: vec![],
+        parent_state_trie: state.state_trie,
+        parent_storage: state.storage,
+        contracts: state.contracts,
+        ancestor_headers: vec![],
+        ancestor_ommers: vec![],
+        l1_origin: None,
+        l1_receipts: vec![],
+    };
+    (chain_spec, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{address, b256, transactions::ethereum::EthereumTxEssence};
+
+    use super::*;
+    use crate::{block_builder::BlockBuilder, initialization::MemDbInitStrategy};
+
+    #[test]
+    fn genesis_input() {
+        let genesis: Genesis = serde_json::from_str(DEV_GENESIS).unwrap();
+        let (_, input) = child_of_genesis::<EthereumTxEssence>(&genesis);
+
+        // the expected values are computed with an independent implementation
+        let header = &input.parent_header;
+        assert_eq!(header.base_fee_per_gas, Some(INITIAL_BASE_FEE));
+        assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT));
+        assert_eq!(
+            header.state_root,
+            b256!("990c24979a67276790a6fd576b3dfa4e4dc01ceb33621a149b3037ee186a53c1")
+        );
+        assert_eq!(
+            header.hash(),
+            b256!("9eadc700cd643551c38c0e56026fbbe73849762e91face188905eab9ead2915a")
+        );
+        assert_eq!(input.contracts.len(), 1);
+
+        // the genesis state must be accepted as the parent of block 1
+        let chain_spec = genesis.chain_spec().unwrap();
+        let block_builder = BlockBuilder::new(&chain_spec, input)
+            .initialize_database::<MemDbInitStrategy>()
+            .unwrap();
+        let db = block_builder.db().unwrap();
+        let account = db
+            .accounts
+            .get(&address!("0000000000000000000000000000000000000002"))
+            .unwrap();
+        assert_eq!(account.storage.get(&U256::from(1)), Some(&U256::from(42)));
+    }
+}
@@ -16,17 +16,20 @@ use std::{
     collections::HashSet,
     fmt::Debug,
     iter::{once, zip},
+    mem,
+    ops::RangeInclusive,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use ethers_core::types::{Bytes, EIP1186ProofResponse, Transaction as EthersTransaction, H256};
-use hashbrown::HashMap;
+use hashbrown::{hash_map::Entry, HashMap};
 use log::info;
 use revm::Database;
 use zeth_primitives::{
     block::Header,
     ethers::{from_ethers_h160, from_ethers_h256, from_ethers_u256},
     keccak::keccak,
+    receipt::Receipt,
     transactions::{Transaction, TxEssence},
     trie::{MptNode, MptNodeData, MptNodeReference, EMPTY_ROOT},
     withdrawal::Withdrawal,
@@ -35,18 +38,22 @@ use zeth_primitives::{
 
 use crate::{
     block_builder::{BlockBuilder, NetworkStrategyBundle},
-    consts::ChainSpec,
+    consts::{ChainSpec, GethChainConfig, OptimismFork, RollupConfig, MAX_OMMER_DEPTH},
+    derivation::L1Block,
     host::{
         mpt::{orphaned_digests, resolve_digests, shorten_key},
-        provider::{new_provider, BlockQuery},
+        provider::{new_provider, AccountQuery, BlockQuery, ProofQuery, Provider, UncleQuery},
     },
-    input::{Input, StorageEntry},
+    input::{Input, MultiBlockInput, StorageEntry},
     mem_db::MemDb,
+    optimism::{output_root, L1BlockInfo, CREATE2_DEPLOYER_ADDRESS},
 };
 
 pub mod mpt;
 pub mod provider;
 pub mod provider_db;
+pub mod simulation;
+pub mod trace;
 
 #[derive(Clone)]
 pub struct Init<E: TxEssence> {
@@ -56,14 +63,145 @@ pub struct Init<E: TxEssence> {
     pub fini_block: Header,
     pub fini_transactions: Vec<Transaction<E>>,
     pub fini_withdrawals: Vec<Withdrawal>,
+    pub fini_ommers: Vec<Header>,
     pub fini_proofs: HashMap<Address, EIP1186ProofResponse>,
     pub ancestor_headers: Vec<Header>,
+    /// Ommers of the parent and of the ancestors, only fetched for blocks with ommers.
+    pub ancestor_ommers: Vec<Vec<Header>>,
+    /// Code that is not contained in the initial state, but deployed by the block.
+    pub contracts: Vec<zeth_primitives::Bytes>,
+    pub l1_origin: Option<Header>,
+    pub l1_receipts: Vec<Receipt>,
+}
+
+/// Loads a [ChainSpec] from a JSON file.
+///
+/// The file can either be a geth-style `genesis.json`, its `config` field alone, or a
+/// serialized [ChainSpec]. The L1 addresses of OP-stack chains missing from a geth-style
+/// config are read from the `rollup.json` given by `rollup_config_path`.
+pub fn load_chain_spec(
+    file_path: &String,
+    rollup_config_path: Option<&String>,
+) -> Result<ChainSpec> {
+    let buf = std::fs::read(file_path)
+        .with_context(|| format!("failed to read chain spec {}", file_path))?;
+    let value: serde_json::Value = serde_json::from_slice(&buf)?;
+    let rollup_config = match rollup_config_path {
+        Some(path) => {
+            let buf = std::fs::read(path)
+                .with_context(|| format!("failed to read rollup config {}", path))?;
+            Some(serde_json::from_slice::<RollupConfig>(&buf)?)
+        }
+        None => None,
+    };
+
+    let config = if let Some(config) = value.get("config") {
+        Some(config.clone())
+    } else if value.get("chainId").is_some() {
+        Some(value.clone())
+    } else {
+        None
+    };
+    let chain_spec = match config {
+        Some(config) => ChainSpec::from_geth_config(
+            serde_json::from_value::<GethChainConfig>(config)?,
+            rollup_config,
+        )?,
+        None => {
+            if rollup_config.is_some() {
+                bail!("A rollup config is only supported for geth-style chain configs");
+            }
+            let chain_spec: ChainSpec = serde_json::from_value(value)?;
+            if let Some(optimism_config) = chain_spec.optimism_config() {
+                optimism_config.validate()?;
+            }
+            chain_spec
+        }
+    };
+    Ok(chain_spec)
+}
+
+/// Fetches the header of the L1 origin of an OP-stack block, as given by the L1
+/// attributes deposit of its `transactions`, together with the receipts of the L1 origin
+/// if the block starts a new epoch. Returns neither for other networks.
+pub fn get_l1_origin<E: TxEssence>(
+    chain_spec: &ChainSpec,
+    l1_cache_path: Option<String>,
+    l1_rpc_url: Option<String>,
+    parent: &Header,
+    transactions: &[Transaction<E>],
+) -> Result<(Option<Header>, Vec<Receipt>)> {
+    let Some(optimism_config) = chain_spec.optimism_config() else {
+        return Ok((None, vec![]));
+    };
+    let ecotone = chain_spec.is_optimism_fork_active(
+        OptimismFork::Ecotone,
+        parent.number,
+        parent.timestamp.saturating_to(),
+    );
+    let deposit = transactions
+        .first()
+        .context("missing L1 attributes deposit")?;
+    let l1_info = L1BlockInfo::from_deposit(deposit, optimism_config, ecotone)?;
+
+    let mut provider = new_provider(l1_cache_path, l1_rpc_url)?;
+    let query = BlockQuery {
+        block_no: l1_info.number,
+    };
+    let l1_origin: Header = provider.get_partial_block(&query)?.try_into()?;
+    // deposits and system config updates only apply to the first block of an epoch
+    let l1_receipts = if l1_info.sequence_number == 0 {
+        provider
+            .get_block_receipts(&query)?
+            .into_iter()
+            .map(Receipt::try_from)
+            .collect::<Result<_>>()?
+    } else {
+        vec![]
+    };
+    provider.save()?;
+
+    info!("L1 origin: {} ({})", l1_origin.number, l1_origin.hash());
+
+    Ok((Some(l1_origin), l1_receipts))
+}
+
+/// Fetches the consecutive L1 blocks with the given numbers, which may contain the
+/// batches of OP-stack blocks.
+pub fn get_l1_blocks(
+    l1_cache_path: Option<String>,
+    l1_rpc_url: Option<String>,
+    block_numbers: RangeInclusive<u64>,
+) -> Result<Vec<L1Block>> {
+    let mut provider = new_provider(l1_cache_path, l1_rpc_url)?;
+    let l1_blocks = block_numbers
+        .map(|block_no| {
+            let block = provider.get_full_block(&BlockQuery { block_no })?;
+            let transactions = block
+                .transactions
+                .iter()
+                .cloned()
+                .map(Transaction::try_from)
+                .collect::<Result<_>>()?;
+            Ok(L1Block {
+                header: block.try_into()?,
+                transactions,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    provider.save()?;
+
+    info!("Fetched {} L1 blocks", l1_blocks.len());
+
+    Ok(l1_blocks)
 }
 
 pub fn get_initial_data<N: NetworkStrategyBundle>(
     chain_spec: ChainSpec,
     cache_path: Option<String>,
     rpc_url: Option<String>,
+    l1_cache_path: Option<String>,
+    l1_rpc_url: Option<String>,
     block_no: u64,
 ) -> Result<Init<N::TxEssence>>
 where
@@ -93,10 +231,54 @@ where
     );
     info!("Transaction count: {:?}", fini_block.transactions.len());
 
+    // Fetch the ommers of the finished block
+    let ommers = (0..fini_block.uncles.len())
+        .map(|uncle_index| {
+            provider
+                .get_uncle_block(&UncleQuery {
+                    block_no,
+                    uncle_index: uncle_index as u64,
+                })?
+                .try_into()
+        })
+        .collect::<Result<Vec<Header>>>()?;
+
+    // The create2deployer is deployed outside of any transaction, so its code is fetched
+    let mut contracts = vec![];
+    if chain_spec.is_optimism_fork_activation(OptimismFork::Canyon, fini_block.timestamp.as_u64()) {
+        let code = provider.get_code(&AccountQuery {
+            block_no,
+            address: CREATE2_DEPLOYER_ADDRESS.into_array().into(),
+        })?;
+        contracts.push(code.0.into());
+    }
+
+    // Ommers are validated against the recent ancestors and the ommers they included
+    let parent_header: Header = init_block.clone().try_into()?;
+    let (ommer_ancestors, ancestor_ommers) = if ommers.is_empty() {
+        (vec![], vec![])
+    } else {
+        get_ommer_ancestry(provider.as_mut(), &parent_header)?
+    };
+
     // Create the provider DB
     let provider_db =
         crate::host::provider_db::ProviderDb::new(provider, init_block.number.unwrap().as_u64());
 
+    let transactions: Vec<Transaction<N::TxEssence>> = fini_block
+        .transactions
+        .clone()
+        .into_iter()
+        .map(|tx| tx.try_into().unwrap())
+        .collect();
+    let (l1_origin, l1_receipts) = get_l1_origin(
+        &chain_spec,
+        l1_cache_path,
+        l1_rpc_url,
+        &parent_header,
+        &transactions,
+    )?;
+
     // Create input
     let input = Input {
         beneficiary: fini_block.author.map(from_ethers_h160).unwrap_or_default(),
@@ -104,12 +286,9 @@ where
         timestamp: from_ethers_u256(fini_block.timestamp),
         extra_data: fini_block.extra_data.0.clone().into(),
         mix_hash: from_ethers_h256(fini_block.mix_hash.unwrap()),
-        transactions: fini_block
-            .transactions
-            .clone()
-            .into_iter()
-            .map(|tx| tx.try_into().unwrap())
-            .collect(),
+        nonce: fini_block.nonce.unwrap_or_default().0.into(),
+        parent_beacon_block_root: fini_block.parent_beacon_block_root.map(from_ethers_h256),
+        transactions: transactions.clone(),
         withdrawals: fini_block
             .withdrawals
             .clone()
@@ -117,11 +296,15 @@ where
             .into_iter()
             .map(|w| w.try_into().unwrap())
             .collect(),
+        ommers: ommers.clone(),
         parent_state_trie: Default::default(),
         parent_storage: Default::default(),
-        contracts: vec![],
-        parent_header: init_block.clone().try_into()?,
-        ancestor_headers: vec![],
+        contracts: contracts.clone(),
+        parent_header,
+        ancestor_headers: ommer_ancestors.clone(),
+        ancestor_ommers: ancestor_ommers.clone(),
+        l1_origin: l1_origin.clone(),
+        l1_receipts: l1_receipts.clone(),
     };
 
     // Create the block builder, run the transactions and extract the DB
@@ -137,8 +320,11 @@ where
     let init_proofs = provider_db.get_initial_proofs()?;
     let fini_proofs = provider_db.get_latest_proofs()?;
 
-    // Gather proofs for block history
-    let ancestor_headers = provider_db.get_ancestor_headers()?;
+    // Gather proofs for block history, which must reach the ancestors of the ommers
+    let mut ancestor_headers = provider_db.get_ancestor_headers()?;
+    if ancestor_headers.len() < ommer_ancestors.len() {
+        ancestor_headers = ommer_ancestors;
+    }
 
     info!("Saving provider cache ...");
 
@@ -147,12 +333,6 @@ where
 
     info!("Provider-backed execution is Done!");
 
-    let transactions = fini_block
-        .transactions
-        .clone()
-        .into_iter()
-        .map(|tx| tx.try_into().unwrap())
-        .collect();
     let withdrawals = fini_block
         .withdrawals
         .clone()
@@ -168,11 +348,46 @@ where
         fini_block: fini_block.try_into()?,
         fini_transactions: transactions,
         fini_withdrawals: withdrawals,
+        fini_ommers: ommers,
         fini_proofs,
         ancestor_headers,
+        ancestor_ommers,
+        contracts,
+        l1_origin,
+        l1_receipts,
     })
 }
 
+/// Fetches the ancestors of `parent` that may be the parents of ommers, together with the
+/// ommers of the parent and of each of these ancestors.
+fn get_ommer_ancestry(
+    provider: &mut dyn Provider,
+    parent: &Header,
+) -> Result<(Vec<Header>, Vec<Vec<Header>>)> {
+    let depth = MAX_OMMER_DEPTH.min(parent.number);
+    let mut ancestor_headers = Vec::with_capacity(depth as usize);
+    let mut ancestor_ommers = Vec::with_capacity(depth as usize + 1);
+    for block_no in (parent.number - depth..=parent.number).rev() {
+        let block = provider.get_partial_block(&BlockQuery { block_no })?;
+        let ommers = (0..block.uncles.len())
+            .map(|uncle_index| {
+                provider
+                    .get_uncle_block(&UncleQuery {
+                        block_no,
+                        uncle_index: uncle_index as u64,
+                    })?
+                    .try_into()
+            })
+            .collect::<Result<Vec<Header>>>()?;
+        ancestor_ommers.push(ommers);
+        if block_no != parent.number {
+            ancestor_headers.push(block.try_into()?);
+        }
+    }
+
+    Ok((ancestor_headers, ancestor_ommers))
+}
+
 #[derive(Debug)]
 pub enum VerifyError {
     BalanceMismatch {
@@ -203,6 +418,30 @@ pub enum VerifyError {
     },
 }
 
+/// Returns the output root of the given OP-stack block, or `None` for other networks.
+///
+/// The `storage_tries` must contain the storage tries after the block, as returned by
+/// [crate::finalization::DebugBuildFromMemDbStrategy].
+pub fn get_output_root(
+    chain_spec: &ChainSpec,
+    header: &Header,
+    storage_tries: &HashMap<Address, MptNode>,
+) -> Result<Option<B256>> {
+    let Some(optimism_config) = chain_spec.optimism_config() else {
+        return Ok(None);
+    };
+    let message_passer = optimism_config.l2_to_l1_message_passer;
+    let storage_trie = storage_tries
+        .get(&message_passer)
+        .with_context(|| format!("storage of the message passer missing: {}", message_passer))?;
+
+    Ok(Some(output_root(
+        header.state_root,
+        storage_trie.hash(),
+        header.hash(),
+    )))
+}
+
 pub fn verify_state(
     mut fini_db: MemDb,
     fini_proofs: HashMap<Address, EIP1186ProofResponse>,
@@ -301,7 +540,7 @@ pub fn verify_state(
                     account_proof
                         .storage_proof
                         .iter()
-                        .find(|&storage| storage_index == storage.key)
+                        .find(|&storage| from_ethers_u256(storage.key) == index)
                         .expect("Could not find storage proof")
                         .value,
                 );
@@ -399,7 +638,7 @@ fn resolve_orphans(
 }
 
 impl<E: TxEssence> From<Init<E>> for Input<E> {
-    fn from(value: Init<E>) -> Input<E> {
+    fn from(mut value: Init<E>) -> Input<E> {
         // construct the proof tries
         let (mut nodes_by_reference, mut storage) =
             proofs_to_tries(value.init_proofs.values().cloned().collect());
@@ -407,7 +646,10 @@ impl<E: TxEssence> From<Init<E>> for Input<E> {
         assert_eq!(storage.len(), value.db.accounts_len());
 
         // collect the code from each account
-        let mut contracts = HashMap::new();
+        let mut contracts: HashMap<_, _> = mem::take(&mut value.contracts)
+            .into_iter()
+            .map(|code| (B256::from(keccak(&code)), code))
+            .collect();
         for account in value.db.accounts.values() {
             let code = account.info.code.clone().unwrap();
             if !code.is_empty() {
@@ -457,18 +699,117 @@ impl<E: TxEssence> From<Init<E>> for Input<E> {
 
         // Create the block builder input
         Input {
-            parent_header: value.init_block,
-            beneficiary: value.fini_block.beneficiary,
-            gas_limit: value.fini_block.gas_limit,
-            timestamp: value.fini_block.timestamp,
-            extra_data: value.fini_block.extra_data.0.clone().into(),
-            mix_hash: value.fini_block.mix_hash,
-            transactions: value.fini_transactions,
-            withdrawals: value.fini_withdrawals,
             parent_state_trie: state_trie,
             parent_storage: storage.into_iter().collect(),
             contracts: contracts.into_values().collect(),
-            ancestor_headers: value.ancestor_headers,
+            ancestor_headers: mem::take(&mut value.ancestor_headers),
+            ancestor_ommers: mem::take(&mut value.ancestor_ommers),
+            ..input_without_witness(value)
         }
     }
 }
+
+/// Returns the block builder input of the given block without any state witness, as used
+/// by all but the first block of a [MultiBlockInput].
+fn input_without_witness<E: TxEssence>(init: Init<E>) -> Input<E> {
+    Input {
+        parent_header: init.init_block,
+        beneficiary: init.fini_block.beneficiary,
+        gas_limit: init.fini_block.gas_limit,
+        timestamp: init.fini_block.timestamp,
+        extra_data: init.fini_block.extra_data.0.clone().into(),
+        mix_hash: init.fini_block.mix_hash,
+        nonce: init.fini_block.nonce,
+        parent_beacon_block_root: init.fini_block.parent_beacon_block_root,
+        transactions: init.fini_transactions,
+        withdrawals: init.fini_withdrawals,
+        ommers: init.fini_ommers,
+        parent_state_trie: Default::default(),
+        parent_storage: Default::default(),
+        contracts: vec![],
+        ancestor_headers: vec![],
+        ancestor_ommers: vec![],
+        l1_origin: init.l1_origin,
+        l1_receipts: init.l1_receipts,
+    }
+}
+
+/// Combines the initial data of consecutive blocks into the input of a single session.
+///
+/// The state witness of the first block is extended by the proofs at its parent of all
+/// accounts and storage slots accessed by the following blocks. The proofs of the final
+/// state of every block are used to resolve the trie nodes required when deleting state.
+pub fn get_multi_block_input<E: TxEssence>(
+    cache_path: Option<String>,
+    rpc_url: Option<String>,
+    inits: Vec<Init<E>>,
+) -> Result<MultiBlockInput<E>> {
+    let mut inits = inits.into_iter();
+    let mut first = inits.next().context("no blocks")?;
+    let parent_no = first.init_block.number;
+    let initial_keys = first.db.storage_keys();
+
+    let mut following = Vec::with_capacity(inits.len());
+    for mut init in inits {
+        for (address, account) in &init.db.accounts {
+            let first_account = first
+                .db
+                .accounts
+                .entry(*address)
+                .or_insert_with(|| account.clone());
+            for (index, value) in &account.storage {
+                first_account.storage.entry(*index).or_insert(*value);
+            }
+        }
+        for (address, proof) in mem::take(&mut init.fini_proofs) {
+            match first.fini_proofs.entry(address) {
+                Entry::Occupied(mut entry) => {
+                    let entry = entry.get_mut();
+                    entry.account_proof.extend(proof.account_proof);
+                    entry.storage_proof.extend(proof.storage_proof);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(proof);
+                }
+            }
+        }
+        // later parents are built in the session, so only older ancestors are needed
+        let ancestor_headers: Vec<_> = mem::take(&mut init.ancestor_headers)
+            .into_iter()
+            .filter(|header| header.number < parent_no)
+            .collect();
+        if ancestor_headers.len() > first.ancestor_headers.len() {
+            first.ancestor_headers = ancestor_headers;
+        }
+        first.contracts.append(&mut init.contracts);
+        following.push(init);
+    }
+
+    info!("Gathering inclusion proofs of the following blocks ...");
+
+    // Prove the accounts and slots first accessed by the following blocks at the parent
+    let mut provider = new_provider(cache_path, rpc_url)?;
+    for (address, indices) in first.db.storage_keys() {
+        if initial_keys
+            .get(&address)
+            .is_some_and(|keys| keys.len() == indices.len())
+        {
+            continue;
+        }
+        let proof = provider.get_proof(&ProofQuery {
+            block_no: parent_no,
+            address: address.into_array().into(),
+            indices: indices
+                .into_iter()
+                .map(|index| index.to_be_bytes().into())
+                .collect(),
+        })?;
+        first.init_proofs.insert(address, proof);
+    }
+    provider.save()?;
+
+    let blocks = once(Input::from(first))
+        .chain(following.into_iter().map(input_without_witness))
+        .collect();
+    Ok(MultiBlockInput { blocks })
+}
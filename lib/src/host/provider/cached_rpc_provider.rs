@@ -13,11 +13,13 @@
 // limitations under the License.
 
 use anyhow::Result;
-use ethers_core::types::{Block, Bytes, EIP1186ProofResponse, Transaction, H256, U256};
+use ethers_core::types::{
+    Block, Bytes, EIP1186ProofResponse, Transaction, TransactionReceipt, H256, U256,
+};
 
 use super::{
     file_provider::FileProvider, rpc_provider::RpcProvider, AccountQuery, BlockQuery, MutProvider,
-    ProofQuery, Provider, StorageQuery,
+    ProofQuery, Provider, StorageQuery, UncleQuery,
 };
 
 pub struct CachedRpcProvider {
@@ -66,6 +68,30 @@ impl Provider for CachedRpcProvider {
         Ok(out)
     }
 
+    fn get_uncle_block(&mut self, query: &UncleQuery) -> Result<Block<H256>> {
+        let cache_out = self.cache.get_uncle_block(query);
+        if cache_out.is_ok() {
+            return cache_out;
+        }
+
+        let out = self.rpc.get_uncle_block(query)?;
+        self.cache.insert_uncle_block(query.clone(), out.clone());
+
+        Ok(out)
+    }
+
+    fn get_block_receipts(&mut self, query: &BlockQuery) -> Result<Vec<TransactionReceipt>> {
+        let cache_out = self.cache.get_block_receipts(query);
+        if cache_out.is_ok() {
+            return cache_out;
+        }
+
+        let out = self.rpc.get_block_receipts(query)?;
+        self.cache.insert_block_receipts(query.clone(), out.clone());
+
+        Ok(out)
+    }
+
     fn get_proof(&mut self, query: &ProofQuery) -> Result<EIP1186ProofResponse> {
         let cache_out = self.cache.get_proof(query);
         if cache_out.is_ok() {
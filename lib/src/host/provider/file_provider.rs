@@ -19,11 +19,15 @@ use std::{
 };
 
 use anyhow::{anyhow, Result};
-use ethers_core::types::{Block, Bytes, EIP1186ProofResponse, Transaction, H256, U256};
+use ethers_core::types::{
+    Block, Bytes, EIP1186ProofResponse, Transaction, TransactionReceipt, H256, U256,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use super::{AccountQuery, BlockQuery, MutProvider, ProofQuery, Provider, StorageQuery};
+use super::{
+    AccountQuery, BlockQuery, MutProvider, ProofQuery, Provider, StorageQuery, UncleQuery,
+};
 
 #[serde_as]
 #[derive(Deserialize, Serialize)]
@@ -36,6 +40,12 @@ pub struct FileProvider {
     full_blocks: HashMap<BlockQuery, Block<Transaction>>,
     #[serde_as(as = "Vec<(_, _)>")]
     partial_blocks: HashMap<BlockQuery, Block<H256>>,
+    #[serde(default)]
+    #[serde_as(as = "Vec<(_, _)>")]
+    uncle_blocks: HashMap<UncleQuery, Block<H256>>,
+    #[serde(default)]
+    #[serde_as(as = "Vec<(_, _)>")]
+    block_receipts: HashMap<BlockQuery, Vec<TransactionReceipt>>,
     #[serde_as(as = "Vec<(_, _)>")]
     proofs: HashMap<ProofQuery, EIP1186ProofResponse>,
     #[serde_as(as = "Vec<(_, _)>")]
@@ -55,6 +65,8 @@ impl FileProvider {
             dirty: false,
             full_blocks: HashMap::new(),
             partial_blocks: HashMap::new(),
+            uncle_blocks: HashMap::new(),
+            block_receipts: HashMap::new(),
             proofs: HashMap::new(),
             transaction_count: HashMap::new(),
             balance: HashMap::new(),
@@ -108,6 +120,20 @@ impl Provider for FileProvider {
         }
     }
 
+    fn get_uncle_block(&mut self, query: &UncleQuery) -> Result<Block<H256>> {
+        match self.uncle_blocks.get(query) {
+            Some(val) => Ok(val.clone()),
+            None => Err(anyhow!("No data for {:?}", query)),
+        }
+    }
+
+    fn get_block_receipts(&mut self, query: &BlockQuery) -> Result<Vec<TransactionReceipt>> {
+        match self.block_receipts.get(query) {
+            Some(val) => Ok(val.clone()),
+            None => Err(anyhow!("No data for {:?}", query)),
+        }
+    }
+
     fn get_proof(&mut self, query: &ProofQuery) -> Result<EIP1186ProofResponse> {
         match self.proofs.get(query) {
             Some(val) => Ok(val.clone()),
@@ -155,6 +181,16 @@ impl MutProvider for FileProvider {
         self.dirty = true;
     }
 
+    fn insert_uncle_block(&mut self, query: UncleQuery, val: Block<H256>) {
+        self.uncle_blocks.insert(query, val);
+        self.dirty = true;
+    }
+
+    fn insert_block_receipts(&mut self, query: BlockQuery, val: Vec<TransactionReceipt>) {
+        self.block_receipts.insert(query, val);
+        self.dirty = true;
+    }
+
     fn insert_proof(&mut self, query: ProofQuery, val: EIP1186ProofResponse) {
         self.proofs.insert(query, val);
         self.dirty = true;
@@ -13,11 +13,13 @@
 // limitations under the License.
 
 use anyhow::{anyhow, Result};
-use ethers_core::types::{Block, Bytes, EIP1186ProofResponse, Transaction, H256, U256};
+use ethers_core::types::{
+    Block, Bytes, EIP1186ProofResponse, Transaction, TransactionReceipt, H256, U256,
+};
 use ethers_providers::{Http, Middleware};
 use log::info;
 
-use super::{AccountQuery, BlockQuery, ProofQuery, Provider, StorageQuery};
+use super::{AccountQuery, BlockQuery, ProofQuery, Provider, StorageQuery, UncleQuery};
 
 pub struct RpcProvider {
     http_client: ethers_providers::Provider<Http>,
@@ -67,6 +69,31 @@ impl Provider for RpcProvider {
         }
     }
 
+    fn get_uncle_block(&mut self, query: &UncleQuery) -> Result<Block<H256>> {
+        info!("Querying RPC for uncle block: {:?}", query);
+
+        let response = self.tokio_handle.block_on(async {
+            self.http_client
+                .get_uncle(query.block_no, query.uncle_index.into())
+                .await
+        })?;
+
+        match response {
+            Some(out) => Ok(out),
+            None => Err(anyhow!("No data for {:?}", query)),
+        }
+    }
+
+    fn get_block_receipts(&mut self, query: &BlockQuery) -> Result<Vec<TransactionReceipt>> {
+        info!("Querying RPC for block receipts: {:?}", query);
+
+        let out = self
+            .tokio_handle
+            .block_on(async { self.http_client.get_block_receipts(query.block_no).await })?;
+
+        Ok(out)
+    }
+
     fn get_proof(&mut self, query: &ProofQuery) -> Result<EIP1186ProofResponse> {
         info!("Querying RPC for inclusion proof: {:?}", query);
 
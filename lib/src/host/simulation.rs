@@ -0,0 +1,313 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of hypothetical blocks on top of an existing parent block.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use hashbrown::HashMap;
+use log::info;
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{
+    block::Header,
+    receipt::Receipt,
+    transactions::{Transaction, TxEssence},
+    withdrawal::Withdrawal,
+    Address, Bytes, B256, U256,
+};
+
+use crate::{
+    block_builder::{BlockBuilder, NetworkStrategyBundle},
+    consts::ChainSpec,
+    execution::TxResult,
+    finalization::DebugBuildFromMemDbStrategy,
+    host::{
+        get_l1_origin, get_output_root,
+        provider::{new_provider, BlockQuery},
+        provider_db::ProviderDb,
+        Init,
+    },
+    initialization::MemDbInitStrategy,
+    input::Input,
+    mem_db::{AccountState, MemDb},
+};
+
+/// Hypothetical block, which does not need to exist on chain.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimulatedBlock<E: TxEssence> {
+    /// Address to which the block rewards and fees are sent.
+    pub beneficiary: Address,
+    /// Maximum amount of gas consumed by the transactions within the block.
+    pub gas_limit: U256,
+    /// Timestamp of the block.
+    pub timestamp: U256,
+    /// Arbitrary data of the block header.
+    pub extra_data: Bytes,
+    /// Hash previously used for the PoW, now containing the RANDAO value.
+    pub mix_hash: B256,
+    /// Hash of the parent beacon block, required after the Cancun update.
+    pub parent_beacon_block_root: Option<B256>,
+    /// List of transactions to execute in the given order.
+    pub transactions: Vec<Transaction<E>>,
+    /// List of withdrawals to process after the transactions.
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+/// Change of an account caused by a simulated block, as `(before, after)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountDiff {
+    pub balance: (U256, U256),
+    pub nonce: (u64, u64),
+    pub code_hash: (B256, B256),
+    /// Only the storage slots whose value has changed.
+    pub storage: BTreeMap<U256, (U256, U256)>,
+}
+
+/// Result of simulating a block.
+#[derive(Debug, Clone)]
+pub struct Simulation<E: TxEssence> {
+    /// Header of the resulting block.
+    pub header: Header,
+    /// Receipts of the executed transactions.
+    pub receipts: Vec<Receipt>,
+    /// Results of the executed transactions.
+    pub tx_results: Vec<TxResult>,
+    /// Changes of all the modified accounts.
+    pub state_diff: HashMap<Address, AccountDiff>,
+    /// Output root of the resulting block, only for OP-stack chains.
+    pub output_root: Option<B256>,
+    /// Whether the block deletes accounts or storage slots of the parent state, in which
+    /// case its state root may be wrong and it must not be proven.
+    pub deletes_state: bool,
+    /// Witness to build and prove the block in the guest.
+    pub input: Input<E>,
+}
+
+/// Builds the given hypothetical block with number `block_no` on top of the existing
+/// block `block_no - 1`.
+///
+/// As the block does not exist, there are no proofs of its final state. Hence, trie nodes
+/// that only become relevant when deleting state cannot be resolved, and the state root of
+/// such blocks may be wrong, which is indicated by [Simulation::deletes_state].
+pub fn simulate_block<N>(
+    chain_spec: ChainSpec,
+    cache_path: Option<String>,
+    rpc_url: Option<String>,
+    l1_cache_path: Option<String>,
+    l1_rpc_url: Option<String>,
+    block_no: u64,
+    block: SimulatedBlock<N::TxEssence>,
+) -> Result<Simulation<N::TxEssence>>
+where
+    N: NetworkStrategyBundle,
+{
+    let parent_no = block_no
+        .checked_sub(1)
+        .context("the genesis block cannot be simulated")?;
+    let mut provider = new_provider(cache_path, rpc_url)?;
+
+    // Fetch the parent block
+    let init_block = provider.get_partial_block(&BlockQuery {
+        block_no: parent_no,
+    })?;
+
+    info!(
+        "Parent block: {:?} ({:?})",
+        init_block.number.unwrap(),
+        init_block.hash.unwrap()
+    );
+    info!("Transaction count: {:?}", block.transactions.len());
+
+    // Create the provider DB
+    let provider_db = ProviderDb::new(provider, init_block.number.unwrap().as_u64());
+
+    let parent_header: Header = init_block.clone().try_into()?;
+    let (l1_origin, l1_receipts) = get_l1_origin(
+        &chain_spec,
+        l1_cache_path,
+        l1_rpc_url,
+        &parent_header,
+        &block.transactions,
+    )?;
+
+    // Create input
+    let input = Input {
+        parent_header,
+        beneficiary: block.beneficiary,
+        gas_limit: block.gas_limit,
+        timestamp: block.timestamp,
+        extra_data: block.extra_data.clone(),
+        mix_hash: block.mix_hash,
+        nonce: Default::default(),
+        parent_beacon_block_root: block.parent_beacon_block_root,
+        transactions: block.transactions.clone(),
+        withdrawals: block.withdrawals.clone(),
+        ommers: vec![],
+        parent_state_trie: Default::default(),
+        parent_storage: Default::default(),
+        contracts: vec![],
+        ancestor_headers: vec![],
+        ancestor_ommers: vec![],
+        l1_origin: l1_origin.clone(),
+        l1_receipts: l1_receipts.clone(),
+    };
+
+    // Create the block builder, run the transactions and extract the DB
+    let mut builder = BlockBuilder::new(&chain_spec, input)
+        .with_db(provider_db)
+        .prepare_header::<N::HeaderPrepStrategy>()?
+        .execute_transactions::<N::TxExecStrategy>()?;
+    let provider_db = builder.mut_db().unwrap();
+
+    info!("Gathering inclusion proofs ...");
+
+    // Gather inclusion proofs for the initial state and the block history
+    let init_proofs = provider_db.get_initial_proofs()?;
+    let ancestor_headers = provider_db.get_ancestor_headers()?;
+
+    info!("Saving provider cache ...");
+
+    // Save the provider cache
+    provider_db.get_provider().save()?;
+
+    info!("Provider-backed execution is Done!");
+
+    let initial_db = provider_db.get_initial_db().clone();
+    let init = Init {
+        db: initial_db.clone(),
+        init_block: init_block.try_into()?,
+        init_proofs,
+        // only the fields given by the simulated block are used to create the input
+        fini_block: Header {
+            beneficiary: block.beneficiary,
+            gas_limit: block.gas_limit,
+            timestamp: block.timestamp,
+            extra_data: block.extra_data,
+            mix_hash: block.mix_hash,
+            parent_beacon_block_root: block.parent_beacon_block_root,
+            ..Default::default()
+        },
+        fini_transactions: block.transactions,
+        fini_withdrawals: block.withdrawals,
+        fini_ommers: vec![],
+        fini_proofs: Default::default(),
+        ancestor_headers,
+        ancestor_ommers: vec![],
+        contracts: vec![],
+        l1_origin,
+        l1_receipts,
+    };
+    let input: Input<N::TxEssence> = init.into();
+
+    // Build the block from the witness to make sure that it is complete
+    let builder = BlockBuilder::new(&chain_spec, input.clone())
+        .initialize_database::<MemDbInitStrategy>()?
+        .prepare_header::<N::HeaderPrepStrategy>()?
+        .execute_transactions::<N::TxExecStrategy>()?;
+    let receipts = builder.receipts().to_vec();
+    let tx_results = builder.tx_results().to_vec();
+    let state_diff = state_diff(&initial_db, builder.db().unwrap());
+    let deletes_state = deletes_state(&initial_db, builder.db().unwrap());
+    let (header, storage_tries) = builder.build::<DebugBuildFromMemDbStrategy>()?;
+    let output_root = get_output_root(&chain_spec, &header, &storage_tries)?;
+
+    info!("Memory-backed execution is Done!");
+
+    Ok(Simulation {
+        header,
+        receipts,
+        tx_results,
+        state_diff,
+        output_root,
+        deletes_state,
+        input,
+    })
+}
+
+/// Returns whether `fini_db` deletes any account or storage slot present in `initial_db`.
+fn deletes_state(initial_db: &MemDb, fini_db: &MemDb) -> bool {
+    fini_db.accounts.iter().any(|(address, account)| {
+        let Some(initial_account) = initial_db.accounts.get(address) else {
+            return false;
+        };
+        match account.state {
+            AccountState::Deleted => !initial_account.info.is_empty(),
+            // cleared storage is rebuilt from the empty trie
+            AccountState::StorageCleared => false,
+            _ => account.storage.iter().any(|(slot, value)| {
+                value == &U256::ZERO
+                    && initial_account
+                        .storage
+                        .get(slot)
+                        .is_some_and(|before| before != &U256::ZERO)
+            }),
+        }
+    })
+}
+
+/// Returns the changes of all accounts modified in `fini_db` compared to `initial_db`.
+fn state_diff(initial_db: &MemDb, fini_db: &MemDb) -> HashMap<Address, AccountDiff> {
+    let mut diff = HashMap::new();
+    for (address, account) in &fini_db.accounts {
+        if account.state == AccountState::None {
+            continue;
+        }
+        let initial_account = initial_db.accounts.get(address);
+        let before = initial_account
+            .and_then(|account| account.info())
+            .unwrap_or_default();
+        let after = account.info().unwrap_or_default();
+
+        // slots that have not been written keep their value, unless the storage is cleared
+        let storage_cleared = matches!(
+            account.state,
+            AccountState::Deleted | AccountState::StorageCleared
+        );
+        let mut storage = BTreeMap::new();
+        let initial_storage = initial_account.map(|account| &account.storage);
+        let slots = initial_storage
+            .into_iter()
+            .flat_map(|storage| storage.keys())
+            .chain(account.storage.keys());
+        for slot in slots {
+            let before = initial_storage
+                .and_then(|storage| storage.get(slot).copied())
+                .unwrap_or_default();
+            let after = match account.storage.get(slot) {
+                Some(value) => *value,
+                None if storage_cleared => U256::ZERO,
+                None => before,
+            };
+            if before != after {
+                storage.insert(*slot, (before, after));
+            }
+        }
+
+        let account_diff = AccountDiff {
+            balance: (before.balance, after.balance),
+            nonce: (before.nonce, after.nonce),
+            code_hash: (before.code_hash, after.code_hash),
+            storage,
+        };
+        if account_diff.balance.0 != account_diff.balance.1
+            || account_diff.nonce.0 != account_diff.nonce.1
+            || account_diff.code_hash.0 != account_diff.code_hash.1
+            || !account_diff.storage.is_empty()
+        {
+            diff.insert(*address, account_diff);
+        }
+    }
+    diff
+}
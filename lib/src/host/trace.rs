@@ -0,0 +1,318 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inspectors tracing the execution of transactions in the formats of geth's
+//! `debug_traceTransaction`.
+
+use revm::{
+    interpreter::{
+        opcode::OPCODE_JUMPMAP, CallInputs, CallScheme, CreateInputs, CreateScheme, Gas,
+        InstructionResult, Interpreter,
+    },
+    Database, EVMData, Inspector,
+};
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{hex, Address, Bytes, U256, U64};
+
+use crate::execution::{TxOutcome, TxResult};
+
+/// Returns whether the instruction result ends a call successfully.
+fn is_success(ret: InstructionResult) -> bool {
+    matches!(
+        ret,
+        InstructionResult::Stop | InstructionResult::Return | InstructionResult::SelfDestruct
+    )
+}
+
+/// Returns the geth error message of an unsuccessful instruction result.
+fn error_message(ret: InstructionResult) -> String {
+    match ret {
+        InstructionResult::Revert => "execution reverted".to_string(),
+        ret => format!("{:?}", ret),
+    }
+}
+
+/// Single step of the struct log tracer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    pub stack: Vec<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Trace of a transaction in the format of geth's default struct log tracer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    pub gas: u64,
+    pub failed: bool,
+    /// Hex-encoded return value, without the `0x` prefix like geth.
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+impl StructLogTrace {
+    /// Returns the index of the first step that differs from the other trace, or `None`
+    /// if all the steps are equal.
+    ///
+    /// The gas cost of steps entering a new call frame is ignored, as geth accounts the
+    /// gas passed to the callee differently.
+    pub fn first_divergence(&self, other: &StructLogTrace) -> Option<usize> {
+        let len = self.struct_logs.len().max(other.struct_logs.len());
+        (0..len).find(
+            |&i| match (self.struct_logs.get(i), other.struct_logs.get(i)) {
+                (Some(a), Some(b)) => {
+                    let enters_call = other
+                        .struct_logs
+                        .get(i + 1)
+                        .is_some_and(|next| next.depth > b.depth);
+                    a.pc != b.pc
+                        || a.op != b.op
+                        || a.gas != b.gas
+                        || (a.gas_cost != b.gas_cost && !enters_call)
+                        || a.depth != b.depth
+                        || a.stack != b.stack
+                }
+                _ => true,
+            },
+        )
+    }
+}
+
+/// Inspector recording every executed opcode like geth's default struct log tracer.
+///
+/// Memory and storage are not recorded.
+#[derive(Debug, Clone, Default)]
+pub struct StructLogger {
+    logs: Vec<StructLog>,
+    /// Indices of the logs whose step has not finished, one for each call depth.
+    pending: Vec<usize>,
+}
+
+impl StructLogger {
+    /// Returns the recorded steps.
+    pub fn struct_logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    /// Returns the trace of the transaction with the given result.
+    pub fn into_trace(self, tx_result: &TxResult) -> StructLogTrace {
+        let (failed, output) = match &tx_result.outcome {
+            TxOutcome::Success { output, .. } => (false, output.clone()),
+            TxOutcome::Revert { output } => (true, output.clone()),
+            TxOutcome::Halt { .. } => (true, Bytes::new()),
+        };
+        StructLogTrace {
+            gas: tx_result.gas_used.to(),
+            failed,
+            return_value: hex::encode(output),
+            struct_logs: self.logs,
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogger {
+    fn step(&mut self, interp: &mut Interpreter, data: &mut EVMData<'_, DB>) -> InstructionResult {
+        let opcode = interp.current_opcode();
+        let op = OPCODE_JUMPMAP[opcode as usize]
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("opcode {:#04x}", opcode));
+
+        self.pending.push(self.logs.len());
+        self.logs.push(StructLog {
+            pc: interp.program_counter() as u64,
+            op,
+            gas: interp.gas.remaining(),
+            gas_cost: 0,
+            depth: data.journaled_state.depth as u64,
+            stack: interp.stack.data().clone(),
+            error: None,
+        });
+
+        InstructionResult::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        eval: InstructionResult,
+    ) -> InstructionResult {
+        if let Some(log) = self.pending.pop().and_then(|i| self.logs.get_mut(i)) {
+            log.gas_cost = log.gas.saturating_sub(interp.gas.remaining());
+            if eval != InstructionResult::Continue && !is_success(eval) {
+                log.error = Some(error_message(eval));
+            }
+        }
+
+        InstructionResult::Continue
+    }
+}
+
+/// Call frame in the format of geth's `callTracer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: Address,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    pub gas: U64,
+    pub gas_used: U64,
+    pub input: Bytes,
+    #[serde(default)]
+    pub output: Bytes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+/// Inspector recording the call frames like geth's `callTracer`.
+///
+/// As recorded, the gas of the outermost frame does not include the intrinsic gas of the
+/// transaction; [CallTracer::into_trace] accounts for the transaction as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct CallTracer {
+    /// Frames that have been entered but not yet exited.
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// Returns the outermost call frame, if the transaction has been executed.
+    pub fn root(&self) -> Option<&CallFrame> {
+        self.root.as_ref()
+    }
+
+    /// Returns the trace of the transaction with the given gas limit and result, i.e. the
+    /// outermost call frame with the gas of the whole transaction, if it has been executed.
+    pub fn into_trace(self, gas_limit: U256, tx_result: &TxResult) -> Option<CallFrame> {
+        self.root.map(|root| CallFrame {
+            gas: gas_limit.to(),
+            gas_used: tx_result.gas_used.to(),
+            ..root
+        })
+    }
+
+    /// Exits the current frame.
+    fn exit(&mut self, ret: InstructionResult, remaining_gas: &Gas, out: &Bytes) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = frame
+            .gas
+            .saturating_sub(U64::from(remaining_gas.remaining()));
+        frame.output = out.clone();
+        if !is_success(ret) {
+            frame.error = Some(error_message(ret));
+        }
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+    ) -> (InstructionResult, Gas, Bytes) {
+        let (call_type, value) = match inputs.context.scheme {
+            CallScheme::Call => ("CALL", Some(inputs.transfer.value)),
+            CallScheme::CallCode => ("CALLCODE", Some(inputs.transfer.value)),
+            CallScheme::DelegateCall => ("DELEGATECALL", None),
+            CallScheme::StaticCall => ("STATICCALL", None),
+        };
+        self.stack.push(CallFrame {
+            call_type: call_type.to_string(),
+            from: inputs.transfer.source,
+            to: Some(inputs.contract),
+            value,
+            gas: U64::from(inputs.gas_limit),
+            gas_used: U64::ZERO,
+            input: inputs.input.clone(),
+            output: Bytes::new(),
+            error: None,
+            calls: vec![],
+        });
+
+        (InstructionResult::Continue, Gas::new(0), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: InstructionResult,
+        out: Bytes,
+    ) -> (InstructionResult, Gas, Bytes) {
+        self.exit(ret, &remaining_gas, &out);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (InstructionResult, Option<Address>, Gas, Bytes) {
+        let call_type = match inputs.scheme {
+            CreateScheme::Create => "CREATE",
+            CreateScheme::Create2 { .. } => "CREATE2",
+        };
+        self.stack.push(CallFrame {
+            call_type: call_type.to_string(),
+            from: inputs.caller,
+            to: None,
+            value: Some(inputs.value),
+            gas: U64::from(inputs.gas_limit),
+            gas_used: U64::ZERO,
+            input: inputs.init_code.clone(),
+            output: Bytes::new(),
+            error: None,
+            calls: vec![],
+        });
+
+        (InstructionResult::Continue, None, Gas::new(0), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: InstructionResult,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (InstructionResult, Option<Address>, Gas, Bytes) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.to = address;
+        }
+        self.exit(ret, &remaining_gas, &out);
+        (ret, address, remaining_gas, out)
+    }
+}
@@ -14,8 +14,7 @@
 
 use core::mem;
 
-use anyhow::{bail, Result};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use revm::primitives::{AccountInfo, Bytecode, B256};
 use zeth_primitives::{
     keccak::{keccak, KECCAK_EMPTY},
@@ -25,7 +24,7 @@ use zeth_primitives::{
 };
 
 use crate::{
-    block_builder::BlockBuilder,
+    block_builder::{BlockBuildError, BlockBuilder},
     consts::MAX_BLOCK_HASH_AGE,
     guest_mem_forget,
     mem_db::{AccountState, DbAccount, MemDb},
@@ -36,7 +35,7 @@ pub trait DbInitStrategy<E: TxEssence> {
 
     fn initialize_database(
         block_builder: BlockBuilder<Self::Database, E>,
-    ) -> Result<BlockBuilder<Self::Database, E>>;
+    ) -> Result<BlockBuilder<Self::Database, E>, BlockBuildError>;
 }
 
 pub struct MemDbInitStrategy {}
@@ -46,16 +45,15 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
 
     fn initialize_database(
         mut block_builder: BlockBuilder<Self::Database, E>,
-    ) -> Result<BlockBuilder<Self::Database, E>> {
+    ) -> Result<BlockBuilder<Self::Database, E>, BlockBuildError> {
         // Verify state trie root
         if block_builder.input.parent_state_trie.hash()
             != block_builder.input.parent_header.state_root
         {
-            bail!(
-                "Invalid state trie: expected {}, got {}",
-                block_builder.input.parent_header.state_root,
-                block_builder.input.parent_state_trie.hash()
-            );
+            return Err(BlockBuildError::StateRootMismatch {
+                expected: block_builder.input.parent_header.state_root,
+                found: block_builder.input.parent_state_trie.hash(),
+            });
         }
 
         // hash all the contract code
@@ -66,6 +64,7 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
 
         // Load account data into db
         let mut accounts = HashMap::with_capacity(block_builder.input.parent_storage.len());
+        let mut referenced_contracts = HashSet::new();
         for (address, (storage_trie, slots)) in &mut block_builder.input.parent_storage {
             // consume the slots, as they are no longer needed afterwards
             let slots = mem::take(slots);
@@ -74,16 +73,16 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
             let state_account = block_builder
                 .input
                 .parent_state_trie
-                .get_rlp::<StateAccount>(&keccak(address))?
+                .get_rlp::<StateAccount>(&keccak(address))
+                .map_err(|err| BlockBuildError::from_trie(*address, err))?
                 .unwrap_or_default();
             // Verify storage trie root
             if storage_trie.hash() != state_account.storage_root {
-                bail!(
-                    "Invalid storage trie for {:?}: expected {}, got {}",
-                    address,
-                    state_account.storage_root,
-                    storage_trie.hash()
-                );
+                return Err(BlockBuildError::StorageRootMismatch {
+                    address: *address,
+                    expected: state_account.storage_root,
+                    found: storage_trie.hash(),
+                });
             }
 
             // load the corresponding code
@@ -91,7 +90,11 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
             let bytecode = if code_hash.0 == KECCAK_EMPTY.0 {
                 Bytecode::new()
             } else {
-                let bytes = contracts.get(&code_hash).unwrap().clone();
+                let bytes = contracts
+                    .get(&code_hash)
+                    .ok_or(BlockBuildError::MissingWitness(*address))?
+                    .clone();
+                referenced_contracts.insert(code_hash);
                 Bytecode::new_raw(bytes)
             };
 
@@ -99,7 +102,8 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
             let mut storage = HashMap::with_capacity(slots.len());
             for slot in slots {
                 let value: zeth_primitives::U256 = storage_trie
-                    .get_rlp(&keccak(slot.to_be_bytes::<32>()))?
+                    .get_rlp(&keccak(slot.to_be_bytes::<32>()))
+                    .map_err(|err| BlockBuildError::from_trie(*address, err))?
                     .unwrap_or_default();
                 storage.insert(slot, value);
             }
@@ -117,6 +121,12 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
 
             accounts.insert(*address, mem_account);
         }
+        // keep the code not contained in the parent state, as it may be deployed by the block
+        block_builder.input.contracts = contracts
+            .iter()
+            .filter(|(code_hash, _)| !referenced_contracts.contains(*code_hash))
+            .map(|(_, bytes)| bytes.clone())
+            .collect();
         guest_mem_forget(contracts);
 
         // prepare block hash history
@@ -129,21 +139,15 @@ impl<E: TxEssence> DbInitStrategy<E> for MemDbInitStrategy {
         let mut prev = &block_builder.input.parent_header;
         for current in &block_builder.input.ancestor_headers {
             let current_hash = current.hash();
+            // each ancestor must be the parent of the previous one
             if prev.parent_hash != current_hash {
-                bail!(
-                    "Invalid chain: {} is not the parent of {}",
-                    current.number,
-                    prev.number
-                );
+                return Err(BlockBuildError::InvalidAncestor(current.number));
             }
+            // and it must be one of the most recent blocks
             if block_builder.input.parent_header.number < current.number
                 || block_builder.input.parent_header.number - current.number >= MAX_BLOCK_HASH_AGE
             {
-                bail!(
-                    "Invalid chain: {} is not one of the {} most recent blocks",
-                    current.number,
-                    MAX_BLOCK_HASH_AGE,
-                );
+                return Err(BlockBuildError::InvalidAncestor(current.number));
             }
             block_hashes.insert(current.number, current_hash);
             prev = current;
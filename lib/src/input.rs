@@ -18,10 +18,11 @@ use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 use zeth_primitives::{
     block::Header,
+    receipt::Receipt,
     transactions::{Transaction, TxEssence},
     trie::MptNode,
     withdrawal::Withdrawal,
-    Address, Bytes, B256, U256,
+    Address, Bytes, B256, B64, U256,
 };
 
 /// External block input.
@@ -39,10 +40,16 @@ pub struct Input<E: TxEssence> {
     pub extra_data: Bytes,
     /// Hash previously used for the PoW now containing the RANDAO value.
     pub mix_hash: B256,
+    /// Proof-of-work nonce, always zero after the Paris update.
+    pub nonce: B64,
+    /// Hash tree root of the parent beacon block, present after the Cancun update.
+    pub parent_beacon_block_root: Option<B256>,
     /// List of transactions for execution
     pub transactions: Vec<Transaction<E>>,
     /// List of stake withdrawals for execution
     pub withdrawals: Vec<Withdrawal>,
+    /// List of ommer headers, always empty after the Paris update.
+    pub ommers: Vec<Header>,
     /// State trie of the parent block.
     pub parent_state_trie: MptNode,
     /// Maps each address with its storage trie and the used storage slots.
@@ -51,10 +58,29 @@ pub struct Input<E: TxEssence> {
     pub contracts: Vec<Bytes>,
     /// List of at most 256 previous block headers
     pub ancestor_headers: Vec<Header>,
+    /// Ommers of the parent and of each ancestor header, only required to validate the
+    /// ommers of a block before the Paris update.
+    pub ancestor_ommers: Vec<Vec<Header>>,
+    /// Header of the L1 origin of an OP-stack block, ignored by other networks.
+    pub l1_origin: Option<Header>,
+    /// Receipts of all transactions of the L1 origin, only required at the start of an
+    /// epoch to derive the deposits.
+    pub l1_receipts: Vec<Receipt>,
 }
 
 pub type StorageEntry = (MptNode, Vec<U256>);
 
+/// External input for building several consecutive blocks in one session.
+///
+/// Only the first block input contains the parent header and the state witness, which
+/// must cover all the blocks. In the following inputs these fields are ignored, as they
+/// are carried over from the previous block.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MultiBlockInput<E: TxEssence> {
+    /// Inputs of the consecutive blocks in ascending order.
+    pub blocks: Vec<Input<E>>,
+}
+
 #[cfg(test)]
 mod tests {
     use zeth_primitives::transactions::ethereum::EthereumTxEssence;
@@ -70,12 +96,18 @@ mod tests {
             timestamp: Default::default(),
             extra_data: Default::default(),
             mix_hash: Default::default(),
+            nonce: Default::default(),
+            parent_beacon_block_root: Default::default(),
             transactions: vec![],
             withdrawals: vec![],
+            ommers: vec![],
             parent_state_trie: Default::default(),
             parent_storage: Default::default(),
             contracts: vec![],
             ancestor_headers: vec![],
+            ancestor_ommers: vec![],
+            l1_origin: None,
+            l1_receipts: vec![],
         };
         let _: Input<EthereumTxEssence> =
             bincode::deserialize(&bincode::serialize(&input).unwrap()).unwrap();
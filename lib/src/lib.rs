@@ -19,11 +19,19 @@ pub mod host;
 
 pub mod block_builder;
 pub mod consts;
+pub mod derivation;
+#[cfg(feature = "ethash")]
+pub mod ethash;
 pub mod execution;
 pub mod finalization;
+pub mod fraud_proof;
+#[cfg(not(target_os = "zkvm"))]
+pub mod genesis;
 pub mod initialization;
 pub mod input;
 pub mod mem_db;
+pub mod optimism;
+pub mod output;
 pub mod preparation;
 
 pub use zeth_primitives::transactions::ethereum::EthereumTxEssence;
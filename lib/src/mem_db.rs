@@ -21,6 +21,8 @@ use revm::{
 use thiserror::Error as ThisError;
 use zeth_primitives::{Address, B256, U256};
 
+use crate::block_builder::BlockBuildError;
+
 /// Error returned by the [MemDb].
 #[derive(Debug, ThisError)]
 pub enum DbError {
@@ -38,6 +40,18 @@ pub enum DbError {
     Unspecified(#[from] anyhow::Error),
 }
 
+impl From<DbError> for BlockBuildError {
+    fn from(err: DbError) -> Self {
+        match err {
+            DbError::AccountNotFound(address) | DbError::SlotNotFound(address, _) => {
+                BlockBuildError::MissingWitness(address)
+            }
+            DbError::BlockNotFound(block_no) => BlockBuildError::MissingBlockHash(block_no),
+            DbError::Unspecified(err) => BlockBuildError::Unspecified(err),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum AccountState {
     // Account can be cleared/removed from state.
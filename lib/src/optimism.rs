@@ -0,0 +1,851 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! L1 attributes and deposits of OP-stack blocks, which link each L2 block to its L1
+//! origin, and the output roots committing to the resulting L2 state.
+
+use core::fmt::Debug;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use revm::Database;
+use ruint::{aliases::U256, uint};
+use zeth_primitives::{
+    address, b256,
+    keccak::keccak,
+    receipt::{Log, Receipt},
+    signature::TxSignature,
+    transactions::{
+        ethereum::TransactionKind,
+        optimism::{OptimismTxEssence, TxEssenceOptimismDeposited},
+        Transaction, TxEssence,
+    },
+    Address, Bytes, B256,
+};
+
+use crate::consts::OptimismConfig;
+
+/// Storage slot of the packed L1 block number and timestamp in the L1 block predeploy.
+pub(crate) const L1_NUMBER_SLOT: U256 = uint!(0_U256);
+/// Storage slot of the L1 base fee.
+pub(crate) const L1_BASE_FEE_SLOT: U256 = uint!(1_U256);
+/// Storage slot of the L1 block hash.
+pub(crate) const L1_HASH_SLOT: U256 = uint!(2_U256);
+/// Storage slot of the sequence number, packed with the fee scalars after Ecotone.
+pub(crate) const L1_SEQUENCE_NUMBER_SLOT: U256 = uint!(3_U256);
+/// Storage slot of the batcher hash.
+pub(crate) const L1_BATCHER_HASH_SLOT: U256 = uint!(4_U256);
+/// Storage slot of the Bedrock fee overhead.
+pub(crate) const L1_FEE_OVERHEAD_SLOT: U256 = uint!(5_U256);
+/// Storage slot of the Bedrock fee scalar.
+pub(crate) const L1_FEE_SCALAR_SLOT: U256 = uint!(6_U256);
+/// Storage slot of the L1 blob base fee, set after Ecotone.
+pub(crate) const L1_BLOB_BASE_FEE_SLOT: U256 = uint!(7_U256);
+
+/// Address of the create2deployer, which is deployed in the Canyon activation block.
+pub const CREATE2_DEPLOYER_ADDRESS: Address = address!("13b0D85CcB8bf860b6b79AF3029fCA081AE9beF2");
+/// Code hash of the create2deployer.
+pub const CREATE2_DEPLOYER_CODE_HASH: B256 =
+    b256!("b0550b5b431e30d38000efb7107aaa0ade03d48a7198a140edda9d27134468b2");
+
+/// Keccak hash of the init code of the Ecotone `L1Block` implementation.
+const L1_BLOCK_ECOTONE_INIT_CODE_HASH: B256 =
+    b256!("c88a313aa75dc4fbf0b6850d9f9ae41e04243b7008cf3eadb29256d4a71c1dfd");
+/// Keccak hash of the init code of the Ecotone `GasPriceOracle` implementation.
+const GAS_PRICE_ORACLE_ECOTONE_INIT_CODE_HASH: B256 =
+    b256!("8b71360ea773b4cfaf1ae6d2bd15464a4e1e2e360f786e475f63aeaed8da0ae5");
+/// Keccak hash of the init code of the EIP-4788 beacon block roots contract.
+const BEACON_ROOTS_INIT_CODE_HASH: B256 =
+    b256!("468e991a328ab315e08296896adc222230a4960692e90cb6e096006ba6ae75d5");
+
+/// Version of the output roots computed by [output_root].
+pub const OUTPUT_ROOT_VERSION: B256 = B256::ZERO;
+
+/// Transaction type of deposits.
+pub(crate) const DEPOSIT_TX_TYPE: u8 = 0x7E;
+
+/// Number of network upgrade deposits in the Ecotone activation block.
+pub const ECOTONE_UPGRADE_DEPOSITS: usize = 6;
+
+/// Topic of the `TransactionDeposited(address,address,uint256,bytes)` event.
+const TRANSACTION_DEPOSITED_TOPIC: B256 =
+    b256!("b3813568d9991fc951961fcb4c784893574240a28925604d09fc577c55bb7c32");
+
+/// Topic of the `ConfigUpdate(uint256,uint8,bytes)` event.
+const CONFIG_UPDATE_TOPIC: B256 =
+    b256!("1d2b0bda21d56b8bd12d4f94ebacffdfb35f5e226f84b461103bb8beab6353be");
+
+/// Selector of `setL1BlockValues`, which sets the L1 attributes before Ecotone.
+const SET_L1_BLOCK_VALUES_SELECTOR: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+/// Selector of `setL1BlockValuesEcotone`, which sets the packed L1 attributes.
+const SET_L1_BLOCK_VALUES_ECOTONE_SELECTOR: [u8; 4] = [0x44, 0x0a, 0x5e, 0x20];
+
+/// L1 attributes set by the first transaction of each OP-stack block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct L1BlockInfo {
+    /// Number of the L1 origin.
+    pub number: u64,
+    /// Timestamp of the L1 origin.
+    pub timestamp: u64,
+    /// Base fee of the L1 origin.
+    pub base_fee: U256,
+    /// Hash of the L1 origin.
+    pub hash: B256,
+    /// Number of the L2 block within the epoch of the L1 origin.
+    pub sequence_number: u64,
+    /// Versioned hash of the batch submitter.
+    pub batcher_hash: B256,
+    /// Fee overhead, only set before Ecotone.
+    pub fee_overhead: U256,
+    /// Fee scalar, only set before Ecotone.
+    pub fee_scalar: U256,
+    /// Scalar of the L1 base fee, only set after Ecotone.
+    pub base_fee_scalar: u32,
+    /// Scalar of the L1 blob base fee, only set after Ecotone.
+    pub blob_base_fee_scalar: u32,
+}
+
+impl L1BlockInfo {
+    /// Decodes the call data of the L1 attributes deposit. The attributes are ABI encoded
+    /// before Ecotone and packed after Ecotone.
+    pub fn decode_calldata(data: &[u8], ecotone: bool) -> Result<Self> {
+        let (selector, args) = data.split_at(data.len().min(4));
+        if !ecotone {
+            ensure!(
+                selector == SET_L1_BLOCK_VALUES_SELECTOR,
+                "expected setL1BlockValues"
+            );
+            ensure!(args.len() == 8 * 32, "invalid length: {}", args.len());
+            let word = |i: usize| &args[32 * i..32 * (i + 1)];
+            return Ok(L1BlockInfo {
+                number: decode_u64_word(word(0))?,
+                timestamp: decode_u64_word(word(1))?,
+                base_fee: U256::from_be_bytes::<32>(word(2).try_into().unwrap()),
+                hash: B256::from_slice(word(3)),
+                sequence_number: decode_u64_word(word(4))?,
+                batcher_hash: B256::from_slice(word(5)),
+                fee_overhead: U256::from_be_bytes::<32>(word(6).try_into().unwrap()),
+                fee_scalar: U256::from_be_bytes::<32>(word(7).try_into().unwrap()),
+                ..Default::default()
+            });
+        }
+
+        ensure!(
+            selector == SET_L1_BLOCK_VALUES_ECOTONE_SELECTOR,
+            "expected setL1BlockValuesEcotone"
+        );
+        ensure!(args.len() == 160, "invalid length: {}", args.len());
+        let be_u32 =
+            |offset: usize| u32::from_be_bytes(args[offset..offset + 4].try_into().unwrap());
+        let be_u64 =
+            |offset: usize| u64::from_be_bytes(args[offset..offset + 8].try_into().unwrap());
+        Ok(L1BlockInfo {
+            base_fee_scalar: be_u32(0),
+            blob_base_fee_scalar: be_u32(4),
+            sequence_number: be_u64(8),
+            timestamp: be_u64(16),
+            number: be_u64(24),
+            base_fee: U256::from_be_bytes::<32>(args[32..64].try_into().unwrap()),
+            hash: B256::from_slice(&args[96..128]),
+            batcher_hash: B256::from_slice(&args[128..160]),
+            ..Default::default()
+        })
+    }
+
+    /// Returns the address of the batcher, whose batches are accepted for this epoch.
+    pub fn batcher(&self) -> Address {
+        Address::from_slice(&self.batcher_hash[12..])
+    }
+
+    /// Decodes the L1 attributes of the given transaction, which must be the L1 attributes
+    /// deposit of the chain.
+    pub fn from_deposit<E: TxEssence>(
+        tx: &Transaction<E>,
+        config: &OptimismConfig,
+        ecotone: bool,
+    ) -> Result<Self> {
+        ensure!(
+            tx.essence.tx_type() == DEPOSIT_TX_TYPE
+                && tx.essence.to() == Some(config.l1_block)
+                && tx.recover_from()? == config.l1_info_depositor,
+            "not the L1 attributes deposit"
+        );
+        Self::decode_calldata(tx.essence.data(), ecotone)
+    }
+
+    /// Reads the L1 attributes stored in the L1 block predeploy at `l1_block`.
+    pub fn from_db<D>(db: &mut D, l1_block: Address) -> Result<Self>
+    where
+        D: Database,
+        <D as Database>::Error: Debug,
+    {
+        let mut read = |slot| {
+            db.storage(l1_block, slot)
+                .map_err(|err| anyhow!("failed to read the L1 block attributes: {:?}", err))
+        };
+        // the number and the timestamp are packed as uint64 each, starting with the least
+        // significant bytes
+        let number_and_timestamp = read(L1_NUMBER_SLOT)?;
+        // after Ecotone, the sequence number is packed with the blob base fee scalar and
+        // the base fee scalar
+        let sequence_number = read(L1_SEQUENCE_NUMBER_SLOT)?;
+        let scalars: [u8; 32] = sequence_number.to_be_bytes();
+        Ok(L1BlockInfo {
+            number: number_and_timestamp.as_limbs()[0],
+            timestamp: number_and_timestamp.as_limbs()[1],
+            base_fee: read(L1_BASE_FEE_SLOT)?,
+            hash: read(L1_HASH_SLOT)?.to_be_bytes::<32>().into(),
+            sequence_number: sequence_number.as_limbs()[0],
+            batcher_hash: read(L1_BATCHER_HASH_SLOT)?.to_be_bytes::<32>().into(),
+            fee_overhead: read(L1_FEE_OVERHEAD_SLOT)?,
+            fee_scalar: read(L1_FEE_SCALAR_SLOT)?,
+            base_fee_scalar: u32::from_be_bytes(scalars[16..20].try_into().unwrap()),
+            blob_base_fee_scalar: u32::from_be_bytes(scalars[20..24].try_into().unwrap()),
+        })
+    }
+}
+
+/// Parameters of the L1 system config, which the L2 blocks of each epoch must follow.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemConfig {
+    /// Versioned hash of the batch submitter.
+    pub batcher_hash: B256,
+    /// Fee overhead, no longer used after Ecotone.
+    pub fee_overhead: U256,
+    /// Fee scalar, which is versioned after Ecotone.
+    pub fee_scalar: U256,
+    /// Gas limit of the L2 blocks.
+    pub gas_limit: U256,
+}
+
+impl SystemConfig {
+    /// Returns the base fee scalar and the blob base fee scalar encoded in the fee scalar.
+    pub fn ecotone_scalars(&self) -> Result<(u32, u32)> {
+        let scalar: [u8; 32] = self.fee_scalar.to_be_bytes();
+        let be_u32 =
+            |offset: usize| u32::from_be_bytes(scalar[offset..offset + 4].try_into().unwrap());
+        match scalar[0] {
+            // a Bedrock scalar with a non-empty padding sets the maximum base fee scalar
+            0 if !is_valid_fee_scalar(&scalar) => Ok((u32::MAX, 0)),
+            0 => Ok((be_u32(28), 0)),
+            1 if is_valid_fee_scalar(&scalar) => Ok((be_u32(28), be_u32(24))),
+            1 => bail!("invalid Ecotone scalar padding"),
+            version => bail!("unsupported scalar version: {}", version),
+        }
+    }
+
+    /// Applies the `ConfigUpdate` events that the `system_config` emitted in the given
+    /// receipts of all transactions of the L1 origin.
+    ///
+    /// After Ecotone, as determined by the timestamp of the L1 origin, invalid fee scalars
+    /// are ignored.
+    pub fn update(
+        &mut self,
+        receipts: &[Receipt],
+        system_config: Address,
+        ecotone: bool,
+    ) -> Result<()> {
+        for receipt in receipts.iter().filter(|receipt| receipt.payload.success) {
+            for log in &receipt.payload.logs {
+                if log.address == system_config && log.topics.first() == Some(&CONFIG_UPDATE_TOPIC)
+                {
+                    self.apply_update(log, ecotone)
+                        .context("invalid system config update")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a single `ConfigUpdate` event of version 0.
+    fn apply_update(&mut self, log: &Log, ecotone: bool) -> Result<()> {
+        ensure!(log.topics.len() == 3, "invalid number of topics");
+        ensure!(
+            log.topics[1] == B256::ZERO,
+            "unsupported version: {}",
+            log.topics[1]
+        );
+        let update_type = U256::from_be_bytes::<32>(log.topics[2].0);
+
+        // the update data is ABI encoded as dynamic bytes
+        let data = &log.data;
+        ensure!(
+            data.len() >= 64
+                && U256::from_be_bytes::<32>(data[..32].try_into().unwrap()) == uint!(32_U256)
+                && U256::from_be_bytes::<32>(data[32..64].try_into().unwrap())
+                    == U256::from(data.len() - 64),
+            "invalid data"
+        );
+        let data = &data[64..];
+
+        match u8::try_from(update_type) {
+            // batcher
+            Ok(0) => {
+                ensure!(
+                    data.len() == 32 && data[..12].iter().all(|b| *b == 0),
+                    "invalid batcher address"
+                );
+                self.batcher_hash = B256::from_slice(data);
+            }
+            // fee overhead and scalar
+            Ok(1) => {
+                ensure!(data.len() == 64, "invalid fee config");
+                let fee_overhead = U256::from_be_bytes::<32>(data[..32].try_into().unwrap());
+                let fee_scalar = U256::from_be_bytes::<32>(data[32..].try_into().unwrap());
+                if !ecotone {
+                    self.fee_overhead = fee_overhead;
+                    self.fee_scalar = fee_scalar;
+                } else if is_valid_fee_scalar(&fee_scalar.to_be_bytes()) {
+                    // the overhead is no longer used and invalid scalars are ignored
+                    self.fee_overhead = U256::ZERO;
+                    self.fee_scalar = fee_scalar;
+                }
+            }
+            // gas limit
+            Ok(2) => {
+                ensure!(data.len() == 32, "invalid gas limit");
+                self.gas_limit = U256::from(decode_u64_word(data)?);
+            }
+            // the unsafe block signer is not relevant for the blocks
+            Ok(3) => {}
+            _ => bail!("unsupported update type: {}", update_type),
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether the given versioned fee scalar is valid, i.e. is of a known version and
+/// only uses the bytes of that version.
+fn is_valid_fee_scalar(scalar: &[u8; 32]) -> bool {
+    match scalar[0] {
+        // the base fee scalar
+        0 => scalar[1..28].iter().all(|b| *b == 0),
+        // the blob base fee scalar and the base fee scalar
+        1 => scalar[1..24].iter().all(|b| *b == 0),
+        _ => false,
+    }
+}
+
+/// Decodes an ABI encoded `uint64`.
+fn decode_u64_word(word: &[u8]) -> Result<u64> {
+    let value = U256::try_from_be_slice(word).context("invalid uint64")?;
+    if value > U256::from(u64::MAX) {
+        bail!("invalid uint64: {}", value);
+    }
+    Ok(value.to())
+}
+
+/// Derives the user deposits of an L1 block from the `TransactionDeposited` events, which
+/// the `optimism_portal` emitted in the given receipts of all its transactions.
+pub fn derive_deposits(
+    l1_hash: B256,
+    receipts: &[Receipt],
+    optimism_portal: Address,
+) -> Result<Vec<Transaction<OptimismTxEssence>>> {
+    let mut deposits = Vec::new();
+    // the source hash commits to the index of the event within the whole block
+    let mut log_index = 0u64;
+    for receipt in receipts {
+        for log in &receipt.payload.logs {
+            if receipt.payload.success
+                && log.address == optimism_portal
+                && log.topics.first() == Some(&TRANSACTION_DEPOSITED_TOPIC)
+            {
+                let source_hash = user_deposit_source_hash(l1_hash, log_index);
+                let deposit = decode_deposit(log, source_hash)
+                    .with_context(|| format!("invalid deposit event {}", log_index))?;
+                deposits.push(Transaction {
+                    essence: OptimismTxEssence::OptimismDeposited(deposit),
+                    // deposits are not signed
+                    signature: TxSignature {
+                        v: 0,
+                        r: U256::ZERO,
+                        s: U256::ZERO,
+                    },
+                });
+            }
+            log_index += 1;
+        }
+    }
+    Ok(deposits)
+}
+
+/// Returns the output root of version 0, which commits to the state of an L2 block, to the
+/// withdrawals stored in the L2-to-L1 message passer and to the block hash.
+pub fn output_root(state_root: B256, message_passer_storage_root: B256, block_hash: B256) -> B256 {
+    let mut buf = [0u8; 128];
+    buf[..32].copy_from_slice(OUTPUT_ROOT_VERSION.as_slice());
+    buf[32..64].copy_from_slice(state_root.as_slice());
+    buf[64..96].copy_from_slice(message_passer_storage_root.as_slice());
+    buf[96..].copy_from_slice(block_hash.as_slice());
+    keccak(buf).into()
+}
+
+/// Returns the source hash of the user deposit emitted as the given log of the L1 block.
+fn user_deposit_source_hash(l1_hash: B256, log_index: u64) -> B256 {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(l1_hash.as_slice());
+    buf[32..].copy_from_slice(&U256::from(log_index).to_be_bytes::<32>());
+    let deposit_id = keccak(buf);
+    // the domain of user deposits is zero
+    let mut buf = [0u8; 64];
+    buf[32..].copy_from_slice(&deposit_id);
+    keccak(buf).into()
+}
+
+/// Returns the source hash of the network upgrade deposit with the given `intent`.
+fn upgrade_deposit_source_hash(intent: &str) -> B256 {
+    // the domain of upgrade deposits is two
+    let mut buf = [0u8; 64];
+    buf[31] = 2;
+    buf[32..].copy_from_slice(&keccak(intent));
+    keccak(buf).into()
+}
+
+/// Returns the call data of `upgradeTo(address)`, which updates the implementation of a
+/// predeploy proxy.
+fn upgrade_to_calldata(implementation: Address) -> Bytes {
+    let mut data = vec![0x36, 0x59, 0xcf, 0xe6];
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(implementation.as_slice());
+    data.into()
+}
+
+/// Returns whether `tx` is the `index`-th network upgrade deposit of the Ecotone activation
+/// block, which follow the user deposits.
+///
+/// The deposits are compared by their hash. The init code of the deployments is only
+/// known by its keccak hash, which is checked before the call data is compared.
+pub fn is_ecotone_upgrade_deposit<E: TxEssence>(index: usize, tx: &Transaction<E>) -> bool {
+    let deployment = |init_code_hash: B256| {
+        let data = tx.essence.data();
+        (B256::from(keccak(data)) == init_code_hash).then(|| data.clone())
+    };
+    let (from, to, gas_limit, data, intent) = match index {
+        0 => (
+            address!("4210000000000000000000000000000000000000"),
+            TransactionKind::Create,
+            375_000,
+            deployment(L1_BLOCK_ECOTONE_INIT_CODE_HASH),
+            "Ecotone: L1 Block Deployment",
+        ),
+        1 => (
+            address!("4210000000000000000000000000000000000001"),
+            TransactionKind::Create,
+            1_000_000,
+            deployment(GAS_PRICE_ORACLE_ECOTONE_INIT_CODE_HASH),
+            "Ecotone: Gas Price Oracle Deployment",
+        ),
+        2 => (
+            Address::ZERO,
+            TransactionKind::Call(address!("4200000000000000000000000000000000000015")),
+            50_000,
+            Some(upgrade_to_calldata(address!(
+                "07dbe8500fc591d1852B76feE44d5a05e13097Ff"
+            ))),
+            "Ecotone: L1 Block Proxy Update",
+        ),
+        3 => (
+            Address::ZERO,
+            TransactionKind::Call(address!("420000000000000000000000000000000000000F")),
+            50_000,
+            Some(upgrade_to_calldata(address!(
+                "b528D11cC114E026F138fE568744c6D45ce6Da7A"
+            ))),
+            "Ecotone: Gas Price Oracle Proxy Update",
+        ),
+        4 => (
+            address!("DeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001"),
+            TransactionKind::Call(address!("420000000000000000000000000000000000000F")),
+            80_000,
+            // setEcotone()
+            Some(vec![0x22, 0xb9, 0x0a, 0xb3].into()),
+            "Ecotone: Gas Price Oracle Set Ecotone",
+        ),
+        5 => (
+            address!("0B799C86a49DEeb90402691F1041aa3AF2d3C875"),
+            TransactionKind::Create,
+            250_000,
+            deployment(BEACON_ROOTS_INIT_CODE_HASH),
+            "Ecotone: beacon block roots contract deployment",
+        ),
+        _ => return false,
+    };
+    let Some(data) = data else {
+        return false;
+    };
+    let deposit = Transaction {
+        essence: OptimismTxEssence::OptimismDeposited(TxEssenceOptimismDeposited {
+            source_hash: upgrade_deposit_source_hash(intent),
+            from,
+            to,
+            mint: U256::ZERO,
+            value: U256::ZERO,
+            gas_limit: U256::from(gas_limit),
+            is_system_tx: false,
+            data,
+        }),
+        signature: TxSignature {
+            v: 0,
+            r: U256::ZERO,
+            s: U256::ZERO,
+        },
+    };
+    tx.hash() == deposit.hash()
+}
+
+/// Decodes a `TransactionDeposited` event of version 0.
+fn decode_deposit(log: &Log, source_hash: B256) -> Result<TxEssenceOptimismDeposited> {
+    ensure!(log.topics.len() == 4, "invalid number of topics");
+    let from = Address::from_slice(&log.topics[1][12..]);
+    let to = Address::from_slice(&log.topics[2][12..]);
+    ensure!(
+        log.topics[3] == B256::ZERO,
+        "unsupported version: {}",
+        log.topics[3]
+    );
+
+    // the opaque data is ABI encoded as dynamic bytes
+    let data = &log.data;
+    ensure!(
+        data.len() >= 64
+            && U256::from_be_bytes::<32>(data[..32].try_into().unwrap()) == uint!(32_U256),
+        "invalid offset"
+    );
+    let len = U256::from_be_bytes::<32>(data[32..64].try_into().unwrap());
+    ensure!(
+        len <= U256::from(data.len() - 64),
+        "invalid length: {}",
+        len
+    );
+    let opaque = &data[64..64 + len.to::<usize>()];
+
+    // the data is packed as mint, value, gas limit, creation flag and call data
+    ensure!(opaque.len() >= 73, "invalid opaque data length: {}", len);
+    Ok(TxEssenceOptimismDeposited {
+        source_hash,
+        from,
+        to: if opaque[72] == 1 {
+            TransactionKind::Create
+        } else {
+            TransactionKind::Call(to)
+        },
+        mint: U256::from_be_bytes::<32>(opaque[..32].try_into().unwrap()),
+        value: U256::from_be_bytes::<32>(opaque[32..64].try_into().unwrap()),
+        gas_limit: U256::from(u64::from_be_bytes(opaque[64..72].try_into().unwrap())),
+        is_system_tx: false,
+        data: opaque[73..].to_vec().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{address, hex};
+
+    use super::*;
+
+    #[test]
+    fn decode_bedrock_calldata() {
+        let data = hex::decode(concat!(
+            "015d8eb9",
+            "000000000000000000000000000000000000000000000000000000000114ff5b",
+            "0000000000000000000000000000000000000000000000000000000064d6ab07",
+            "00000000000000000000000000000000000000000000000000000004a817c800",
+            "6f1e2a46f9e0b1b3e3b8e34c1f8c3f1a6a4f8f1b7e0ee2cba0ff9c6b4e3a3cb7",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985",
+            "00000000000000000000000000000000000000000000000000000000000000bc",
+            "00000000000000000000000000000000000000000000000000000000000a6fe0",
+        ))
+        .unwrap();
+
+        let info = L1BlockInfo::decode_calldata(&data, false).unwrap();
+        assert_eq!(
+            info,
+            L1BlockInfo {
+                number: 0x114ff5b,
+                timestamp: 0x64d6ab07,
+                base_fee: U256::from(20_000_000_000u64),
+                hash: b256!("6f1e2a46f9e0b1b3e3b8e34c1f8c3f1a6a4f8f1b7e0ee2cba0ff9c6b4e3a3cb7"),
+                sequence_number: 3,
+                batcher_hash: b256!(
+                    "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985"
+                ),
+                fee_overhead: U256::from(188),
+                fee_scalar: U256::from(684000),
+                ..Default::default()
+            }
+        );
+        // the Ecotone format must not be accepted before Ecotone and vice versa
+        L1BlockInfo::decode_calldata(&data, true).unwrap_err();
+    }
+
+    #[test]
+    fn decode_ecotone_calldata() {
+        let data = hex::decode(concat!(
+            "440a5e20",
+            "00000558",
+            "000c5fc5",
+            "0000000000000002",
+            "0000000065f23e6f",
+            "000000000125d6f0",
+            "0000000000000000000000000000000000000000000000000000000a1f6a5a11",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "3c6e7d3f1b0f7ad2cd39e9e1a8d7b7d14a6a6a4d2d5e8f1b2f0b2a9b8f6d1e0c",
+            "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985",
+        ))
+        .unwrap();
+
+        let info = L1BlockInfo::decode_calldata(&data, true).unwrap();
+        assert_eq!(
+            info,
+            L1BlockInfo {
+                number: 0x125d6f0,
+                timestamp: 0x65f23e6f,
+                base_fee: U256::from(0xa1f6a5a11u64),
+                hash: b256!("3c6e7d3f1b0f7ad2cd39e9e1a8d7b7d14a6a6a4d2d5e8f1b2f0b2a9b8f6d1e0c"),
+                sequence_number: 2,
+                batcher_hash: b256!(
+                    "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985"
+                ),
+                base_fee_scalar: 1368,
+                blob_base_fee_scalar: 810949,
+                ..Default::default()
+            }
+        );
+        L1BlockInfo::decode_calldata(&data, false).unwrap_err();
+    }
+
+    #[test]
+    fn system_config_updates() {
+        let system_config = address!("229047fed2591dbec1eF1118d64F7aF3dB9EB290");
+        let update = |update_type: u8, data: &str| Log {
+            address: system_config,
+            topics: vec![
+                CONFIG_UPDATE_TOPIC,
+                B256::ZERO,
+                B256::with_last_byte(update_type),
+            ],
+            data: hex::decode(data).unwrap().into(),
+        };
+        let batcher = update(
+            0,
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985",
+            ),
+        );
+        let fee_config = update(
+            1,
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "00000000000000000000000000000000000000000000000000000000000000bc",
+                "010000000000000000000000000000000000000000000000000c5fc500000558",
+            ),
+        );
+        let gas_limit = update(
+            2,
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000001c9c380",
+            ),
+        );
+        let receipts = vec![
+            Receipt::new(2, true, U256::from(50000), vec![batcher, gas_limit]),
+            // events of reverted transactions are ignored
+            Receipt::new(2, false, U256::from(50000), vec![fee_config.clone()]),
+        ];
+
+        let mut config = SystemConfig::default();
+        config.update(&receipts, system_config, false).unwrap();
+        assert_eq!(
+            config,
+            SystemConfig {
+                batcher_hash: b256!(
+                    "0000000000000000000000006887246668a3b87f54deb3b94ba47a6f63f32985"
+                ),
+                gas_limit: U256::from(30_000_000),
+                ..Default::default()
+            }
+        );
+        // events of other contracts are ignored
+        let receipts = vec![Receipt::new(2, true, U256::from(50000), vec![fee_config])];
+        config.update(&receipts, Address::ZERO, false).unwrap();
+        assert_eq!(config.fee_scalar, U256::ZERO);
+
+        let mut bedrock = config.clone();
+        bedrock.update(&receipts, system_config, false).unwrap();
+        assert_eq!(bedrock.fee_overhead, U256::from(188));
+        assert_eq!(bedrock.ecotone_scalars().unwrap(), (1368, 810949));
+        // the overhead is no longer used after Ecotone
+        let mut ecotone = config.clone();
+        ecotone.fee_overhead = U256::from(188);
+        ecotone.update(&receipts, system_config, true).unwrap();
+        assert_eq!(ecotone.fee_overhead, U256::ZERO);
+        assert_eq!(ecotone.fee_scalar, bedrock.fee_scalar);
+    }
+
+    #[test]
+    fn ecotone_fee_scalars() {
+        let scalars = |fee_scalar: U256| {
+            SystemConfig {
+                fee_scalar,
+                ..Default::default()
+            }
+            .ecotone_scalars()
+        };
+        assert_eq!(scalars(U256::from(684000)).unwrap(), (684000, 0));
+        // a Bedrock scalar with a non-empty padding
+        assert_eq!(scalars(U256::from(1) << 224).unwrap(), (u32::MAX, 0));
+        let ecotone = (U256::from(1) << 248) | (U256::from(810949) << 32) | U256::from(1368);
+        assert_eq!(scalars(ecotone).unwrap(), (1368, 810949));
+        scalars(ecotone | (U256::from(1) << 192)).unwrap_err();
+        scalars(U256::from(2) << 248).unwrap_err();
+    }
+
+    /// Init code of the EIP-4788 beacon block roots contract.
+    const BEACON_ROOTS_INIT_CODE: &str = "60618060095f395ff33373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500";
+
+    #[test]
+    fn ecotone_upgrade_deposits() {
+        // as given by the Ecotone specification
+        assert_eq!(
+            upgrade_deposit_source_hash("Ecotone: L1 Block Deployment"),
+            b256!("877a6077205782ea15a6dc8699fa5ebcec5e0f4389f09cb8eda09488231346f8")
+        );
+
+        let mut deposit = TxEssenceOptimismDeposited {
+            source_hash: upgrade_deposit_source_hash("Ecotone: Gas Price Oracle Set Ecotone"),
+            from: address!("DeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001"),
+            to: TransactionKind::Call(address!("420000000000000000000000000000000000000F")),
+            mint: U256::ZERO,
+            value: U256::ZERO,
+            gas_limit: U256::from(80_000),
+            is_system_tx: false,
+            data: hex::decode("22b90ab3").unwrap().into(),
+        };
+        let tx = |deposit: &TxEssenceOptimismDeposited| Transaction {
+            essence: OptimismTxEssence::OptimismDeposited(deposit.clone()),
+            signature: TxSignature {
+                v: 0,
+                r: U256::ZERO,
+                s: U256::ZERO,
+            },
+        };
+        assert!(is_ecotone_upgrade_deposit(4, &tx(&deposit)));
+        assert!(!is_ecotone_upgrade_deposit(3, &tx(&deposit)));
+        deposit.mint = U256::from(1);
+        assert!(!is_ecotone_upgrade_deposit(4, &tx(&deposit)));
+
+        // the deployments must contain the expected init code
+        let mut deployment = TxEssenceOptimismDeposited {
+            source_hash: upgrade_deposit_source_hash(
+                "Ecotone: beacon block roots contract deployment",
+            ),
+            from: address!("0B799C86a49DEeb90402691F1041aa3AF2d3C875"),
+            to: TransactionKind::Create,
+            mint: U256::ZERO,
+            value: U256::ZERO,
+            gas_limit: U256::from(250_000),
+            is_system_tx: false,
+            data: hex::decode(BEACON_ROOTS_INIT_CODE).unwrap().into(),
+        };
+        assert!(is_ecotone_upgrade_deposit(5, &tx(&deployment)));
+        let mut code = deployment.data.to_vec();
+        code[20] ^= 0xff;
+        deployment.data = code.into();
+        assert!(!is_ecotone_upgrade_deposit(5, &tx(&deployment)));
+        // the other deployments do not accept arbitrary code either
+        deployment.source_hash = upgrade_deposit_source_hash("Ecotone: L1 Block Deployment");
+        deployment.from = address!("4210000000000000000000000000000000000000");
+        deployment.gas_limit = U256::from(375_000);
+        assert!(!is_ecotone_upgrade_deposit(0, &tx(&deployment)));
+    }
+
+    #[test]
+    fn derive_user_deposits() {
+        let portal = address!("bEb5Fc579115071764c7423A4f12eDde41f106Ed");
+        let deposit_log = Log {
+            address: portal,
+            topics: vec![
+                TRANSACTION_DEPOSITED_TOPIC,
+                b256!("000000000000000000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+                b256!("000000000000000000000000bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"),
+                B256::ZERO,
+            ],
+            data: hex::decode(concat!(
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "000000000000000000000000000000000000000000000000000000000000004d",
+                "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+                "00000000000000000000000000000000000000000000000006f05b59d3b20000",
+                "00000000000186a0",
+                "00",
+                "deadbeef",
+                "00000000000000000000000000000000000000",
+            ))
+            .unwrap()
+            .into(),
+        };
+        let receipts = vec![
+            // unrelated logs still count towards the log index
+            Receipt::new(
+                2,
+                true,
+                U256::from(21000),
+                vec![Log {
+                    address: portal,
+                    ..Default::default()
+                }],
+            ),
+            Receipt::new(2, true, U256::from(90000), vec![deposit_log.clone()]),
+            // events of reverted transactions are ignored
+            Receipt::new(2, false, U256::from(120000), vec![deposit_log]),
+        ];
+        let l1_hash = b256!("3c6e7d3f1b0f7ad2cd39e9e1a8d7b7d14a6a6a4d2d5e8f1b2f0b2a9b8f6d1e0c");
+
+        let deposits = derive_deposits(l1_hash, &receipts, portal).unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(
+            deposits[0].essence,
+            OptimismTxEssence::OptimismDeposited(TxEssenceOptimismDeposited {
+                source_hash: b256!(
+                    "854f2847ed6405bdb384ab8e6101454498c00014e51e24c33899c9bf108519c2"
+                ),
+                from: address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+                to: TransactionKind::Call(address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb")),
+                mint: U256::from(1_000_000_000_000_000_000u64),
+                value: U256::from(500_000_000_000_000_000u64),
+                gas_limit: U256::from(100_000),
+                is_system_tx: false,
+                data: hex::decode("deadbeef").unwrap().into(),
+            })
+        );
+    }
+
+    #[test]
+    fn output_root_v0() {
+        let root = output_root(
+            B256::repeat_byte(0x11),
+            B256::repeat_byte(0x22),
+            B256::repeat_byte(0x33),
+        );
+        assert_eq!(
+            root,
+            b256!("d50bf2ff34ced71be0d2f0be7c2433c6b39d9c3b16c95daf1ed6f24b7578a3b2")
+        );
+    }
+}
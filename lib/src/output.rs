@@ -0,0 +1,150 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Journals committed by the block guests.
+
+use serde::{Deserialize, Serialize};
+use zeth_primitives::{block::Header, BlockHash, BlockNumber, ChainId, B256, U256};
+
+use crate::{consts::ChainSpec, fraud_proof::BlockVerdict};
+
+/// Version of the [BlockOutput] committed by the current guests.
+pub const BLOCK_OUTPUT_VERSION: u32 = 2;
+
+/// Version of the [MultiBlockOutput] committed by the current multi-block guests.
+pub const MULTI_BLOCK_OUTPUT_VERSION: u32 = 1;
+
+/// Version of the [FraudProofOutput] committed by the current fraud-proof guests.
+pub const FRAUD_PROOF_OUTPUT_VERSION: u32 = 1;
+
+/// Summary of a proven block, so that verifiers do not need the header preimage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockOutput {
+    /// Version of the output format.
+    pub version: u32,
+    /// Chain ID of the block.
+    pub chain_id: ChainId,
+    /// Hash of the chain specification used to build the block, see [ChainSpec::hash].
+    pub chain_spec_hash: B256,
+    /// Hash of the parent block.
+    pub parent_hash: BlockHash,
+    /// Hash of the block.
+    pub block_hash: BlockHash,
+    /// Number of the block.
+    pub number: BlockNumber,
+    /// Root of the state trie after the block.
+    pub state_root: B256,
+    /// Root of the receipts trie of the block.
+    pub receipts_root: B256,
+    /// Total gas used by the transactions of the block.
+    pub gas_used: U256,
+}
+
+impl BlockOutput {
+    /// Creates the output of the given block header.
+    pub fn new(chain_spec: &ChainSpec, header: &Header) -> Self {
+        BlockOutput {
+            version: BLOCK_OUTPUT_VERSION,
+            chain_id: chain_spec.chain_id(),
+            chain_spec_hash: chain_spec.hash(),
+            parent_hash: header.parent_hash,
+            block_hash: header.hash(),
+            number: header.number,
+            state_root: header.state_root,
+            receipts_root: header.receipts_root,
+            gas_used: header.gas_used,
+        }
+    }
+}
+
+/// Summary of several consecutive proven blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiBlockOutput {
+    /// Version of the output format.
+    pub version: u32,
+    /// Chain ID of the blocks.
+    pub chain_id: ChainId,
+    /// Hash of the chain specification used to build the blocks, see [ChainSpec::hash].
+    pub chain_spec_hash: B256,
+    /// Hash of the parent of the first block.
+    pub parent_hash: BlockHash,
+    /// Hash of the final block.
+    pub block_hash: BlockHash,
+    /// Number of the final block.
+    pub number: BlockNumber,
+    /// Root of the state trie after the final block.
+    pub state_root: B256,
+}
+
+impl MultiBlockOutput {
+    /// Creates the output of the blocks between the given parent and final header.
+    pub fn new(chain_spec: &ChainSpec, parent_hash: BlockHash, header: &Header) -> Self {
+        MultiBlockOutput {
+            version: MULTI_BLOCK_OUTPUT_VERSION,
+            chain_id: chain_spec.chain_id(),
+            chain_spec_hash: chain_spec.hash(),
+            parent_hash,
+            block_hash: header.hash(),
+            number: header.number,
+            state_root: header.state_root,
+        }
+    }
+}
+
+/// Summary of a proven OP-stack block, including its L2 output root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimismBlockOutput {
+    /// Summary of the block.
+    pub block: BlockOutput,
+    /// Output root of the block, as proposed to the L1 output oracle.
+    pub output_root: B256,
+}
+
+impl OptimismBlockOutput {
+    /// Creates the output of the given block header and its output root.
+    pub fn new(chain_spec: &ChainSpec, header: &Header, output_root: B256) -> Self {
+        OptimismBlockOutput {
+            block: BlockOutput::new(chain_spec, header),
+            output_root,
+        }
+    }
+}
+
+/// Verdict on the validity of a claimed block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FraudProofOutput {
+    /// Version of the output format.
+    pub version: u32,
+    /// Chain ID of the block.
+    pub chain_id: ChainId,
+    /// Hash of the chain specification used to judge the block, see [ChainSpec::hash].
+    pub chain_spec_hash: B256,
+    /// Hash of the claimed block.
+    pub block_hash: BlockHash,
+    /// Verdict on the claimed block.
+    pub verdict: BlockVerdict,
+}
+
+impl FraudProofOutput {
+    /// Creates the output of the verdict on the given claimed header.
+    pub fn new(chain_spec: &ChainSpec, claimed_header: &Header, verdict: BlockVerdict) -> Self {
+        FraudProofOutput {
+            version: FRAUD_PROOF_OUTPUT_VERSION,
+            chain_id: chain_spec.chain_id(),
+            chain_spec_hash: chain_spec.hash(),
+            block_hash: claimed_header.hash(),
+            verdict,
+        }
+    }
+}
@@ -12,19 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::fmt::Debug;
+use core::{fmt::Debug, iter};
 
-use anyhow::{bail, Context, Result};
-use revm::{Database, DatabaseCommit};
-use zeth_primitives::{block::Header, transactions::TxEssence, U256};
+use anyhow::{Context, Result};
+use revm::{
+    primitives::{calc_excess_blob_gas, SpecId},
+    Database, DatabaseCommit,
+};
+use zeth_primitives::{
+    block::{Header, EMPTY_LIST_HASH},
+    keccak::keccak,
+    rlp,
+    transactions::TxEssence,
+    trie::MptNode,
+    uint, RlpBytes, B64, U256,
+};
 
 use crate::{
-    block_builder::BlockBuilder,
-    consts::{Eip1559Constants, GAS_LIMIT_BOUND_DIVISOR, MAX_EXTRA_DATA_BYTES, MIN_GAS_LIMIT, ONE},
+    block_builder::{BlockBuildError, BlockBuilder},
+    consts::{
+        ChainSpec, Eip1559Constants, OptimismFork, DIFFICULTY_BOMB_PERIOD,
+        DIFFICULTY_BOUND_DIVISOR, GAS_LIMIT_BOUND_DIVISOR, INITIAL_BASE_FEE, MAX_EXTRA_DATA_BYTES,
+        MAX_OMMERS, MAX_OMMER_DEPTH, MIN_DIFFICULTY, MIN_GAS_LIMIT, MIN_SPEC_ID, ONE,
+    },
+    input::Input,
+    optimism::{
+        derive_deposits, is_ecotone_upgrade_deposit, L1BlockInfo, SystemConfig, DEPOSIT_TX_TYPE,
+        ECOTONE_UPGRADE_DEPOSITS,
+    },
 };
 
 pub trait HeaderPrepStrategy {
-    fn prepare_header<D, E>(block_builder: BlockBuilder<D, E>) -> Result<BlockBuilder<D, E>>
+    fn prepare_header<D, E>(
+        block_builder: BlockBuilder<D, E>,
+    ) -> Result<BlockBuilder<D, E>, BlockBuildError>
     where
         D: Database + DatabaseCommit,
         <D as Database>::Error: Debug,
@@ -34,109 +55,772 @@ pub trait HeaderPrepStrategy {
 pub struct EthHeaderPrepStrategy {}
 
 impl HeaderPrepStrategy for EthHeaderPrepStrategy {
-    fn prepare_header<D, E>(mut block_builder: BlockBuilder<D, E>) -> Result<BlockBuilder<D, E>>
+    fn prepare_header<D, E>(
+        mut block_builder: BlockBuilder<D, E>,
+    ) -> Result<BlockBuilder<D, E>, BlockBuildError>
     where
         D: Database + DatabaseCommit,
         <D as Database>::Error: Debug,
         E: TxEssence,
     {
-        // Validate gas limit
-        let diff = block_builder
-            .input
-            .parent_header
-            .gas_limit
-            .abs_diff(block_builder.input.gas_limit);
-        let limit = block_builder.input.parent_header.gas_limit / GAS_LIMIT_BOUND_DIVISOR;
-        if diff >= limit {
-            bail!(
-                "Invalid gas limit: expected {} +- {}, got {}",
-                block_builder.input.parent_header.gas_limit,
-                limit,
-                block_builder.input.gas_limit,
-            );
+        let (header, spec_id) = derive_header(&block_builder)?;
+        validate_post_merge(&block_builder.input, spec_id)?;
+        validate_eth_limits(&block_builder, spec_id)?;
+        block_builder.header = Some(header);
+        Ok(block_builder)
+    }
+}
+
+/// Header preparation for proof-of-work blocks before the Paris update.
+///
+/// The difficulty is derived from the parent header and the ommers are validated against
+/// the recent ancestors. The Ethash seals of the block and its ommers, i.e. their
+/// `mix_hash` and `nonce`, are verified once the header is complete, see
+/// [crate::finalization::PowBuildFromMemDbStrategy].
+pub struct PowHeaderPrepStrategy {}
+
+impl HeaderPrepStrategy for PowHeaderPrepStrategy {
+    fn prepare_header<D, E>(
+        mut block_builder: BlockBuilder<D, E>,
+    ) -> Result<BlockBuilder<D, E>, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug,
+        E: TxEssence,
+    {
+        let (mut header, spec_id) = derive_header(&block_builder)?;
+        // Validate protocol version
+        if !(MIN_SPEC_ID..SpecId::MERGE).contains(&spec_id) {
+            return Err(BlockBuildError::UnsupportedSpecId(spec_id));
         }
-        if block_builder.input.gas_limit < MIN_GAS_LIMIT {
-            bail!(
-                "Invalid gas limit: expected >= {}, got {}",
-                MIN_GAS_LIMIT,
-                block_builder.input.gas_limit,
-            );
+        validate_eth_limits(&block_builder, spec_id)?;
+        validate_ommers(&block_builder, header.number)?;
+        // Derive the proof-of-work fields
+        header.difficulty = derive_difficulty(
+            &block_builder.input.parent_header,
+            block_builder.input.timestamp,
+            spec_id,
+        );
+        header.nonce = block_builder.input.nonce;
+        header.ommers_hash = keccak(rlp::encode(&block_builder.input.ommers)).into();
+
+        block_builder.header = Some(header);
+        Ok(block_builder)
+    }
+}
+
+/// Header preparation for OP-stack blocks.
+///
+/// Instead of the Ethereum bounds, the header follows the rollup derivation rules: the
+/// timestamp advances by the block time, the first transaction sets the attributes of an
+/// L1 origin that continues the epoch of the parent or starts the next one, and the mix
+/// hash is the randao of this L1 origin. As the gas limit is set by the system config of
+/// the L1 origin, it can only change at the start of an epoch.
+///
+/// The first block of an epoch must include the user deposits of its L1 origin right after
+/// the L1 attributes deposit. They are derived from the events of the OptimismPortal in
+/// the receipts of the input, which must match the receipts root of the L1 origin.
+pub struct OpHeaderPrepStrategy {}
+
+impl HeaderPrepStrategy for OpHeaderPrepStrategy {
+    fn prepare_header<D, E>(
+        mut block_builder: BlockBuilder<D, E>,
+    ) -> Result<BlockBuilder<D, E>, BlockBuildError>
+    where
+        D: Database + DatabaseCommit,
+        <D as Database>::Error: Debug,
+        E: TxEssence,
+    {
+        let optimism_config = *block_builder
+            .chain_spec
+            .optimism_config()
+            .context("Invalid chain spec: Optimism config missing")?;
+        optimism_config.validate()?;
+        let (header, spec_id) = derive_header(&block_builder)?;
+        validate_post_merge(&block_builder.input, spec_id)?;
+
+        let input = &block_builder.input;
+        let parent = &input.parent_header;
+        // Validate that the extra data is unused
+        if !input.extra_data.is_empty() {
+            return Err(BlockBuildError::InvalidExtraData(input.extra_data.len()));
         }
         // Validate timestamp
-        if block_builder.input.timestamp <= block_builder.input.parent_header.timestamp {
-            bail!(
-                "Invalid timestamp: expected > {}, got {}",
-                block_builder.input.parent_header.timestamp,
-                block_builder.input.timestamp,
+        if input.timestamp != parent.timestamp + U256::from(optimism_config.block_time) {
+            return Err(BlockBuildError::InvalidTimestamp(input.timestamp));
+        }
+
+        // Decode the L1 attributes, which are only packed after the first Ecotone block
+        let ecotone = block_builder.chain_spec.is_optimism_fork_active(
+            OptimismFork::Ecotone,
+            parent.number,
+            parent.timestamp.saturating_to(),
+        );
+        let l1_info = input
+            .transactions
+            .first()
+            .context("missing L1 attributes deposit")
+            .and_then(|tx| L1BlockInfo::from_deposit(tx, &optimism_config, ecotone))
+            .map_err(|err| BlockBuildError::InvalidL1Attributes(err.to_string()))?;
+        if U256::from(l1_info.timestamp) > input.timestamp {
+            return Err(BlockBuildError::InvalidL1Attributes(
+                "L1 origin is ahead of the block".to_string(),
+            ));
+        }
+
+        // Validate the epoch against the L1 attributes of the parent first, as this does
+        // not depend on the L1 origin
+        let db = block_builder
+            .db
+            .as_mut()
+            .expect("Database is not initialized");
+        let parent_info = L1BlockInfo::from_db(db, optimism_config.l1_block)?;
+        let new_epoch = if l1_info.number == parent_info.number {
+            if l1_info.hash != parent_info.hash
+                || Some(l1_info.sequence_number) != parent_info.sequence_number.checked_add(1)
+            {
+                return Err(BlockBuildError::InvalidL1Attributes(
+                    "block does not continue the epoch of the parent".to_string(),
+                ));
+            }
+            false
+        } else if Some(l1_info.number) == parent_info.number.checked_add(1) {
+            if l1_info.sequence_number != 0 {
+                return Err(BlockBuildError::InvalidL1Attributes(
+                    "block does not start the epoch after the parent".to_string(),
+                ));
+            }
+            true
+        } else {
+            return Err(BlockBuildError::InvalidL1Attributes(format!(
+                "L1 origin {} does not follow {}",
+                l1_info.number, parent_info.number
+            )));
+        };
+
+        // Validate the remaining attributes against the L1 origin
+        let l1_origin = input.l1_origin.as_ref().context("L1 origin missing")?;
+        if l1_origin.hash() != l1_info.hash {
+            return Err(BlockBuildError::L1OriginMismatch {
+                expected: l1_info.hash,
+                found: l1_origin.hash(),
+            });
+        }
+        if new_epoch && l1_origin.parent_hash != parent_info.hash {
+            return Err(BlockBuildError::InvalidL1Attributes(
+                "block does not start the epoch after the parent".to_string(),
+            ));
+        }
+        if l1_info.number != l1_origin.number
+            || U256::from(l1_info.timestamp) != l1_origin.timestamp
+            || Some(l1_info.base_fee) != l1_origin.base_fee_per_gas
+        {
+            return Err(BlockBuildError::InvalidL1Attributes(
+                "attributes differ from the L1 origin".to_string(),
+            ));
+        }
+
+        // Validate that the deposits are exactly those of the L1 origin
+        let deposits = if l1_info.sequence_number == 0 {
+            // the complete receipt trie proves that no deposit event is omitted
+            let mut receipt_trie = MptNode::default();
+            for (i, receipt) in input.l1_receipts.iter().enumerate() {
+                receipt_trie
+                    .insert_rlp(&i.to_rlp(), receipt)
+                    .context("failed to insert L1 receipt")?;
+            }
+            if receipt_trie.hash() != l1_origin.receipts_root {
+                return Err(BlockBuildError::L1ReceiptsMismatch {
+                    expected: l1_origin.receipts_root,
+                    found: receipt_trie.hash(),
+                });
+            }
+            derive_deposits(
+                l1_info.hash,
+                &input.l1_receipts,
+                optimism_config.optimism_portal,
+            )?
+        } else {
+            vec![]
+        };
+        // the user deposits directly follow the L1 attributes deposit
+        let mut txs = input.transactions.iter().enumerate().skip(1);
+        for deposit in deposits {
+            match txs.next() {
+                Some((_, tx)) if tx.hash() == deposit.hash() => {}
+                Some((tx_no, _)) => return Err(BlockBuildError::InvalidDeposit { tx_no }),
+                None => {
+                    return Err(BlockBuildError::InvalidDeposit {
+                        tx_no: input.transactions.len(),
+                    })
+                }
+            }
+        }
+        // the network upgrade deposits of the Ecotone activation block follow the user
+        // deposits
+        let ecotone_activation = !ecotone
+            && block_builder.chain_spec.is_optimism_fork_active(
+                OptimismFork::Ecotone,
+                header.number,
+                input.timestamp.saturating_to(),
             );
+        if ecotone_activation {
+            for index in 0..ECOTONE_UPGRADE_DEPOSITS {
+                match txs.next() {
+                    Some((_, tx)) if is_ecotone_upgrade_deposit(index, tx) => {}
+                    Some((tx_no, _)) => return Err(BlockBuildError::InvalidDeposit { tx_no }),
+                    None => {
+                        return Err(BlockBuildError::InvalidDeposit {
+                            tx_no: input.transactions.len(),
+                        })
+                    }
+                }
+            }
         }
-        // Validate extra data
-        let extra_data_bytes = block_builder.input.extra_data.len();
-        if extra_data_bytes >= MAX_EXTRA_DATA_BYTES {
-            bail!(
-                "Invalid extra data: expected <= {}, got {}",
-                MAX_EXTRA_DATA_BYTES,
-                extra_data_bytes,
-            )
+        // there must not be any other deposits
+        if let Some((tx_no, _)) = txs.find(|(_, tx)| tx.essence.tx_type() == DEPOSIT_TX_TYPE) {
+            return Err(BlockBuildError::InvalidDeposit { tx_no });
         }
-        // Derive header
-        block_builder.header = Some(Header {
-            // Initialize fields that we can compute from the parent
-            parent_hash: block_builder.input.parent_header.hash(),
-            number: block_builder
-                .input
-                .parent_header
-                .number
-                .checked_add(1)
-                .context("Invalid block number: too large")?,
-            base_fee_per_gas: derive_base_fee(
-                &block_builder.input.parent_header,
-                block_builder.chain_spec.gas_constants(),
-            )?,
-            // Initialize metadata from input
-            beneficiary: block_builder.input.beneficiary,
-            gas_limit: block_builder.input.gas_limit,
-            timestamp: block_builder.input.timestamp,
-            mix_hash: block_builder.input.mix_hash,
-            extra_data: block_builder.input.extra_data.clone(),
-            // do not fill the remaining fields
-            ..Default::default()
-        });
+
+        // Validate the system config, which is inherited from the parent and only changes
+        // with the L1 origin
+        let parent_ecotone = block_builder.chain_spec.is_optimism_fork_active(
+            OptimismFork::Ecotone,
+            parent.number.saturating_sub(1),
+            parent
+                .timestamp
+                .saturating_to::<u64>()
+                .saturating_sub(optimism_config.block_time),
+        );
+        let mut system_config = SystemConfig {
+            batcher_hash: parent_info.batcher_hash,
+            fee_overhead: parent_info.fee_overhead,
+            // the parent only stores the decoded scalars after the first Ecotone block
+            fee_scalar: if parent_ecotone {
+                (U256::from(1) << 248)
+                    | (U256::from(parent_info.blob_base_fee_scalar) << 32)
+                    | U256::from(parent_info.base_fee_scalar)
+            } else {
+                parent_info.fee_scalar
+            },
+            gas_limit: parent.gas_limit,
+        };
+        if l1_info.sequence_number == 0 {
+            // the L1 receipts have been validated against the L1 origin above
+            let l1_ecotone = block_builder.chain_spec.is_optimism_fork_active(
+                OptimismFork::Ecotone,
+                header.number,
+                l1_info.timestamp,
+            );
+            system_config
+                .update(
+                    &input.l1_receipts,
+                    optimism_config.system_config,
+                    l1_ecotone,
+                )
+                .map_err(|err| BlockBuildError::InvalidL1Attributes(format!("{:#}", err)))?;
+        }
+        if input.gas_limit != system_config.gas_limit {
+            return Err(BlockBuildError::InvalidGasLimit(input.gas_limit));
+        }
+        let fees_match = if ecotone {
+            system_config
+                .ecotone_scalars()
+                .map_err(|err| BlockBuildError::InvalidL1Attributes(err.to_string()))?
+                == (l1_info.base_fee_scalar, l1_info.blob_base_fee_scalar)
+        } else {
+            system_config.fee_overhead == l1_info.fee_overhead
+                && system_config.fee_scalar == l1_info.fee_scalar
+        };
+        if l1_info.batcher_hash != system_config.batcher_hash || !fees_match {
+            return Err(BlockBuildError::InvalidL1Attributes(
+                "attributes differ from the system config".to_string(),
+            ));
+        }
+
+        // Validate the fields inherited from the L1 origin
+        if input.mix_hash != l1_origin.mix_hash {
+            return Err(BlockBuildError::InvalidMixHash(input.mix_hash));
+        }
+        if header.parent_beacon_block_root.is_some()
+            && header.parent_beacon_block_root != l1_origin.parent_beacon_block_root
+        {
+            return Err(BlockBuildError::InvalidL1Attributes(
+                "parent beacon block root differs from the L1 origin".to_string(),
+            ));
+        }
+
+        block_builder.header = Some(header);
         Ok(block_builder)
     }
 }
 
+/// Validates that the proof-of-work fields are unused after the Paris update.
+fn validate_post_merge<E: TxEssence>(
+    input: &Input<E>,
+    spec_id: SpecId,
+) -> Result<(), BlockBuildError> {
+    // Validate protocol version
+    if spec_id < SpecId::MERGE {
+        return Err(BlockBuildError::UnsupportedSpecId(spec_id));
+    }
+    // Validate that the proof-of-work fields are unused
+    if input.nonce != B64::ZERO {
+        return Err(BlockBuildError::InvalidNonce(input.nonce));
+    }
+    if !input.ommers.is_empty() {
+        return Err(BlockBuildError::InvalidOmmers(input.ommers.len()));
+    }
+    Ok(())
+}
+
+/// Validates the gas limit and the extra data against the bounds of the Ethereum protocol.
+fn validate_eth_limits<D, E: TxEssence>(
+    block_builder: &BlockBuilder<D, E>,
+    spec_id: SpecId,
+) -> Result<(), BlockBuildError> {
+    let input = &block_builder.input;
+
+    // Validate gas limit
+    if !is_valid_gas_limit(
+        block_builder.chain_spec,
+        spec_id,
+        &input.parent_header,
+        input.gas_limit,
+    ) {
+        return Err(BlockBuildError::InvalidGasLimit(input.gas_limit));
+    }
+    // Validate extra data
+    let extra_data_bytes = input.extra_data.len();
+    if extra_data_bytes >= MAX_EXTRA_DATA_BYTES {
+        return Err(BlockBuildError::InvalidExtraData(extra_data_bytes));
+    }
+    Ok(())
+}
+
+/// Returns whether the gas limit of a child of `parent` is within the bounds of the
+/// Ethereum protocol.
+fn is_valid_gas_limit(
+    chain_spec: &ChainSpec,
+    spec_id: SpecId,
+    parent: &Header,
+    gas_limit: U256,
+) -> bool {
+    let parent_gas_limit = match chain_spec.gas_constants(spec_id) {
+        // the gas limit of the first London block is relative to the scaled parent limit
+        Some(constants) if spec_id >= SpecId::LONDON && parent.base_fee_per_gas.is_none() => {
+            parent.gas_limit * constants.elasticity_multiplier
+        }
+        _ => parent.gas_limit,
+    };
+    let diff = parent_gas_limit.abs_diff(gas_limit);
+    let limit = parent_gas_limit / GAS_LIMIT_BOUND_DIVISOR;
+    diff < limit && gas_limit >= MIN_GAS_LIMIT
+}
+
+/// Validates the ommers of a proof-of-work block.
+///
+/// As in geth, each ommer must be the child of one of the six ancestors preceding the
+/// parent, it must be neither an ancestor itself nor included by the block or one of its
+/// last seven ancestors, and its header must be valid with respect to its own parent.
+/// This requires the ancestor headers to reach back seven generations, or up to the
+/// genesis, together with the ommers of each of these generations.
+fn validate_ommers<D, E: TxEssence>(
+    block_builder: &BlockBuilder<D, E>,
+    number: u64,
+) -> Result<(), BlockBuildError> {
+    let input = &block_builder.input;
+    let ommers = &input.ommers;
+    if ommers.len() > MAX_OMMERS {
+        return Err(BlockBuildError::InvalidOmmers(ommers.len()));
+    }
+    if ommers.is_empty() {
+        return Ok(());
+    }
+
+    // Collect the recent generations, as far as they exist
+    let generations: Vec<_> = iter::once(&input.parent_header)
+        .chain(&input.ancestor_headers)
+        .take(MAX_OMMER_DEPTH as usize + 1)
+        .collect();
+    let oldest = generations.last().unwrap();
+    if generations.len() <= MAX_OMMER_DEPTH as usize && oldest.number > 0 {
+        return Err(BlockBuildError::MissingAncestors);
+    }
+    if input.ancestor_ommers.len() < generations.len() {
+        return Err(BlockBuildError::MissingAncestors);
+    }
+    let generation_hashes: Vec<_> = generations.iter().map(|header| header.hash()).collect();
+    let mut included = Vec::new();
+    for (generation, generation_ommers) in generations.iter().zip(&input.ancestor_ommers) {
+        if keccak(rlp::encode(generation_ommers)) != generation.ommers_hash.0 {
+            return Err(BlockBuildError::InvalidAncestor(generation.number));
+        }
+        included.extend(generation_ommers.iter().map(Header::hash));
+    }
+
+    for (i, ommer) in ommers.iter().enumerate() {
+        // ommers must be recent ancestors of the block
+        if ommer.number >= number || ommer.number + MAX_OMMER_DEPTH < number {
+            return Err(BlockBuildError::InvalidOmmer(i));
+        }
+        // and must neither be an ancestor nor be included twice
+        let hash = ommer.hash();
+        if generation_hashes.contains(&hash) || included.contains(&hash) {
+            return Err(BlockBuildError::InvalidOmmer(i));
+        }
+        included.push(hash);
+        // their parent must be an ancestor other than the parent of the block
+        let ommer_parent = generations
+            .iter()
+            .zip(&generation_hashes)
+            .skip(1)
+            .find(|(_, hash)| **hash == ommer.parent_hash)
+            .map(|(header, _)| *header)
+            .ok_or(BlockBuildError::InvalidOmmer(i))?;
+        if !is_valid_ommer_header(block_builder.chain_spec, ommer_parent, ommer) {
+            return Err(BlockBuildError::InvalidOmmer(i));
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the header of an ommer, except for its seal, is valid with respect to
+/// its parent.
+fn is_valid_ommer_header(chain_spec: &ChainSpec, parent: &Header, ommer: &Header) -> bool {
+    if ommer.number != parent.number + 1
+        || ommer.timestamp <= parent.timestamp
+        || ommer.extra_data.len() > MAX_EXTRA_DATA_BYTES
+        || ommer.gas_used > ommer.gas_limit
+    {
+        return false;
+    }
+    let Ok(timestamp) = ommer.timestamp.try_into() else {
+        return false;
+    };
+    let Ok(spec_id) = chain_spec.spec_id(ommer.number, timestamp) else {
+        return false;
+    };
+    if !(MIN_SPEC_ID..SpecId::MERGE).contains(&spec_id)
+        || !is_valid_gas_limit(chain_spec, spec_id, parent, ommer.gas_limit)
+    {
+        return false;
+    }
+    let base_fee = match chain_spec.gas_constants(spec_id) {
+        Some(constants) if spec_id >= SpecId::LONDON => derive_base_fee(parent, constants).ok(),
+        _ => None,
+    };
+    ommer.base_fee_per_gas == base_fee
+        && ommer.difficulty == derive_difficulty(parent, ommer.timestamp, spec_id)
+}
+
+/// Validates the input and derives the header fields that are common to all blocks.
+fn derive_header<D, E: TxEssence>(
+    block_builder: &BlockBuilder<D, E>,
+) -> Result<(Header, SpecId), BlockBuildError> {
+    let input = &block_builder.input;
+    let parent = &input.parent_header;
+
+    // Derive the protocol version
+    let number = parent
+        .number
+        .checked_add(1)
+        .ok_or(BlockBuildError::InvalidBlockNumber)?;
+    let timestamp = input
+        .timestamp
+        .try_into()
+        .map_err(|_| BlockBuildError::InvalidTimestamp(input.timestamp))?;
+    let spec_id = block_builder.chain_spec.spec_id(number, timestamp)?;
+    let eip_1559_constants = if spec_id < SpecId::LONDON {
+        None
+    } else {
+        Some(
+            block_builder
+                .chain_spec
+                .gas_constants(spec_id)
+                .context("Invalid chain spec: EIP-1559 constants missing")?,
+        )
+    };
+
+    // Validate timestamp
+    if input.timestamp <= parent.timestamp {
+        return Err(BlockBuildError::InvalidTimestamp(input.timestamp));
+    }
+    // Derive header
+    let header = Header {
+        // Initialize fields that we can compute from the parent
+        parent_hash: parent.hash(),
+        number,
+        base_fee_per_gas: eip_1559_constants
+            .map(|constants| derive_base_fee(parent, constants))
+            .transpose()?,
+        excess_blob_gas: if spec_id < SpecId::CANCUN {
+            None
+        } else {
+            Some(derive_excess_blob_gas(parent)?)
+        },
+        parent_beacon_block_root: if spec_id < SpecId::CANCUN {
+            None
+        } else {
+            Some(
+                input
+                    .parent_beacon_block_root
+                    .ok_or(BlockBuildError::MissingParentBeaconBlockRoot)?,
+            )
+        },
+        // Initialize metadata from input
+        beneficiary: input.beneficiary,
+        gas_limit: input.gas_limit,
+        timestamp: input.timestamp,
+        mix_hash: input.mix_hash,
+        extra_data: input.extra_data.clone(),
+        // do not fill the remaining fields
+        ..Default::default()
+    };
+    Ok((header, spec_id))
+}
+
 /// Base fee for next block. [EIP-1559](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-1559.md) spec
 #[inline(always)]
 pub fn derive_base_fee(parent: &Header, eip_1559_constants: &Eip1559Constants) -> Result<U256> {
+    // the base fee of the first block after the London update is fixed
+    let Some(parent_base_fee) = parent.base_fee_per_gas else {
+        return Ok(INITIAL_BASE_FEE);
+    };
     let parent_gas_target = parent.gas_limit / eip_1559_constants.elasticity_multiplier;
 
     match parent.gas_used.cmp(&parent_gas_target) {
-        std::cmp::Ordering::Equal => Ok(parent.base_fee_per_gas),
+        std::cmp::Ordering::Equal => Ok(parent_base_fee),
 
         std::cmp::Ordering::Greater => {
             let gas_used_delta = parent.gas_used - parent_gas_target;
             let base_fee_delta = ONE
                 .max(
-                    parent.base_fee_per_gas * gas_used_delta
+                    parent_base_fee * gas_used_delta
                         / parent_gas_target
                         / eip_1559_constants.base_fee_change_denominator,
                 )
-                .min(
-                    parent.base_fee_per_gas / eip_1559_constants.base_fee_max_increase_denominator,
-                );
-            Ok(parent.base_fee_per_gas + base_fee_delta)
+                .min(parent_base_fee / eip_1559_constants.base_fee_max_increase_denominator);
+            Ok(parent_base_fee + base_fee_delta)
         }
 
         std::cmp::Ordering::Less => {
             let gas_used_delta = parent_gas_target - parent.gas_used;
-            let base_fee_delta = (parent.base_fee_per_gas * gas_used_delta
+            let base_fee_delta = (parent_base_fee * gas_used_delta
                 / parent_gas_target
                 / eip_1559_constants.base_fee_change_denominator)
-                .min(
-                    parent.base_fee_per_gas / eip_1559_constants.base_fee_max_decrease_denominator,
-                );
-            Ok(parent.base_fee_per_gas - base_fee_delta)
+                .min(parent_base_fee / eip_1559_constants.base_fee_max_decrease_denominator);
+            Ok(parent_base_fee - base_fee_delta)
+        }
+    }
+}
+
+/// Excess blob gas for next block. [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844) spec
+#[inline(always)]
+pub fn derive_excess_blob_gas(parent: &Header) -> Result<U256, BlockBuildError> {
+    // the fields are not present in the header of the last block before the fork
+    let parent_excess_blob_gas = parent.excess_blob_gas.unwrap_or_default();
+    let parent_blob_gas_used = parent.blob_gas_used.unwrap_or_default();
+
+    let excess_blob_gas = calc_excess_blob_gas(
+        parent_excess_blob_gas
+            .try_into()
+            .map_err(|_| BlockBuildError::InvalidBlobGas(parent_excess_blob_gas))?,
+        parent_blob_gas_used
+            .try_into()
+            .map_err(|_| BlockBuildError::InvalidBlobGas(parent_blob_gas_used))?,
+    );
+    Ok(U256::from(excess_blob_gas))
+}
+
+/// Difficulty for next block. [EIP-100](https://eips.ethereum.org/EIPS/eip-100) spec
+pub fn derive_difficulty(parent: &Header, timestamp: U256, spec_id: SpecId) -> U256 {
+    let bound = parent.difficulty / DIFFICULTY_BOUND_DIVISOR;
+    // the difficulty increases if the parent included ommers
+    let ommers_factor = if parent.ommers_hash == EMPTY_LIST_HASH {
+        ONE
+    } else {
+        uint!(2_U256)
+    };
+    let time_factor = (timestamp - parent.timestamp) / uint!(9_U256);
+    let difficulty = if time_factor <= ommers_factor {
+        parent.difficulty + bound * (ommers_factor - time_factor)
+    } else {
+        let decrease = (time_factor - ommers_factor).min(uint!(99_U256));
+        parent.difficulty.saturating_sub(bound * decrease)
+    };
+    let mut difficulty = difficulty.max(MIN_DIFFICULTY);
+
+    // add the exponentially increasing difficulty bomb
+    let fake_block_number = (parent.number + 1).saturating_sub(difficulty_bomb_delay(spec_id));
+    let period_count = fake_block_number / DIFFICULTY_BOMB_PERIOD;
+    if period_count > 1 {
+        difficulty += ONE << (period_count - 2) as usize;
+    }
+
+    difficulty
+}
+
+/// Number of blocks by which the difficulty bomb is delayed in the given protocol version.
+fn difficulty_bomb_delay(spec_id: SpecId) -> u64 {
+    if spec_id >= SpecId::GRAY_GLACIER {
+        11_400_000
+    } else if spec_id >= SpecId::ARROW_GLACIER {
+        10_700_000
+    } else if spec_id >= SpecId::LONDON {
+        9_700_000
+    } else if spec_id >= SpecId::MUIR_GLACIER {
+        9_000_000
+    } else if spec_id >= SpecId::CONSTANTINOPLE {
+        5_000_000
+    } else {
+        3_000_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zeth_primitives::{transactions::ethereum::EthereumTxEssence, Address};
+
+    use super::*;
+    use crate::{consts::ETH_MAINNET_CHAIN_SPEC, mem_db::MemDb};
+
+    /// Returns a valid child of `parent` in the Byzantium era.
+    fn child(parent: &Header, beneficiary: u8) -> Header {
+        let timestamp = parent.timestamp + U256::from(13);
+        Header {
+            parent_hash: parent.hash(),
+            beneficiary: Address::with_last_byte(beneficiary),
+            difficulty: derive_difficulty(parent, timestamp, SpecId::BYZANTIUM),
+            number: parent.number + 1,
+            gas_limit: parent.gas_limit,
+            timestamp,
+            ..Default::default()
         }
     }
+
+    /// Returns the input of a block whose last eight generations have no ommers.
+    fn input(ommers: Vec<Header>) -> Input<EthereumTxEssence> {
+        let mut generations = vec![Header {
+            difficulty: U256::from(2_000_000_000_000_000u64),
+            number: 5_000_000,
+            gas_limit: U256::from(8_000_000),
+            timestamp: U256::from(1_517_000_000),
+            ..Default::default()
+        }];
+        for _ in 0..7 {
+            generations.push(child(generations.last().unwrap(), 0));
+        }
+        generations.reverse();
+
+        Input {
+            parent_header: generations[0].clone(),
+            beneficiary: Default::default(),
+            gas_limit: generations[0].gas_limit,
+            timestamp: generations[0].timestamp + U256::from(13),
+            extra_data: Default::default(),
+            mix_hash: Default::default(),
+            nonce: Default::default(),
+            parent_beacon_block_root: None,
+            transactions: vec![],
+            withdrawals: vec![],
+            ommers,
+            parent_state_trie: Default::default(),
+            parent_storage: Default::default(),
+            contracts: vec![],
+            ancestor_headers: generations[1..].to_vec(),
+            ancestor_ommers: vec![vec![]; MAX_OMMER_DEPTH as usize + 1],
+            l1_origin: None,
+            l1_receipts: vec![],
+        }
+    }
+
+    fn validate(input: Input<EthereumTxEssence>) -> Result<(), BlockBuildError> {
+        let number = input.parent_header.number + 1;
+        let block_builder = BlockBuilder::<MemDb, _>::new(&ETH_MAINNET_CHAIN_SPEC, input);
+        validate_ommers(&block_builder, number)
+    }
+
+    #[test]
+    fn valid_ommers() {
+        let input = input(vec![]);
+        let ommers = vec![
+            child(&input.ancestor_headers[0], 1),
+            child(&input.ancestor_headers[5], 2),
+        ];
+        validate(Input { ommers, ..input }).unwrap();
+    }
+
+    #[test]
+    fn invalid_ommers() {
+        let input = input(vec![]);
+        let ommer = child(&input.ancestor_headers[0], 1);
+        let invalid_ommers = [
+            // a child of the parent
+            child(&input.parent_header, 1),
+            // a child of an ancestor that is too old
+            child(&input.ancestor_headers[6], 1),
+            // an ancestor
+            input.ancestor_headers[0].clone(),
+            // a header with the wrong difficulty
+            Header {
+                difficulty: ommer.difficulty + U256::from(1),
+                ..ommer.clone()
+            },
+            // a header with too much extra data
+            Header {
+                extra_data: vec![0; MAX_EXTRA_DATA_BYTES + 1].into(),
+                ..ommer.clone()
+            },
+        ];
+        for invalid_ommer in invalid_ommers {
+            let input = Input {
+                ommers: vec![invalid_ommer],
+                ..input.clone()
+            };
+            assert!(matches!(
+                validate(input).unwrap_err(),
+                BlockBuildError::InvalidOmmer(0)
+            ));
+        }
+
+        // the same ommer must not be included twice
+        let input = Input {
+            ommers: vec![ommer.clone(), ommer.clone()],
+            ..input
+        };
+        assert!(matches!(
+            validate(input).unwrap_err(),
+            BlockBuildError::InvalidOmmer(1)
+        ));
+    }
+
+    #[test]
+    fn ancestor_ommers() {
+        let input = input(vec![]);
+        let ommer = child(&input.ancestor_headers[0], 1);
+
+        // the ommers of the ancestors are required
+        let mut missing = Input {
+            ommers: vec![ommer.clone()],
+            ..input.clone()
+        };
+        missing.ancestor_ommers.pop();
+        let err = validate(missing).unwrap_err();
+        assert!(matches!(err, BlockBuildError::MissingAncestors));
+        // which does not prove the block to be invalid
+        assert_eq!(err.violation(), None);
+
+        // and must match the ommers hash of each ancestor
+        let mut mismatch = Input {
+            ommers: vec![ommer],
+            ..input
+        };
+        mismatch.ancestor_ommers[1].push(Header::default());
+        let number = mismatch.ancestor_headers[0].number;
+        assert!(matches!(
+            validate(mismatch).unwrap_err(),
+            BlockBuildError::InvalidAncestor(n) if n == number
+        ));
+    }
 }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use alloy_primitives::{b256, Address, BlockHash, BlockNumber, Bloom, Bytes, B256, B64, U256};
+use alloy_rlp::Encodable;
 use alloy_rlp_derive::RlpEncodable;
 use serde::{Deserialize, Serialize};
 
@@ -27,7 +28,7 @@ pub const EMPTY_LIST_HASH: B256 =
 pub struct Header {
     /// Hash of the parent block's header.
     pub parent_hash: BlockHash,
-    /// Unused 256-bit hash, always [EMPTY_LIST_HASH].
+    /// Hash of the list of ommer headers, always [EMPTY_LIST_HASH] after the Paris update.
     pub ommers_hash: B256,
     /// Address that receives the priority fees of each transaction in the block.
     pub beneficiary: Address,
@@ -39,7 +40,7 @@ pub struct Header {
     pub receipts_root: B256,
     /// Bloom filter for log entries in the block.
     pub logs_bloom: Bloom,
-    /// Always set to `0` as it's unused.
+    /// Proof-of-work difficulty of the block, always `0` after the Paris update.
     pub difficulty: U256,
     /// The block number in the chain.
     pub number: BlockNumber,
@@ -53,14 +54,26 @@ pub struct Header {
     pub extra_data: Bytes,
     /// Hash previously used for the PoW now containing the RANDAO value.
     pub mix_hash: B256,
-    /// Unused 64-bit hash, always zero.
+    /// Proof-of-work nonce of the block, always zero after the Paris update.
     pub nonce: B64,
-    /// Base fee paid by all transactions in the block.
-    pub base_fee_per_gas: U256,
+    /// Base fee paid by all transactions in the block. Present after the London update.
+    #[serde(default)]
+    pub base_fee_per_gas: Option<U256>,
     /// Root hash of the trie containing all withdrawals in the block. Present after the
     /// Shanghai update.
     #[serde(default)]
     pub withdrawals_root: Option<B256>,
+    /// Total amount of blob gas consumed by the transactions in the block. Present after
+    /// the Cancun update.
+    #[serde(default)]
+    pub blob_gas_used: Option<U256>,
+    /// Running total of blob gas consumed in excess of the target, prior to the block.
+    /// Present after the Cancun update.
+    #[serde(default)]
+    pub excess_blob_gas: Option<U256>,
+    /// Hash tree root of the parent beacon block. Present after the Cancun update.
+    #[serde(default)]
+    pub parent_beacon_block_root: Option<B256>,
 }
 
 impl Default for Header {
@@ -82,8 +95,11 @@ impl Default for Header {
             extra_data: Bytes::new(),
             mix_hash: B256::ZERO,
             nonce: B64::ZERO,
-            base_fee_per_gas: U256::ZERO,
+            base_fee_per_gas: None,
             withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
         }
     }
 }
@@ -93,6 +109,37 @@ impl Header {
     pub fn hash(&self) -> BlockHash {
         keccak(alloy_rlp::encode(self)).into()
     }
+
+    /// Computes the hash of the block header without its proof-of-work seal, i.e. without
+    /// the `mix_hash` and the `nonce`. This is the header hash sealed by Ethash.
+    pub fn seal_hash(&self) -> B256 {
+        let mut payload = Vec::new();
+        self.parent_hash.encode(&mut payload);
+        self.ommers_hash.encode(&mut payload);
+        self.beneficiary.encode(&mut payload);
+        self.state_root.encode(&mut payload);
+        self.transactions_root.encode(&mut payload);
+        self.receipts_root.encode(&mut payload);
+        self.logs_bloom.encode(&mut payload);
+        self.difficulty.encode(&mut payload);
+        self.number.encode(&mut payload);
+        self.gas_limit.encode(&mut payload);
+        self.gas_used.encode(&mut payload);
+        self.timestamp.encode(&mut payload);
+        self.extra_data.encode(&mut payload);
+        if let Some(base_fee_per_gas) = self.base_fee_per_gas {
+            base_fee_per_gas.encode(&mut payload);
+        }
+
+        let mut out = Vec::with_capacity(payload.len() + 9);
+        alloy_rlp::Header {
+            list: true,
+            payload_length: payload.len(),
+        }
+        .encode(&mut out);
+        out.extend_from_slice(&payload);
+        keccak(out).into()
+    }
 }
 
 #[cfg(test)]
@@ -101,6 +148,41 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn frontier() {
+        // first block after the genesis
+        let value = json!({
+            "parent_hash": "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+            "ommers_hash": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "beneficiary": "0x05a56e2d52c817161883f50c441c3228cfe54d9f",
+            "state_root": "0xd67e4d450343046425ae4271474353857ab860dbc0a1dde64b41b5cd3a532bf3",
+            "transactions_root": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "receipts_root": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "logs_bloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "difficulty": "0x3ff800000",
+            "number": 1,
+            "gas_limit": "0x1388",
+            "gas_used": "0x0",
+            "timestamp": "0x55ba4224",
+            "extra_data": "0x476574682f76312e302e302f6c696e75782f676f312e342e32",
+            "mix_hash": "0x969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f59",
+            "nonce": "0x539bd4979fef1ec4"
+        });
+        let header: Header = serde_json::from_value(value).unwrap();
+
+        // verify that bincode serialization works
+        let _: Header = bincode::deserialize(&bincode::serialize(&header).unwrap()).unwrap();
+
+        assert_eq!(
+            "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6",
+            header.hash().to_string()
+        );
+        assert_eq!(
+            "0x85913a3057ea8bec78cd916871ca73802e77724e014dda65add3405d02240eb7",
+            header.seal_hash().to_string()
+        )
+    }
+
     #[test]
     fn paris() {
         // first block after the Paris network upgrade
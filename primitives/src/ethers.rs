@@ -20,17 +20,21 @@ use ethers_core::types::{
     transaction::eip2930::{
         AccessList as EthersAccessList, AccessListItem as EthersAccessListItem,
     },
-    Block as EthersBlock, Bytes as EthersBytes, Transaction as EthersTransaction,
+    Block as EthersBlock, Bytes as EthersBytes, Log as EthersLog, OtherFields,
+    Transaction as EthersTransaction, TransactionReceipt as EthersReceipt,
     Withdrawal as EthersWithdrawal, H160 as EthersH160, H256 as EthersH256, U256 as EthersU256,
 };
+use serde::de::DeserializeOwned;
 
 use crate::{
     access_list::{AccessList, AccessListItem},
     block::Header,
+    receipt::{Log, Receipt},
     signature::TxSignature,
     transactions::{
         ethereum::{
-            EthereumTxEssence, TransactionKind, TxEssenceEip1559, TxEssenceEip2930, TxEssenceLegacy,
+            EthereumTxEssence, TransactionKind, TxEssenceEip1559, TxEssenceEip2930,
+            TxEssenceEip4844, TxEssenceLegacy,
         },
         optimism::{OptimismTxEssence, TxEssenceOptimismDeposited},
         Transaction, TxEssence,
@@ -62,6 +66,17 @@ pub fn from_ethers_bytes(v: EthersBytes) -> Bytes {
     v.0.into()
 }
 
+/// Deserialize an optional field not natively supported by Ethers.
+fn get_other_field<T: DeserializeOwned>(
+    other: &OtherFields,
+    key: &str,
+) -> anyhow::Result<Option<T>> {
+    other
+        .get_deserialized(key)
+        .transpose()
+        .with_context(|| format!("invalid {}", key))
+}
+
 /// Conversion from `EthersAccessListItem` to the local [AccessListItem].
 impl From<EthersAccessListItem> for AccessListItem {
     fn from(item: EthersAccessListItem) -> Self {
@@ -117,10 +132,11 @@ impl<T> TryFrom<EthersBlock<T>> for Header {
             extra_data: block.extra_data.0.into(),
             mix_hash: block.mix_hash.context("mix_hash missing")?.0.into(),
             nonce: block.nonce.context("nonce missing")?.0.into(),
-            base_fee_per_gas: from_ethers_u256(
-                block.base_fee_per_gas.context("base_fee_per_gas missing")?,
-            ),
+            base_fee_per_gas: block.base_fee_per_gas.map(from_ethers_u256),
             withdrawals_root: block.withdrawals_root.map(from_ethers_h256),
+            blob_gas_used: block.blob_gas_used.map(from_ethers_u256),
+            excess_blob_gas: block.excess_blob_gas.map(from_ethers_u256),
+            parent_beacon_block_root: block.parent_beacon_block_root.map(from_ethers_h256),
         })
     }
 }
@@ -208,6 +224,34 @@ impl TryFrom<EthersTransaction> for EthereumTxEssence {
                 access_list: tx.access_list.context("access_list missing")?.into(),
                 data: tx.input.0.into(),
             }),
+            Some(3) => EthereumTxEssence::Eip4844(TxEssenceEip4844 {
+                chain_id: tx
+                    .chain_id
+                    .context("chain_id missing")?
+                    .try_into()
+                    .map_err(|err| anyhow!("invalid chain_id: {}", err))?,
+                nonce: tx
+                    .nonce
+                    .try_into()
+                    .map_err(|err| anyhow!("invalid nonce: {}", err))?,
+                max_priority_fee_per_gas: from_ethers_u256(
+                    tx.max_priority_fee_per_gas
+                        .context("max_priority_fee_per_gas missing")?,
+                ),
+                max_fee_per_gas: from_ethers_u256(
+                    tx.max_fee_per_gas.context("max_fee_per_gas missing")?,
+                ),
+                gas_limit: from_ethers_u256(tx.gas),
+                to: from_ethers_h160(tx.to.context("to missing")?),
+                value: from_ethers_u256(tx.value),
+                access_list: tx.access_list.context("access_list missing")?.into(),
+                data: tx.input.0.into(),
+                // the blob fields are not natively supported by ethers
+                max_fee_per_blob_gas: get_other_field(&tx.other, "maxFeePerBlobGas")?
+                    .context("maxFeePerBlobGas missing")?,
+                blob_versioned_hashes: get_other_field(&tx.other, "blobVersionedHashes")?
+                    .context("blobVersionedHashes missing")?,
+            }),
             _ => unreachable!(),
         };
         Ok(essence)
@@ -227,9 +271,9 @@ impl TryFrom<EthersTransaction> for OptimismTxEssence {
                 to: tx.to.into(),
                 value: from_ethers_u256(tx.value),
                 data: tx.input.0.into(),
-                source_hash: from_ethers_h256(tx.source_hash.unwrap_or_default()),
+                source_hash: from_ethers_h256(tx.source_hash),
                 mint: from_ethers_u256(tx.mint.unwrap_or_default()),
-                is_system_tx: tx.is_system_tx.unwrap_or_default(),
+                is_system_tx: tx.is_system_tx,
             }),
             _ => OptimismTxEssence::Ethereum(tx.try_into()?),
         };
@@ -254,3 +298,119 @@ impl TryFrom<EthersWithdrawal> for Withdrawal {
         })
     }
 }
+
+/// Conversion from `EthersLog` to the local [Log].
+impl From<EthersLog> for Log {
+    fn from(log: EthersLog) -> Self {
+        Log {
+            address: from_ethers_h160(log.address),
+            topics: log.topics.into_iter().map(from_ethers_h256).collect(),
+            data: from_ethers_bytes(log.data),
+        }
+    }
+}
+
+/// Conversion from `EthersReceipt` to the local [Receipt].
+/// This conversion may fail if certain expected fields are missing.
+impl TryFrom<EthersReceipt> for Receipt {
+    type Error = anyhow::Error;
+
+    fn try_from(receipt: EthersReceipt) -> Result<Self, Self::Error> {
+        let tx_type = receipt
+            .transaction_type
+            .map_or(0, |tx_type| tx_type.as_u64());
+        Ok(Receipt::new(
+            tx_type
+                .try_into()
+                .map_err(|_| anyhow!("invalid transaction type: {}", tx_type))?,
+            receipt.status.context("status missing")?.as_u64() == 1,
+            from_ethers_u256(receipt.cumulative_gas_used),
+            receipt.logs.into_iter().map(Log::from).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::b256;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn cancun_header() {
+        // the hash of the header with the Cancun fields is computed with an independent
+        // implementation
+        let block: EthersBlock<EthersH256> = serde_json::from_value(json!({
+            "parentHash": "0xc2558f8143d5f5acb8382b8cb2b8e2f1a10c8bdfeededad850eaca048ed85d8f",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x388c818ca8b9251b393131c08a736a67ccb19297",
+            "stateRoot": "0x7fd42f5027bc18315b3781e65f19e4c8828fd5c5fce33410f0fb4fea0b65541f",
+            "transactionsRoot": "0x6f235d618461c08943aa5c23cc751310d6177ab8a9b9a7b66ffa637d988680e6",
+            "receiptsRoot": "0xe0ac34bafdd757bcca2dea27a3fc5870dd0836998877e29361c1fc55e19416ec",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "difficulty": "0x0",
+            "number": "0x103e466",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x1c9bfe2",
+            "timestamp": "0x6437306f",
+            "extraData": "0xd883010b05846765746888676f312e32302e32856c696e7578",
+            "mixHash": "0x812ed704cc408c435c7baa6e86296c1ac654a139ae8c4a26d6460742b951d4f9",
+            "nonce": "0x0000000000000000",
+            "baseFeePerGas": "0x42fbae6d5",
+            "withdrawalsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "blobGasUsed": "0x40000",
+            "excessBlobGas": "0x20000",
+            "parentBeaconBlockRoot": "0x0a2b0fa8a9f26bd9b7a0f4d1e1e1c7b0c8e2bd1a4d27d5b0b4d5c9f0e3a8b1c2"
+        }))
+        .unwrap();
+        let header: Header = block.try_into().unwrap();
+
+        assert_eq!(header.blob_gas_used, Some(U256::from(0x40000)));
+        assert_eq!(header.excess_blob_gas, Some(U256::from(0x20000)));
+        assert_eq!(
+            header.hash(),
+            b256!("dfe1fe1f3f765eb5c01874f2562c457f760523413b30091708237e2b99d362c1")
+        );
+    }
+
+    #[test]
+    fn blob_transaction() {
+        // the hash of the transaction is computed with an independent implementation
+        let tx: EthersTransaction = serde_json::from_value(json!({
+            "hash": "0xda77857aaf57711ac8cb932ef35c234aaaabd97f5bd56be3c84067faf66461cd",
+            "type": "0x3",
+            "chainId": "0x1",
+            "nonce": "0x2a",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x00000000000000000000000000000000000000aa",
+            "value": "0x0",
+            "gas": "0x5208",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "maxFeePerGas": "0x4a817c800",
+            "maxFeePerBlobGas": "0x3",
+            "input": "0x",
+            "accessList": [],
+            "blobVersionedHashes": [
+                "0x0111111111111111111111111111111111111111111111111111111111111111",
+                "0x0122222222222222222222222222222222222222222222222222222222222222"
+            ],
+            "v": "0x1",
+            "yParity": "0x1",
+            "r": "0x8a1d2f1c8e3b6a7f9d0c4e5b2a1f3e6d7c8b9a0f1e2d3c4b5a69788776655443",
+            "s": "0x1b2c3d4e5f60718293a4b5c6d7e8f90112233445566778899aabbccddeeff001"
+        }))
+        .unwrap();
+        let tx: Transaction<EthereumTxEssence> = tx.try_into().unwrap();
+
+        let EthereumTxEssence::Eip4844(essence) = &tx.essence else {
+            panic!("not a blob transaction");
+        };
+        assert_eq!(essence.max_fee_per_blob_gas, U256::from(3));
+        assert_eq!(essence.blob_versioned_hashes.len(), 2);
+        assert_eq!(
+            tx.hash(),
+            b256!("da77857aaf57711ac8cb932ef35c234aaaabd97f5bd56be3c84067faf66461cd")
+        );
+    }
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use alloy_primitives::{b256, B256};
-use sha3::{Digest, Keccak256};
+use sha3::{Digest, Keccak256, Keccak512};
 
 /// Represents the Keccak-256 hash of an empty byte slice.
 ///
@@ -36,3 +36,11 @@ pub fn keccak(data: impl AsRef<[u8]>) -> [u8; 32] {
     // std::hint::black_box(sha2::Sha256::digest(&data));
     Keccak256::digest(data).into()
 }
+
+/// Computes the Keccak-512 hash of the provided data, as used by the Ethash proof-of-work.
+#[inline]
+pub fn keccak512(data: impl AsRef<[u8]>) -> [u8; 64] {
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(&Keccak512::digest(data));
+    hash
+}
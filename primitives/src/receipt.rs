@@ -29,7 +29,7 @@ pub struct Log {
 }
 
 /// Payload of a [Receipt].
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, RlpEncodable)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct ReceiptPayload {
     /// Indicates whether the transaction was executed successfully.
     pub success: bool,
@@ -39,6 +39,59 @@ pub struct ReceiptPayload {
     pub logs_bloom: Bloom,
     /// Logs generated during the execution of the transaction.
     pub logs: Vec<Log>,
+    /// Nonce of the sender of an Optimism deposit before its execution. Present after the
+    /// Regolith update, but only encoded together with the receipt version.
+    #[serde(default)]
+    pub deposit_nonce: Option<u64>,
+    /// Version of the Optimism deposit receipt. Present after the Canyon update.
+    #[serde(default)]
+    pub deposit_receipt_version: Option<u64>,
+}
+
+impl ReceiptPayload {
+    /// Returns the length of the RLP payload, i.e. the encoded fields without the list
+    /// header.
+    fn payload_length(&self) -> usize {
+        let mut payload_length = self.success.length()
+            + self.cumulative_gas_used.length()
+            + self.logs_bloom.length()
+            + self.logs.length();
+        if let Some(version) = self.deposit_receipt_version {
+            payload_length += self.deposit_nonce.unwrap_or_default().length() + version.length();
+        }
+        payload_length
+    }
+}
+
+impl Encodable for ReceiptPayload {
+    /// Encodes the payload into the `out` buffer.
+    ///
+    /// Before the Canyon update, the deposit nonce of an Optimism deposit receipt is not
+    /// part of its consensus encoding. It is only encoded together with the receipt
+    /// version, which was introduced by the Canyon update.
+    #[inline]
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        alloy_rlp::Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.success.encode(out);
+        self.cumulative_gas_used.encode(out);
+        self.logs_bloom.encode(out);
+        self.logs.encode(out);
+        if let Some(version) = self.deposit_receipt_version {
+            self.deposit_nonce.unwrap_or_default().encode(out);
+            version.encode(out);
+        }
+    }
+
+    /// Returns the length of the encoded payload in bytes.
+    #[inline]
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
 }
 
 /// Receipt containing result of transaction execution.
@@ -98,9 +151,23 @@ impl Receipt {
                 cumulative_gas_used,
                 logs_bloom,
                 logs,
+                deposit_nonce: None,
+                deposit_receipt_version: None,
             },
         }
     }
+
+    /// Adds the nonce of the sender of an Optimism deposit.
+    pub fn with_deposit_nonce(mut self, deposit_nonce: u64) -> Self {
+        self.payload.deposit_nonce = Some(deposit_nonce);
+        self
+    }
+
+    /// Adds the version of an Optimism deposit receipt.
+    pub fn with_deposit_receipt_version(mut self, deposit_receipt_version: u64) -> Self {
+        self.payload.deposit_receipt_version = Some(deposit_receipt_version);
+        self
+    }
 }
 
 // test vectors from https://github.com/ethereum/go-ethereum/blob/c40ab6af72ce282020d03c33e8273ea9b03d58f6/core/types/receipt_test.go
@@ -209,4 +276,76 @@ mod tests {
 
         assert_eq!(data, expected);
     }
+
+    #[test]
+    fn optimism_deposit() {
+        // the EIP-1559 test vector with the deposit nonce and receipt version appended
+        let expected = hex!("7ef901c78001b9010000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000500000000000000000000000000000000000014000000000000000000000000000000000000000000000000000000000000000000000000000010000080000000000000000000004000000000000000000000000000040000000000000000000000000000800000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000f8bef85d940000000000000000000000000000000000000011f842a0000000000000000000000000000000000000000000000000000000000000deada0000000000000000000000000000000000000000000000000000000000000beef830100fff85d940000000000000000000000000000000000000111f842a0000000000000000000000000000000000000000000000000000000000000deada0000000000000000000000000000000000000000000000000000000000000beef830100ff0101");
+        let receipt = Receipt::new(
+            0x7e,
+            false,
+            U256::from(1),
+            serde_json::from_value(json!([
+                {
+                    "address": "0x0000000000000000000000000000000000000011",
+                    "topics": [
+                        "0x000000000000000000000000000000000000000000000000000000000000dead",
+                        "0x000000000000000000000000000000000000000000000000000000000000beef"
+                    ],
+                    "data": "0x0100ff"
+                },
+                {
+                    "address": "0x0000000000000000000000000000000000000111",
+                    "topics": [
+                        "0x000000000000000000000000000000000000000000000000000000000000dead",
+                        "0x000000000000000000000000000000000000000000000000000000000000beef"
+                    ],
+                    "data": "0x0100ff"
+                }
+            ]))
+            .unwrap(),
+        )
+        .with_deposit_nonce(1)
+        .with_deposit_receipt_version(1);
+        let mut data = vec![];
+        receipt.encode(&mut data);
+
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn optimism_deposit_pre_canyon() {
+        // before Canyon, the deposit nonce is not encoded and the EIP-1559 test vector only
+        // differs in the transaction type
+        let expected = hex!("7ef901c58001b9010000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000500000000000000000000000000000000000014000000000000000000000000000000000000000000000000000000000000000000000000000010000080000000000000000000004000000000000000000000000000040000000000000000000000000000800000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000f8bef85d940000000000000000000000000000000000000011f842a0000000000000000000000000000000000000000000000000000000000000deada0000000000000000000000000000000000000000000000000000000000000beef830100fff85d940000000000000000000000000000000000000111f842a0000000000000000000000000000000000000000000000000000000000000deada0000000000000000000000000000000000000000000000000000000000000beef830100ff");
+        let receipt = Receipt::new(
+            0x7e,
+            false,
+            U256::from(1),
+            serde_json::from_value(json!([
+                {
+                    "address": "0x0000000000000000000000000000000000000011",
+                    "topics": [
+                        "0x000000000000000000000000000000000000000000000000000000000000dead",
+                        "0x000000000000000000000000000000000000000000000000000000000000beef"
+                    ],
+                    "data": "0x0100ff"
+                },
+                {
+                    "address": "0x0000000000000000000000000000000000000111",
+                    "topics": [
+                        "0x000000000000000000000000000000000000000000000000000000000000dead",
+                        "0x000000000000000000000000000000000000000000000000000000000000beef"
+                    ],
+                    "data": "0x0100ff"
+                }
+            ]))
+            .unwrap(),
+        )
+        .with_deposit_nonce(1);
+        let mut data = vec![];
+        receipt.encode(&mut data);
+
+        assert_eq!(data, expected);
+    }
 }
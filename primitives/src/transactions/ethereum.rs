@@ -211,6 +211,45 @@ pub struct TxEssenceEip1559 {
     pub access_list: AccessList,
 }
 
+/// Represents an Ethereum transaction carrying blobs, as detailed in [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844).
+///
+/// The `TxEssenceEip4844` struct extends the fields of an EIP-1559 transaction with a
+/// separate fee market for blob data. Only the versioned hashes of the blobs are part of
+/// the transaction; the blobs themselves are propagated separately and never executed.
+/// Unlike other transaction types, a blob transaction cannot create a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, RlpEncodable)]
+pub struct TxEssenceEip4844 {
+    /// The network's chain ID, ensuring the transaction is valid on the intended chain,
+    /// as introduced in EIP-155.
+    pub chain_id: ChainId,
+    /// A numeric value representing the total number of transactions previously sent by
+    /// the sender.
+    pub nonce: TxNumber,
+    /// The maximum priority fee per unit of gas that the sender is willing to pay to the
+    /// miner.
+    pub max_priority_fee_per_gas: U256,
+    /// The combined maximum fee (base + priority) per unit of gas that the sender is
+    /// willing to pay for the transaction's execution.
+    pub max_fee_per_gas: U256,
+    /// The maximum amount of gas allocated for the transaction's execution.
+    pub gas_limit: U256,
+    /// The 160-bit address of the intended recipient for a message call. Blob
+    /// transactions must always have a recipient.
+    pub to: Address,
+    /// The amount, in Wei, to be transferred to the recipient of the message call.
+    pub value: U256,
+    /// The transaction's payload, represented as a variable-length byte array.
+    pub data: Bytes,
+    /// A list of addresses and storage keys that the transaction will access, aiding in
+    /// gas optimization.
+    pub access_list: AccessList,
+    /// The maximum fee per unit of blob gas that the sender is willing to pay.
+    pub max_fee_per_blob_gas: U256,
+    /// The versioned hashes of the KZG commitments of the blobs carried by the
+    /// transaction.
+    pub blob_versioned_hashes: Vec<B256>,
+}
+
 /// Represents the type of an Ethereum transaction: either a contract creation or a call
 /// to an existing contract.
 ///
@@ -298,6 +337,10 @@ pub enum EthereumTxEssence {
     /// This mechanism aims to improve the predictability of gas fees and enhances the
     /// overall user experience.
     Eip1559(TxEssenceEip1559),
+    /// Represents an Ethereum transaction carrying blobs, as detailed in [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844).
+    /// Blob transactions pay for their data in a separate fee market, while only the
+    /// versioned hashes of the blobs are part of the transaction itself.
+    Eip4844(TxEssenceEip4844),
 }
 
 // Implement the Encodable trait for the TxEssence enum.
@@ -313,6 +356,7 @@ impl Encodable for EthereumTxEssence {
             EthereumTxEssence::Legacy(tx) => tx.encode(out),
             EthereumTxEssence::Eip2930(tx) => tx.encode(out),
             EthereumTxEssence::Eip1559(tx) => tx.encode(out),
+            EthereumTxEssence::Eip4844(tx) => tx.encode(out),
         }
     }
 
@@ -326,6 +370,7 @@ impl Encodable for EthereumTxEssence {
             EthereumTxEssence::Legacy(tx) => tx.length(),
             EthereumTxEssence::Eip2930(tx) => tx.length(),
             EthereumTxEssence::Eip1559(tx) => tx.length(),
+            EthereumTxEssence::Eip4844(tx) => tx.length(),
         }
     }
 }
@@ -342,8 +387,8 @@ impl EthereumTxEssence {
     /// Retrieves the data that should be signed for the transaction essence.
     ///
     /// Depending on the variant of the [EthereumTxEssence] enum, this method prepares the
-    /// appropriate data for signing. For EIP-2930 and EIP-1559 transactions, a specific
-    /// prefix byte is added before the transaction data.
+    /// appropriate data for signing. For EIP-2930, EIP-1559 and EIP-4844 transactions, a
+    /// specific prefix byte is added before the transaction data.
     fn signing_data(&self) -> Vec<u8> {
         match self {
             EthereumTxEssence::Legacy(tx) => {
@@ -363,6 +408,12 @@ impl EthereumTxEssence {
                 tx.encode(&mut buf);
                 buf
             }
+            EthereumTxEssence::Eip4844(tx) => {
+                let mut buf = Vec::with_capacity(tx.length() + 1);
+                buf.push(0x03);
+                tx.encode(&mut buf);
+                buf
+            }
         }
     }
 
@@ -407,11 +458,13 @@ impl TxEssence for EthereumTxEssence {
     /// - `0x00` for Legacy transactions.
     /// - `0x01` for EIP-2930 transactions.
     /// - `0x02` for EIP-1559 transactions.
+    /// - `0x03` for EIP-4844 transactions.
     fn tx_type(&self) -> u8 {
         match self {
             EthereumTxEssence::Legacy(_) => 0x00,
             EthereumTxEssence::Eip2930(_) => 0x01,
             EthereumTxEssence::Eip1559(_) => 0x02,
+            EthereumTxEssence::Eip4844(_) => 0x03,
         }
     }
     /// Retrieves the gas limit set for the transaction.
@@ -423,6 +476,7 @@ impl TxEssence for EthereumTxEssence {
             EthereumTxEssence::Legacy(tx) => tx.gas_limit,
             EthereumTxEssence::Eip2930(tx) => tx.gas_limit,
             EthereumTxEssence::Eip1559(tx) => tx.gas_limit,
+            EthereumTxEssence::Eip4844(tx) => tx.gas_limit,
         }
     }
     /// Retrieves the recipient address of the transaction, if available.
@@ -434,6 +488,16 @@ impl TxEssence for EthereumTxEssence {
             EthereumTxEssence::Legacy(tx) => tx.to.into(),
             EthereumTxEssence::Eip2930(tx) => tx.to.into(),
             EthereumTxEssence::Eip1559(tx) => tx.to.into(),
+            EthereumTxEssence::Eip4844(tx) => Some(tx.to),
+        }
+    }
+    /// Retrieves the input data of the transaction, i.e. the call data or the init code.
+    fn data(&self) -> &Bytes {
+        match self {
+            EthereumTxEssence::Legacy(tx) => &tx.data,
+            EthereumTxEssence::Eip2930(tx) => &tx.data,
+            EthereumTxEssence::Eip1559(tx) => &tx.data,
+            EthereumTxEssence::Eip4844(tx) => &tx.data,
         }
     }
     /// Recovers the Ethereum address of the sender from the transaction's signature.
@@ -472,6 +536,7 @@ impl TxEssence for EthereumTxEssence {
             EthereumTxEssence::Legacy(tx) => tx.payload_length(),
             EthereumTxEssence::Eip2930(tx) => tx._alloy_rlp_payload_length(),
             EthereumTxEssence::Eip1559(tx) => tx._alloy_rlp_payload_length(),
+            EthereumTxEssence::Eip4844(tx) => tx._alloy_rlp_payload_length(),
         }
     }
 
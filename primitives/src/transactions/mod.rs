@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloy_primitives::{Address, TxHash};
+use alloy_primitives::{Address, Bytes, TxHash};
 use alloy_rlp::Encodable;
 use serde::{Deserialize, Serialize};
 
@@ -49,6 +49,7 @@ pub trait TxEssence: Encodable + Clone {
     /// - `0x00` for Legacy transactions.
     /// - `0x01` for EIP-2930 transactions.
     /// - `0x02` for EIP-1559 transactions.
+    /// - `0x03` for EIP-4844 transactions.
     fn tx_type(&self) -> u8;
     /// Retrieves the gas limit set for the transaction.
     ///
@@ -60,6 +61,8 @@ pub trait TxEssence: Encodable + Clone {
     /// For contract creation transactions, this method returns `None` as there's no
     /// recipient address.
     fn to(&self) -> Option<Address>;
+    /// Retrieves the input data of the transaction, i.e. the call data or the init code.
+    fn data(&self) -> &Bytes;
     /// Recovers the Ethereum address of the sender from the transaction's signature.
     ///
     /// This method uses the ECDSA recovery mechanism to derive the sender's public key
@@ -333,6 +336,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn eip4844() {
+        // Blob transaction encoded and signed by an independent implementation
+        let tx = json!({
+                "Eip4844": {
+                  "chain_id": 1,
+                  "nonce": 42,
+                  "max_priority_fee_per_gas": "0x3b9aca00",
+                  "max_fee_per_gas": "0x6fc23ac00",
+                  "gas_limit": "0x5208",
+                  "to": "0xff00000000000000000000000000000000000010",
+                  "value": "0x0",
+                  "data": "0x",
+                  "access_list": [],
+                  "max_fee_per_blob_gas": "0x3b9aca00",
+                  "blob_versioned_hashes": [
+                    "0x01a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5",
+                    "0x015a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"
+                  ]
+                }
+        });
+        let essence: EthereumTxEssence = serde_json::from_value(tx).unwrap();
+        assert_eq!(essence.tx_type(), 0x03);
+
+        let encoded = alloy_rlp::encode(&essence);
+        assert_eq!(encoded.len(), essence.length());
+        assert_eq!(
+            essence.payload_length() + alloy_rlp::length_of_length(essence.payload_length()),
+            encoded.len()
+        );
+
+        let signature: TxSignature = serde_json::from_value(json!({
+            "v": 0,
+            "r": "0xe9af29f450ee1634477fa6cb3f2987d8b8c3b11290380cb7cfd817fd0a44104e",
+            "s": "0x17d5d9a45154f8cda4915d2a2e05619a00f510288f3199ab49f2bd0675bf18c3"
+        }))
+        .unwrap();
+        let transaction = EthereumTransaction { essence, signature };
+
+        // verify that bincode serialization works
+        let _: EthereumTransaction =
+            bincode::deserialize(&bincode::serialize(&transaction).unwrap()).unwrap();
+
+        // the encoding must be prefixed with the EIP-2718 transaction type
+        let encoded = alloy_rlp::encode(&transaction);
+        assert_eq!(encoded.len(), transaction.length());
+        assert_eq!(
+            alloy_primitives::hex::encode(encoded),
+            concat!(
+                "03f8b4012a843b9aca008506fc23ac0082520894ff000000000000000000000000000000",
+                "000000108080c0843b9aca00f842a001a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5",
+                "a5a5a5a5a5a5a5a5a5a5a5a0015a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+                "5a5a5a5a5a5a5a5a80a0e9af29f450ee1634477fa6cb3f2987d8b8c3b11290380cb7cfd8",
+                "17fd0a44104ea017d5d9a45154f8cda4915d2a2e05619a00f510288f3199ab49f2bd0675",
+                "bf18c3",
+            )
+        );
+
+        assert_eq!(
+            "0x9f23dd057cc7bfc1d0f3aca5fb9d801c06302bc7b2bd441f56f72c3cea5c6104",
+            transaction.hash().to_string()
+        );
+        let recovered = transaction.recover_from().unwrap();
+        assert_eq!(
+            "0xcaa9c5006afde1c0eb44c580da53e7fa32ba22f9",
+            recovered.to_string().to_lowercase()
+        );
+    }
+
     #[test]
     fn rlp() {
         // Tx: 0x275631a3549307b2e8c93b18dfcc0fe8aedf0276bb650c28eaa0a8a011d18867
@@ -108,6 +108,13 @@ impl TxEssence for OptimismTxEssence {
         }
     }
 
+    fn data(&self) -> &Bytes {
+        match self {
+            OptimismTxEssence::Ethereum(eth) => eth.data(),
+            OptimismTxEssence::OptimismDeposited(op) => &op.data,
+        }
+    }
+
     fn recover_from(&self, signature: &TxSignature) -> anyhow::Result<Address> {
         match self {
             OptimismTxEssence::Ethereum(eth) => eth.recover_from(signature),
@@ -13,7 +13,8 @@
 // limitations under the License.
 
 use ethers_core::types::{
-    Block, Bloom, Bytes, EIP1186ProofResponse, StorageProof, Transaction, H256, U256,
+    Block, Bloom, Bytes, EIP1186ProofResponse, StorageProof, Transaction, TransactionReceipt, H256,
+    U256,
 };
 use zeth_primitives::U256 as LibU256;
 
@@ -55,13 +56,27 @@ impl Provider for TestProvider {
             extra_data: self.header.extra_data.0.clone().into(),
             mix_hash: Some(self.header.mix_hash.0.into()),
             nonce: Some(self.header.nonce.0.into()),
-            base_fee_per_gas: Some(self.header.base_fee_per_gas.to_be_bytes().into()),
+            base_fee_per_gas: self.header.base_fee_per_gas.map(|f| f.to_be_bytes().into()),
             withdrawals_root: self.header.withdrawals_root.map(|r| r.0.into()),
+            blob_gas_used: self.header.blob_gas_used.map(|g| g.to_be_bytes().into()),
+            excess_blob_gas: self.header.excess_blob_gas.map(|g| g.to_be_bytes().into()),
+            parent_beacon_block_root: self.header.parent_beacon_block_root.map(|r| r.0.into()),
             hash: Some(self.header.hash().0.into()),
             ..Default::default()
         })
     }
 
+    fn get_uncle_block(&mut self, _: &UncleQuery) -> Result<Block<H256>, anyhow::Error> {
+        bail!("uncle blocks are not supported by TestProvider")
+    }
+
+    fn get_block_receipts(
+        &mut self,
+        _: &BlockQuery,
+    ) -> Result<Vec<TransactionReceipt>, anyhow::Error> {
+        bail!("block receipts are not supported by TestProvider")
+    }
+
     fn get_proof(&mut self, query: &ProofQuery) -> Result<EIP1186ProofResponse, anyhow::Error> {
         assert_eq!(query.block_no, self.header.number);
 
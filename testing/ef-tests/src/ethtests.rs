@@ -41,7 +41,7 @@ pub fn read_eth_test(path: PathBuf) -> Vec<EthTestCase> {
 
             let spec: SpecId = json.network.as_str().into();
             // skip tests with an unsupported network version
-            if spec < SpecId::MERGE || spec > SpecId::SHANGHAI {
+            if spec < SpecId::MERGE || spec > SpecId::CANCUN {
                 println!("skipping ({})", json.network);
                 return None;
             }
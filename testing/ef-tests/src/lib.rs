@@ -23,7 +23,7 @@ use zeth_lib::{
     consts::ChainSpec,
     execution::ethereum::EthTxExecStrategy,
     host::{
-        provider::{AccountQuery, BlockQuery, ProofQuery, Provider, StorageQuery},
+        provider::{AccountQuery, BlockQuery, ProofQuery, Provider, StorageQuery, UncleQuery},
         provider_db::ProviderDb,
         Init,
     },
@@ -39,7 +39,8 @@ use zeth_primitives::{
     signature::TxSignature,
     transactions::{
         ethereum::{
-            EthereumTxEssence, TransactionKind, TxEssenceEip1559, TxEssenceEip2930, TxEssenceLegacy,
+            EthereumTxEssence, TransactionKind, TxEssenceEip1559, TxEssenceEip2930,
+            TxEssenceEip4844, TxEssenceLegacy,
         },
         EthereumTransaction,
     },
@@ -148,6 +149,9 @@ pub struct TestHeader {
     pub transactions_trie: B256,
     pub uncle_hash: B256,
     pub withdrawals_root: Option<B256>,
+    pub blob_gas_used: Option<U256>,
+    pub excess_blob_gas: Option<U256>,
+    pub parent_beacon_block_root: Option<B256>,
 }
 
 impl From<TestHeader> for Header {
@@ -168,8 +172,11 @@ impl From<TestHeader> for Header {
             extra_data: header.extra_data,
             mix_hash: header.mix_hash,
             nonce: header.nonce,
-            base_fee_per_gas: header.base_fee_per_gas.unwrap(),
+            base_fee_per_gas: header.base_fee_per_gas,
             withdrawals_root: header.withdrawals_root,
+            blob_gas_used: header.blob_gas_used,
+            excess_blob_gas: header.excess_blob_gas,
+            parent_beacon_block_root: header.parent_beacon_block_root,
         }
     }
 }
@@ -184,6 +191,8 @@ pub struct TestTransaction {
     pub gas_price: Option<U256>,
     pub max_fee_per_gas: Option<U256>,
     pub max_priority_fee_per_gas: Option<U256>,
+    pub max_fee_per_blob_gas: Option<U256>,
+    pub blob_versioned_hashes: Option<Vec<B256>>,
     pub value: U256,
     #[serde_as(as = "NoneAsEmptyString")]
     pub to: Option<Address>,
@@ -227,7 +236,7 @@ impl From<TestTransaction> for EthereumTransaction {
                 data: tx.data,
                 access_list: tx.access_list.unwrap().into(),
             })
-        } else {
+        } else if tx.max_fee_per_blob_gas.is_none() {
             EthereumTxEssence::Eip1559(TxEssenceEip1559 {
                 chain_id: 1,
                 nonce: tx.nonce.try_into().unwrap(),
@@ -242,6 +251,20 @@ impl From<TestTransaction> for EthereumTransaction {
                 data: tx.data,
                 access_list: tx.access_list.unwrap().into(),
             })
+        } else {
+            EthereumTxEssence::Eip4844(TxEssenceEip4844 {
+                chain_id: 1,
+                nonce: tx.nonce.try_into().unwrap(),
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap(),
+                max_fee_per_gas: tx.max_fee_per_gas.unwrap(),
+                gas_limit: tx.gas_limit,
+                to: tx.to.unwrap(),
+                value: tx.value,
+                data: tx.data,
+                access_list: tx.access_list.unwrap().into(),
+                max_fee_per_blob_gas: tx.max_fee_per_blob_gas.unwrap(),
+                blob_versioned_hashes: tx.blob_versioned_hashes.unwrap_or_default(),
+            })
         };
         EthereumTransaction { essence, signature }
     }
@@ -339,14 +362,21 @@ pub fn create_input(
         timestamp: header.timestamp,
         extra_data: header.extra_data.clone(),
         mix_hash: header.mix_hash,
+        nonce: header.nonce,
+        parent_beacon_block_root: header.parent_beacon_block_root,
         transactions: transactions.clone(),
         withdrawals: withdrawals.clone(),
+        ommers: vec![],
         parent_state_trie: Default::default(),
         parent_storage: Default::default(),
         contracts: vec![],
         parent_header: parent_header.clone(),
 
         ancestor_headers: vec![],
+
+        ancestor_ommers: vec![],
+        l1_origin: None,
+        l1_receipts: vec![],
     };
 
     // create and run the block builder once to create the initial DB
@@ -371,8 +401,13 @@ pub fn create_input(
         fini_block: header,
         fini_transactions: transactions,
         fini_withdrawals: withdrawals,
+        fini_ommers: vec![],
         fini_proofs,
         ancestor_headers,
+        ancestor_ommers: vec![],
+        contracts: vec![],
+        l1_origin: None,
+        l1_receipts: vec![],
     }
     .into()
 }